@@ -4,7 +4,7 @@ use hdk::{hash_path::path::Component, prelude::*};
 use crate::entries::{IndexType, StringIndex, WrappedPath};
 use crate::errors::IndexResult;
 use crate::search::get_naivedatetime;
-use crate::utils::find_divergent_time;
+use crate::utils::{find_divergent_time, get_children_paths};
 
 /// Find all paths which exist between from & until timestamps with starting index
 /// This function is executed in BFS maner and will return all paths between from/until bounds
@@ -58,7 +58,7 @@ pub(crate) fn get_next_level_path_bfs(
     index_type: &IndexType,
 ) -> IndexResult<Vec<Path>> {
     //Get the naivedatetime representation for from & until
-    let (from_time, until_time) = match get_naivedatetime(from, until, index_type) {
+    let (from_time, until_time) = match get_naivedatetime(from, until, index_type)? {
         Some(tuple) => tuple,
         None => return Ok(paths),
     };
@@ -66,8 +66,7 @@ pub(crate) fn get_next_level_path_bfs(
     //Iterate over paths and get children for each and only return paths where path is between from & until naivedatetime
     let mut out = vec![];
     for path in paths {
-        let mut lower_paths: Vec<Path> = path
-            .children_paths()?
+        let mut lower_paths: Vec<Path> = get_children_paths(&path)?
             .into_iter()
             .filter_map(|path| {
                 let path_wrapped = WrappedPath(path.clone());
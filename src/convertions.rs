@@ -1,6 +1,6 @@
 use std::convert::{TryFrom, TryInto};
 
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use hdk::{
     hash_path::path::{Component, Path},
     prelude::{SerializedBytes, UnsafeBytes},
@@ -8,6 +8,7 @@ use hdk::{
 
 use crate::entries::{Index, IndexSegment, IndexType, StringIndex, TimeIndex, WrappedPath};
 use crate::errors::{IndexError, IndexResult};
+use crate::utils::{checked_naivedatetime, get_index_for_timestamp};
 use crate::INDEX_DEPTH;
 
 impl TryFrom<Path> for Index {
@@ -27,6 +28,15 @@ impl TryFrom<Path> for Index {
     }
 }
 
+/// Index which contains the given time using the configured MAX_CHUNK_INTERVAL & genesis
+impl TryFrom<DateTime<Utc>> for Index {
+    type Error = IndexError;
+
+    fn try_from(data: DateTime<Utc>) -> IndexResult<Index> {
+        get_index_for_timestamp(data)
+    }
+}
+
 impl TryFrom<Component> for TimeIndex {
     type Error = IndexError;
 
@@ -144,11 +154,9 @@ impl TryInto<NaiveDateTime> for WrappedPath {
     fn try_into(self) -> Result<NaiveDateTime, Self::Error> {
         let data = self.0;
         let path_comps: Vec<Component> = data.into();
-        let nd = NaiveDate::from_ymd(
-            get_time_index_from_components_strict(&path_comps, 1)?.0 as i32,
-            get_time_index_from_components(&path_comps, 2)?.0,
-            get_time_index_from_components(&path_comps, 3)?.0,
-        );
+        let year = get_time_index_from_components_strict(&path_comps, 1)?.0;
+        let month = get_time_index_from_components(&path_comps, 2)?.0;
+        let day = get_time_index_from_components(&path_comps, 3)?.0;
         //Get the path time components that are optionally present
         let hour = if INDEX_DEPTH.contains(&IndexType::Hour) {
             Some(get_time_index_from_components(&path_comps, 4)?.0)
@@ -165,12 +173,17 @@ impl TryInto<NaiveDateTime> for WrappedPath {
         } else {
             None
         };
-        let ndt = nd.and_hms(
+        //Path components are not trusted; values which are not valid times are returned as errors rather than panicking
+        let year =
+            i32::try_from(year).map_err(|_err| IndexError::InvalidTime("Year is out of range"))?;
+        checked_naivedatetime(
+            year,
+            month,
+            day,
             hour.unwrap_or(1) as u32,
             min.unwrap_or(1) as u32,
             second.unwrap_or(1) as u32,
-        );
-        Ok(ndt)
+        )
     }
 }
 
@@ -207,3 +220,72 @@ impl Into<u32> for TimeIndex {
         self.0
     }
 }
+
+mod convertions_tests {
+    #[test]
+    fn test_index_from_path() {
+        use crate::entries::{Index, StringIndex, TimeIndex};
+        use hdk::hash_path::path::{Component, Path};
+        use hdk::prelude::SerializedBytes;
+        use std::convert::TryFrom;
+        use std::time::Duration;
+
+        let index = Index {
+            from: Duration::from_secs(10),
+            until: Duration::from_secs(20),
+        };
+        let root = Component::from(
+            StringIndex(String::from("test_index"))
+                .get_sb()
+                .unwrap()
+                .bytes()
+                .to_owned(),
+        );
+        let year = Component::from(TimeIndex(2021).get_sb().unwrap().bytes().to_owned());
+        let index_component = Component::from(
+            SerializedBytes::try_from(index.clone())
+                .unwrap()
+                .bytes()
+                .to_owned(),
+        );
+
+        let index_path = Path::from(vec![root.clone(), year.clone(), index_component]);
+        assert_eq!(Index::try_from(index_path).unwrap(), index);
+
+        //Paths which do not end in an index component are not indexes
+        let year_path = Path::from(vec![root, year]);
+        assert!(Index::try_from(year_path).is_err());
+        assert!(Index::try_from(Path::from(vec![])).is_err());
+    }
+
+    #[test]
+    fn test_index_from_time_round_trip() {
+        use crate::utils::index_for_timestamp;
+        use chrono::{TimeZone, Utc};
+        use std::time::Duration;
+
+        let genesis = Duration::from_secs(0);
+        for interval in vec![
+            Duration::from_millis(250),
+            Duration::from_secs(10),
+            Duration::from_secs(3600),
+        ] {
+            let time = Utc.timestamp(1_628_776_883, 600_000_000);
+            let index = index_for_timestamp(
+                Duration::new(time.timestamp() as u64, time.timestamp_subsec_nanos()),
+                &interval,
+                &genesis,
+            )
+            .unwrap();
+            let (from, until) = index.window();
+            assert!(from <= time && time < until);
+
+            //Start of window maps back onto the same index
+            let from = Duration::new(from.timestamp() as u64, from.timestamp_subsec_nanos());
+            assert_eq!(
+                index_for_timestamp(from, &interval, &genesis).unwrap(),
+                index
+            );
+        }
+    }
+}
@@ -8,8 +8,10 @@ use std::fmt::Debug;
 use crate::dfs::SearchState;
 use crate::entries::{Index, IndexType, StringIndex, WrappedPath};
 use crate::errors::{IndexError, IndexResult};
-use crate::search::get_naivedatetime;
-use crate::utils::find_divergent_time;
+use crate::search::{get_naivedatetime, sort_by_decoded_key};
+use crate::utils::{
+    find_divergent_time, get_children_paths, get_index_links, get_target_with_retry,
+};
 use crate::{IndexableEntry, Order, DEFAULT_INDEX_DEPTH, INDEX_DEPTH};
 
 pub(crate) fn make_dfs_search<
@@ -105,31 +107,25 @@ pub(crate) fn make_dfs_search<
         if node.0.len() == max_depth_size {
             // debug!("Found node with correct depth, getting index links");
             end_node = next_node;
-            let mut indexes = Path::from(
+            let indexes = get_children_paths(&Path::from(
                 search_state
                     .0
                     .node_weight(end_node.unwrap())
                     .unwrap()
                     .0
                     .clone(),
-            )
-            .children_paths()?;
-            indexes.sort_by(|a, b| {
-                let index_chunk = Index::try_from(a.clone()).unwrap();
-                let index_chunk_b = Index::try_from(b.clone()).unwrap();
-                match order {
-                    Order::Desc => index_chunk_b.from.partial_cmp(&index_chunk.from).unwrap(),
-                    Order::Asc => index_chunk.from.partial_cmp(&index_chunk_b.from).unwrap(),
-                }
-            });
+            ))?;
+            let indexes = sort_by_decoded_key(indexes, order, |index| {
+                Ok(Index::try_from(index.clone())?.from)
+            })?;
             for index in indexes {
                 // debug!(
                 //     "Getting links for path: {:#?}",
                 //     WrappedPath(index.clone())
                 // );
-                let mut links = get_links(index.path_entry_hash()?, link_tag.clone())?
+                let mut links = get_index_links(index.path_entry_hash()?, link_tag.clone(), false)?
                     .into_iter()
-                    .map(|link| match get(link.target, GetOptions::latest())? {
+                    .map(|link| match get_target_with_retry(link.target)? {
                         Some(chunk) => Ok(Some(chunk.entry().to_app_option::<T>()?.ok_or(
                             IndexError::InternalError("Expected element to contain app entry data"),
                         )?)),
@@ -250,33 +246,29 @@ pub(crate) fn make_dfs_search<
 /// is executed in a dfs maner and will choose one path (dependant on order; highest (Order::Desc) or lowest value (Order::Asc))
 /// And then get the next set of paths from the choosen path
 pub(crate) fn get_next_level_path_dfs(
-    mut paths: Vec<Path>,
+    paths: Vec<Path>,
     from: &DateTime<Utc>,
     until: &DateTime<Utc>,
     index_type: &IndexType,
     order: &Order,
 ) -> IndexResult<Vec<Path>> {
     //Get the naivedatetime representation for from & until
-    let (from_time, until_time) = match get_naivedatetime(from, until, index_type) {
+    let (from_time, until_time) = match get_naivedatetime(from, until, index_type)? {
         Some(tuple) => tuple,
         None => return Ok(paths),
     };
 
-    paths.sort_by(|patha, pathb| {
-        let chrono_path_a: NaiveDateTime = WrappedPath(patha.clone()).try_into().unwrap();
-        let chrono_path_b: NaiveDateTime = WrappedPath(pathb.clone()).try_into().unwrap();
-        match order {
-            Order::Desc => chrono_path_a.partial_cmp(&chrono_path_b).unwrap(),
-            Order::Asc => chrono_path_b.partial_cmp(&chrono_path_a).unwrap(),
-        }
-    });
-
-    let chosen_path = paths.pop().unwrap();
+    //Newest path when searching in Order::Desc, oldest in Order::Asc
+    let chosen_path = sort_by_decoded_key(paths, order, |path| -> IndexResult<NaiveDateTime> {
+        WrappedPath(path.clone()).try_into()
+    })?
+    .into_iter()
+    .next()
+    .unwrap();
     // debug!("Got chosen path: {:#?}", WrappedPath(chosen_path.clone()));
 
     //Iterate over paths and get children for each and only return paths where path is between from & until naivedatetime
-    let mut lower_paths: Vec<Path> = chosen_path
-        .children_paths()?
+    let lower_paths: Vec<Path> = get_children_paths(&chosen_path)?
         .into_iter()
         .filter_map(|path| {
             // debug!("Got path in map {:#?}", path);
@@ -305,21 +297,7 @@ pub(crate) fn get_next_level_path_dfs(
             }
         })
         .collect::<IndexResult<Vec<Path>>>()?;
-    lower_paths.sort_by(|a, b| {
-        let path_wrapped = WrappedPath(a.clone());
-        let path_wrapped_b = WrappedPath(b.clone());
-        let chrono_path_a: IndexResult<NaiveDateTime> = path_wrapped.try_into();
-        let chrono_path_b: IndexResult<NaiveDateTime> = path_wrapped_b.try_into();
-        match order {
-            Order::Desc => chrono_path_b
-                .unwrap()
-                .partial_cmp(&chrono_path_a.unwrap())
-                .unwrap(),
-            Order::Asc => chrono_path_a
-                .unwrap()
-                .partial_cmp(&chrono_path_b.unwrap())
-                .unwrap(),
-        }
-    });
-    Ok(lower_paths)
+    sort_by_decoded_key(lower_paths, order, |path| -> IndexResult<NaiveDateTime> {
+        WrappedPath(path.clone()).try_into()
+    })
 }
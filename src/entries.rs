@@ -3,15 +3,28 @@ use std::time::Duration;
 
 use hdk::prelude::*;
 
-#[derive(Clone, SerializedBytes, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
+/// Equality, ordering & hashing of an index are by the time span it represents; so indexes decoded from different paths
+/// or built from different timestamps inside the same chunk will de-duplicate in client collections.
+/// Indexes are ordered chronologically by from, with until as tiebreaker; so can be sorted directly or used as BTreeMap keys.
+/// Ordering relies on field declaration order.
+/// Prefer [`Index::new_aligned()`] over building an index by hand so that indexes are always aligned to chunk interval & genesis
+/// Serialized index is used as a path component; field names & order must never change as this would move every index in the DHT.
+/// For that reason until is stored even though it is always from + MAX_CHUNK_INTERVAL; indexes where it is not are rejected by
+/// validation. Use [`Index::until()`] where the stored value cannot be trusted
+#[derive(Clone, SerializedBytes, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq, Hash)]
 pub struct Index {
     pub from: Duration,
     pub until: Duration,
 }
 
+/// Name of an index; the first component of its time paths. Encoded as a MessagePack string
 #[derive(Clone, SerializedBytes, Debug, Serialize, Deserialize)]
 pub struct StringIndex(pub String);
 
+/// Year, month, day, hour, minute or second value used as a time path component. The component bytes are the MessagePack encoding
+/// of the u32 value: always the smallest MessagePack integer format which fits the value, with multi byte values in big endian order.
+/// This encoding is independent of the platform a zome is built for; it must never change as every client has to derive identical
+/// path hashes
 #[derive(Clone, Eq, PartialEq, SerializedBytes, Debug, Serialize, Deserialize)]
 pub struct TimeIndex(pub u32);
 
@@ -30,3 +43,123 @@ pub enum IndexType {
 }
 
 pub type IndexSegment = (String, Option<NaiveDateTime>, Option<Index>);
+
+mod entries_tests {
+    #[test]
+    fn test_index_wire_format() {
+        use crate::entries::Index;
+        use hdk::prelude::*;
+        use std::convert::TryFrom;
+        use std::time::Duration;
+
+        let index = Index {
+            from: Duration::from_secs(10),
+            until: Duration::from_secs(20),
+        };
+        let bytes = SerializedBytes::try_from(index.clone()).unwrap();
+        let mut golden = vec![0x82, 0xa4];
+        golden.extend(b"from");
+        golden.extend(vec![0x82, 0xa4]);
+        golden.extend(b"secs");
+        golden.push(0x0a);
+        golden.push(0xa5);
+        golden.extend(b"nanos");
+        golden.extend(vec![0x00, 0xa5]);
+        golden.extend(b"until");
+        golden.extend(vec![0x82, 0xa4]);
+        golden.extend(b"secs");
+        golden.push(0x14);
+        golden.push(0xa5);
+        golden.extend(b"nanos");
+        golden.push(0x00);
+        assert_eq!(bytes.bytes().to_owned(), golden);
+
+        //Round trip
+        assert_eq!(Index::try_from(bytes).unwrap(), index);
+    }
+
+    #[test]
+    fn test_time_index_component_encoding() {
+        use crate::entries::{StringIndex, TimeIndex};
+        use hdk::hash_path::path::Component;
+        use std::convert::TryFrom;
+
+        let golden: Vec<(u32, Vec<u8>)> = vec![
+            (0, vec![0x00]),
+            (5, vec![0x05]),
+            (59, vec![0x3b]),
+            (127, vec![0x7f]),
+            (128, vec![0xcc, 0x80]),
+            (2021, vec![0xcd, 0x07, 0xe5]),
+            (65_535, vec![0xcd, 0xff, 0xff]),
+            (65_536, vec![0xce, 0x00, 0x01, 0x00, 0x00]),
+            (u32::MAX, vec![0xce, 0xff, 0xff, 0xff, 0xff]),
+        ];
+        for (value, bytes) in golden {
+            let component = Component::from(TimeIndex(value).get_sb().unwrap().bytes().to_owned());
+            assert_eq!(Vec::<u8>::from(component.clone()), bytes);
+            //Round trip
+            assert_eq!(TimeIndex::try_from(component).unwrap(), TimeIndex(value));
+        }
+
+        let mut golden = vec![0xaa];
+        golden.extend(b"test_index");
+        assert_eq!(
+            StringIndex(String::from("test_index"))
+                .get_sb()
+                .unwrap()
+                .bytes()
+                .to_owned(),
+            golden
+        );
+    }
+
+    #[test]
+    fn test_entry_chunk_index_wire_format() {
+        use crate::entries::Index;
+        use crate::{EntryChunkIndex, ENTRY_CHUNK_INDEX_VERSION};
+        use hdk::prelude::*;
+        use std::convert::TryFrom;
+        use std::time::Duration;
+
+        let index = Index {
+            from: Duration::from_secs(10),
+            until: Duration::from_secs(20),
+        };
+        let chunk = EntryChunkIndex {
+            index: index.clone(),
+            links: vec![],
+            version: ENTRY_CHUNK_INDEX_VERSION,
+        };
+        let bytes = SerializedBytes::try_from(chunk).unwrap();
+        let index_bytes = SerializedBytes::try_from(index.clone()).unwrap();
+        let mut golden = vec![0x83, 0xa5];
+        golden.extend(b"index");
+        golden.extend(index_bytes.bytes());
+        golden.push(0xa5);
+        golden.extend(b"links");
+        golden.extend(vec![0x90, 0xa7]);
+        golden.extend(b"version");
+        golden.push(0x01);
+        assert_eq!(bytes.bytes().to_owned(), golden);
+
+        //Round trip
+        let decoded = EntryChunkIndex::try_from(bytes).unwrap();
+        assert_eq!(decoded.index, index);
+        assert!(decoded.links.is_empty());
+        assert_eq!(decoded.version, ENTRY_CHUNK_INDEX_VERSION);
+
+        //Payloads from before versioning decode as version 0
+        let mut unversioned = vec![0x82, 0xa5];
+        unversioned.extend(b"index");
+        unversioned.extend(index_bytes.bytes());
+        unversioned.push(0xa5);
+        unversioned.extend(b"links");
+        unversioned.push(0x90);
+        let decoded =
+            EntryChunkIndex::try_from(SerializedBytes::from(UnsafeBytes::from(unversioned)))
+                .unwrap();
+        assert_eq!(decoded.index, index);
+        assert_eq!(decoded.version, 0);
+    }
+}
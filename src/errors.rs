@@ -17,6 +17,20 @@ pub enum IndexError {
     // HdkError(#[from] HdkError),
     #[error("Invalid Request Data. Error: {0}")]
     RequestError(&'static str),
+    #[error("Index depth is not compatible with chunk interval. Error: {0}")]
+    DepthIntervalMismatch(&'static str),
+    #[error("Invalid time. Error: {0}")]
+    InvalidTime(&'static str),
+    #[error("Invalid index configuration in DNA properties. Error: {0}")]
+    InvalidConfiguration(&'static str),
+    #[error("System clock is set to a time before the configured genesis")]
+    ClockBeforeGenesis,
+    #[error("Entry to be indexed could not be found")]
+    TargetNotFound,
+    #[error("Agent has reached the link limit for this index")]
+    LinkLimitReached,
+    #[error("Time tree contains a path which does not match the expected shape. Error: {0}")]
+    MalformedPath(&'static str),
 }
 
 pub type IndexResult<T> = Result<T, IndexError>;
@@ -26,3 +40,29 @@ impl From<IndexError> for String {
         format!("{}", e)
     }
 }
+
+/// Allows IndexError to be returned from zome functions using `?`
+impl From<IndexError> for WasmError {
+    fn from(e: IndexError) -> Self {
+        WasmError::Host(String::from(e))
+    }
+}
+
+mod error_tests {
+    #[test]
+    fn test_internal_errors_convert_to_index_error() {
+        use crate::errors::IndexError;
+        use hdk::prelude::*;
+
+        let err = IndexError::from(SerializedBytesError::Deserialize(String::from("bad bytes")));
+        assert!(matches!(err, IndexError::Serialization(_)));
+
+        let err = IndexError::from(WasmError::Host(String::from("host error")));
+        assert!(matches!(err, IndexError::Wasm(_)));
+
+        let err = WasmError::from(IndexError::RequestError("bad request"));
+        assert!(
+            matches!(err, WasmError::Host(msg) if msg == "Invalid Request Data. Error: bad request")
+        );
+    }
+}
@@ -1,10 +1,19 @@
-use chrono::NaiveDateTime;
-use std::{convert::TryInto, ops::Sub};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Utc};
+use std::{collections::HashMap, convert::TryInto, ops::Sub, time::Duration};
 
-use hdk::prelude::SerializedBytes;
+use hdk::prelude::{EntryHash, GetOptions, Link, LinkTag, SerializedBytes};
 
-use crate::entries::{Index, StringIndex, TimeIndex};
-use crate::errors::IndexResult;
+use crate::entries::{Index, IndexType, StringIndex, TimeIndex};
+use crate::errors::{IndexError, IndexResult};
+use crate::utils::{
+    chunk_offset, duration_to_datetime, get_index_for_timestamp, group_links_by_tag,
+    index_depth_for_interval, now_since_epoch, resolve_genesis, sort_links, step_back,
+};
+use crate::validation::{validate_genesis_alignment, validate_index_depth};
+use crate::{
+    Consistency, EntryChunkIndex, IndexConfiguration, IndexSample, Until, GENESIS,
+    MAX_CHUNK_INTERVAL,
+};
 
 /// Helper function to get serializedbytes of StringIndex and make this cleaner in the code
 impl StringIndex {
@@ -20,6 +29,229 @@ impl TimeIndex {
     }
 }
 
+impl IndexType {
+    /// Duration of one unit of time at this depth of the time tree.
+    /// Month & Year do not have a fixed length; here they are approximated as 30 & 365 days respectively.
+    /// Use [`IndexType::granularity_at()`] when the exact calendar length is required
+    pub fn granularity(&self) -> Duration {
+        match self {
+            IndexType::Year => Duration::from_secs(365 * 86400),
+            IndexType::Month => Duration::from_secs(30 * 86400),
+            IndexType::Day => Duration::from_secs(86400),
+            IndexType::Hour => Duration::from_secs(3600),
+            IndexType::Minute => Duration::from_secs(60),
+            IndexType::Second => Duration::from_secs(1),
+        }
+    }
+
+    /// Calendar aware duration of the unit of time at this depth which contains the given timestamp.
+    /// Accounts for variable month lengths & leap years. Errors with InvalidTime if the unit ends beyond the dates chrono can represent
+    pub fn granularity_at(&self, at: &DateTime<Utc>) -> IndexResult<Duration> {
+        let first_of = |year: i32, month: u32| {
+            NaiveDate::from_ymd_opt(year, month, 1)
+                .ok_or(IndexError::InvalidTime("Date is out of range"))
+        };
+        let (start, end) = match self {
+            IndexType::Year => (first_of(at.year(), 1)?, first_of(at.year() + 1, 1)?),
+            IndexType::Month => (
+                first_of(at.year(), at.month())?,
+                if at.month() == 12 {
+                    first_of(at.year() + 1, 1)?
+                } else {
+                    first_of(at.year(), at.month() + 1)?
+                },
+            ),
+            _ => return Ok(self.granularity()),
+        };
+        Ok(Duration::from_secs(
+            end.signed_duration_since(start).num_seconds() as u64,
+        ))
+    }
+}
+
+impl Index {
+    /// Create the index which contains the given time; aligned to the configured MAX_CHUNK_INTERVAL & genesis.
+    /// Preferred over building an Index by hand as the result will always pass alignment validation
+    pub fn new_aligned(containing: DateTime<Utc>) -> IndexResult<Index> {
+        get_index_for_timestamp(containing)
+    }
+
+    /// Number of chunk intervals between genesis and the start of this index; genesis chunk has offset 0
+    pub fn genesis_offset(&self) -> IndexResult<u64> {
+        chunk_offset(self, &MAX_CHUNK_INTERVAL, &GENESIS)
+    }
+
+    /// Does this index end before (or at) the given time
+    pub fn ends_before(&self, time: &DateTime<Utc>) -> bool {
+        self.window().1 <= *time
+    }
+
+    /// Get the index which sits chunks intervals before this index. Errors if that index would start before UNIX epoch or genesis
+    pub fn previous(&self, chunks: u32) -> IndexResult<Index> {
+        step_back(self, chunks, &MAX_CHUNK_INTERVAL, &GENESIS)
+    }
+
+    /// Time window covered by this index as (from, until). Derived from the index itself so no DHT calls are made
+    pub fn window(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        (
+            duration_to_datetime(&self.from),
+            duration_to_datetime(&self.until),
+        )
+    }
+
+    /// Start of this index. Indexes are half open; start is the first instant covered by the index
+    pub fn start(&self) -> DateTime<Utc> {
+        duration_to_datetime(&self.from)
+    }
+
+    /// End of this index. Indexes are half open; end is the first instant not covered by the index and is the start of the next index
+    pub fn end(&self) -> DateTime<Utc> {
+        duration_to_datetime(&self.until)
+    }
+
+    /// Instant halfway between start & end of this index; useful for placing an index on a timeline
+    pub fn midpoint(&self) -> DateTime<Utc> {
+        duration_to_datetime(&(self.from + (self.until - self.from) / 2))
+    }
+
+    /// End of this index derived from its start & the configured MAX_CHUNK_INTERVAL. Unlike the stored until field this can never
+    /// disagree with the chunk interval; prefer it when handling indexes which have not passed validation
+    pub fn until(&self) -> Duration {
+        self.until_for(&MAX_CHUNK_INTERVAL)
+    }
+
+    pub(crate) fn until_for(&self, interval: &Duration) -> Duration {
+        self.from + *interval
+    }
+}
+
+impl From<&Consistency> for GetOptions {
+    fn from(consistency: &Consistency) -> Self {
+        match consistency {
+            Consistency::Cached => GetOptions::content(),
+            Consistency::Latest => GetOptions::latest(),
+        }
+    }
+}
+
+impl From<DateTime<Utc>> for Until {
+    fn from(time: DateTime<Utc>) -> Self {
+        Until::At(time)
+    }
+}
+
+impl Until {
+    /// Resolve upper bound to a time; Until::Now will resolve to the current sys_time()
+    pub fn resolve(self) -> IndexResult<DateTime<Utc>> {
+        match self {
+            Until::Now => Ok(self.resolve_with(now_since_epoch()?)),
+            Until::At(time) => Ok(time),
+        }
+    }
+
+    /// Resolve upper bound to a time using now (as duration since UNIX epoch) as the current time
+    pub(crate) fn resolve_with(self, now: Duration) -> DateTime<Utc> {
+        match self {
+            Until::Now => duration_to_datetime(&now),
+            Until::At(time) => time,
+        }
+    }
+}
+
+impl EntryChunkIndex {
+    /// Link targets of this index grouped by link tag. Targets in each group are in the order links were made
+    pub fn group_by_tag(&self) -> HashMap<LinkTag, Vec<EntryHash>> {
+        group_links_by_tag(self.links.clone())
+    }
+
+    /// Count of links on this index along with the targets of its first sample_size links, in the order links were made
+    pub fn sample(&self, sample_size: usize) -> IndexSample {
+        let mut links = self.links.clone();
+        sort_links(&mut links);
+        IndexSample {
+            index: self.index.clone(),
+            count: links.len(),
+            sample: links
+                .into_iter()
+                .take(sample_size)
+                .map(|link| link.target)
+                .collect(),
+        }
+    }
+
+    /// Merge two results for the same index into one, unioning their links. Links present in both results are only included once.
+    /// Useful when accumulating paged results where a single index has been split across pages
+    pub fn merge(mut self, other: EntryChunkIndex) -> IndexResult<EntryChunkIndex> {
+        if self.index != other.index {
+            return Err(IndexError::RequestError(
+                "Cannot merge results for different indexes",
+            ));
+        };
+        let mut other_links: Vec<Link> = other
+            .links
+            .into_iter()
+            .filter(|link| {
+                !self
+                    .links
+                    .iter()
+                    .any(|existing| existing.create_link_hash == link.create_link_hash)
+            })
+            .collect();
+        self.links.append(&mut other_links);
+        Ok(self)
+    }
+}
+
+impl IndexConfiguration {
+    /// Configured max_chunk_interval as a Duration
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.max_chunk_interval as u64)
+    }
+
+    /// Configured max_chunk_interval in milliseconds
+    pub fn interval_millis(&self) -> i64 {
+        self.interval().as_millis() as i64
+    }
+
+    /// Configured max_chunk_interval in whole seconds; sub second intervals are rounded down
+    pub fn interval_secs(&self) -> i64 {
+        self.interval().as_secs() as i64
+    }
+
+    /// Check configuration is internally consistent. Spam limit, retry attempts, shards & concurrency must be at least one, the chunk interval must be
+    /// non zero & evenly divide the level of the time tree chunks will be hung from and genesis must fall on a chunk interval boundary
+    pub fn validate(&self) -> IndexResult<()> {
+        if self.enforce_spam_limit < 1 {
+            return Err(IndexError::InvalidConfiguration(
+                "enforce_spam_limit must be at least 1",
+            ));
+        };
+        if self.get_retry_attempts == Some(0) {
+            return Err(IndexError::InvalidConfiguration(
+                "get_retry_attempts must be at least 1",
+            ));
+        };
+        if self.index_shards == Some(0) {
+            return Err(IndexError::InvalidConfiguration(
+                "index_shards must be at least 1",
+            ));
+        };
+        if self.get_links_concurrency == Some(0) {
+            return Err(IndexError::InvalidConfiguration(
+                "get_links_concurrency must be at least 1",
+            ));
+        };
+        let interval = self.interval();
+        if interval.as_nanos() == 0 {
+            return Err(IndexError::InvalidConfiguration(
+                "max_chunk_interval must be greater than zero",
+            ));
+        };
+        validate_index_depth(&index_depth_for_interval(&interval), &interval)?;
+        validate_genesis_alignment(&resolve_genesis(self.genesis), &interval)
+    }
+}
+
 impl std::fmt::Debug for Index {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut debug_struct = f.debug_struct("Index");
@@ -37,3 +269,492 @@ impl std::fmt::Debug for Index {
         debug_struct.finish()
     }
 }
+
+mod impl_utils_tests {
+    #[test]
+    fn test_index_type_granularity() {
+        use crate::entries::IndexType;
+        use std::time::Duration;
+
+        assert_eq!(IndexType::Second.granularity(), Duration::from_secs(1));
+        assert_eq!(IndexType::Minute.granularity(), Duration::from_secs(60));
+        assert_eq!(IndexType::Hour.granularity(), Duration::from_secs(3600));
+        assert_eq!(IndexType::Day.granularity(), Duration::from_secs(86400));
+    }
+
+    #[test]
+    fn test_index_type_granularity_at() {
+        use crate::entries::IndexType;
+        use chrono::{TimeZone, Utc};
+        use std::time::Duration;
+
+        let day = 86400;
+        let feb_leap = Utc.ymd(2020, 2, 10).and_hms(12, 0, 0);
+        let feb = Utc.ymd(2021, 2, 10).and_hms(12, 0, 0);
+        let dec = Utc.ymd(2021, 12, 31).and_hms(23, 59, 59);
+        assert_eq!(
+            IndexType::Month.granularity_at(&feb_leap).unwrap(),
+            Duration::from_secs(29 * day)
+        );
+        assert_eq!(
+            IndexType::Month.granularity_at(&feb).unwrap(),
+            Duration::from_secs(28 * day)
+        );
+        assert_eq!(
+            IndexType::Month.granularity_at(&dec).unwrap(),
+            Duration::from_secs(31 * day)
+        );
+        assert_eq!(
+            IndexType::Year.granularity_at(&feb_leap).unwrap(),
+            Duration::from_secs(366 * day)
+        );
+        assert_eq!(
+            IndexType::Year.granularity_at(&feb).unwrap(),
+            Duration::from_secs(365 * day)
+        );
+        assert_eq!(
+            IndexType::Hour.granularity_at(&feb).unwrap(),
+            Duration::from_secs(3600)
+        );
+        //Units ending beyond the last year chrono can represent error rather than panic
+        let last_year = Utc.ymd(262_143, 12, 10).and_hms(12, 0, 0);
+        assert!(IndexType::Year.granularity_at(&last_year).is_err());
+        assert!(IndexType::Month.granularity_at(&last_year).is_err());
+        assert_eq!(
+            IndexType::Day.granularity_at(&last_year).unwrap(),
+            Duration::from_secs(day)
+        );
+    }
+}
+
+mod index_tests {
+    #[test]
+    fn test_index_ends_before() {
+        use crate::entries::Index;
+        use chrono::{TimeZone, Utc};
+        use std::time::Duration;
+
+        let index = Index {
+            from: Duration::from_secs(1_628_776_880),
+            until: Duration::from_secs(1_628_776_890),
+        };
+        assert!(index.ends_before(&Utc.timestamp(1_628_776_900, 0)));
+        assert!(index.ends_before(&Utc.timestamp(1_628_776_890, 0)));
+        assert!(!index.ends_before(&Utc.timestamp(1_628_776_885, 0)));
+        assert!(!index.ends_before(&Utc.timestamp(1_628_776_800, 0)));
+        //Times before UNIX epoch are before every index
+        assert!(!index.ends_before(&Utc.timestamp(-1, 0)));
+        assert!(!index.ends_before(&Utc.timestamp(-1_628_776_900, 0)));
+    }
+
+    #[test]
+    fn test_index_window() {
+        use crate::entries::Index;
+        use crate::utils::index_for_timestamp;
+        use chrono::{TimeZone, Utc};
+        use std::time::Duration;
+
+        let index = index_for_timestamp(
+            Duration::new(1_628_776_883, 500_000_000),
+            &Duration::from_secs(10),
+            &Duration::from_secs(0),
+        )
+        .unwrap();
+        let (from, until) = index.window();
+        assert_eq!(from, Utc.timestamp(1_628_776_880, 0));
+        assert_eq!(until, Utc.timestamp(1_628_776_890, 0));
+
+        let index = Index {
+            from: Duration::new(1_628_776_880, 250_000_000),
+            until: Duration::new(1_628_776_880, 500_000_000),
+        };
+        assert_eq!(
+            index.window(),
+            (
+                Utc.timestamp(1_628_776_880, 250_000_000),
+                Utc.timestamp(1_628_776_880, 500_000_000)
+            )
+        );
+    }
+
+    #[test]
+    fn test_index_start_end_midpoint() {
+        use crate::utils::index_for_timestamp;
+        use chrono::{TimeZone, Utc};
+        use std::time::Duration;
+
+        let interval = Duration::from_secs(10);
+        let index = index_for_timestamp(
+            Duration::new(1_628_776_883, 500_000_000),
+            &interval,
+            &Duration::from_secs(0),
+        )
+        .unwrap();
+        assert_eq!(index.start(), Utc.timestamp(1_628_776_880, 0));
+        assert_eq!(index.end(), Utc.timestamp(1_628_776_890, 0));
+        assert_eq!(index.midpoint(), Utc.timestamp(1_628_776_885, 0));
+        assert_eq!((index.end() - index.start()).to_std().unwrap(), interval);
+        assert_eq!((index.start(), index.end()), index.window());
+
+        //End of an index is the start of the next
+        let next = index_for_timestamp(index.until, &interval, &Duration::from_secs(0)).unwrap();
+        assert_eq!(next.start(), index.end());
+
+        //Sub second intervals
+        let interval = Duration::from_millis(250);
+        let index = index_for_timestamp(
+            Duration::new(1_628_776_880, 300_000_000),
+            &interval,
+            &Duration::from_secs(0),
+        )
+        .unwrap();
+        assert_eq!((index.end() - index.start()).to_std().unwrap(), interval);
+        assert_eq!(index.midpoint(), Utc.timestamp(1_628_776_880, 375_000_000));
+    }
+
+    #[test]
+    fn test_index_derived_until() {
+        use crate::entries::Index;
+        use crate::utils::index_for_timestamp;
+        use crate::validation::validate_index_alignment;
+        use std::time::Duration;
+
+        let genesis = Duration::from_secs(0);
+        for interval in [
+            Duration::from_millis(250),
+            Duration::from_secs(10),
+            Duration::from_secs(3600),
+        ] {
+            for time in [0, 1_628_776_883, 1_628_776_899] {
+                let index =
+                    index_for_timestamp(Duration::from_secs(time), &interval, &genesis).unwrap();
+                assert_eq!(index.until_for(&interval), index.from + interval);
+                assert_eq!(index.until_for(&interval), index.until);
+            }
+        }
+
+        //Index stored with an until which does not match the interval; derived until ignores it & validation rejects it
+        let interval = Duration::from_secs(10);
+        let bad = Index {
+            from: Duration::from_secs(1_628_776_880),
+            until: Duration::from_secs(1_628_776_885),
+        };
+        assert_eq!(bad.until_for(&interval), Duration::from_secs(1_628_776_890));
+        assert!(validate_index_alignment(&bad, &interval, &genesis).is_err());
+        let backwards = Index {
+            from: Duration::from_secs(1_628_776_880),
+            until: Duration::from_secs(1_628_776_870),
+        };
+        assert_eq!(
+            backwards.until_for(&interval),
+            Duration::from_secs(1_628_776_890)
+        );
+        assert!(validate_index_alignment(&backwards, &interval, &genesis).is_err());
+    }
+
+    #[test]
+    fn test_index_equality_and_hash() {
+        use crate::utils::index_for_timestamp;
+        use std::collections::HashSet;
+        use std::time::Duration;
+
+        let minute = Duration::from_secs(60);
+        let genesis = Duration::from_secs(0);
+        let start =
+            index_for_timestamp(Duration::from_secs(1_628_776_860), &minute, &genesis).unwrap();
+        let middle =
+            index_for_timestamp(Duration::new(1_628_776_890, 123_000_000), &minute, &genesis)
+                .unwrap();
+        let next =
+            index_for_timestamp(Duration::from_secs(1_628_776_920), &minute, &genesis).unwrap();
+        assert_eq!(start, middle);
+        assert_ne!(start, next);
+
+        let mut set = HashSet::new();
+        set.insert(start);
+        set.insert(middle);
+        set.insert(next);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_aligned_index_passes_validation() {
+        use crate::utils::index_for_timestamp;
+        use crate::validation::validate_index_alignment;
+        use std::time::Duration;
+
+        let intervals = vec![
+            Duration::from_millis(250),
+            Duration::from_secs(10),
+            Duration::from_secs(15 * 60),
+            Duration::from_secs(60 * 60),
+            Duration::from_secs(24 * 60 * 60),
+        ];
+        for interval in intervals {
+            for genesis in vec![Duration::from_secs(0), Duration::from_secs(1_628_726_400)] {
+                for offset in vec![0, 1, 999, 12_345_678, 86_399_999, 1_000_000_000] {
+                    let time = Duration::from_secs(1_628_776_800) + Duration::from_millis(offset);
+                    let index = index_for_timestamp(time, &interval, &genesis).unwrap();
+                    assert!(validate_index_alignment(&index, &interval, &genesis).is_ok());
+                    assert!(index.from <= time && time < index.until);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_index_ordering() {
+        use crate::entries::Index;
+        use std::collections::BTreeMap;
+        use std::time::Duration;
+
+        let index = |from: u64, until: u64| Index {
+            from: Duration::from_secs(from),
+            until: Duration::from_secs(until),
+        };
+        let mut indexes = vec![
+            index(30, 40),
+            index(10, 20),
+            index(50, 60),
+            index(10, 15),
+            index(20, 30),
+        ];
+        indexes.sort();
+        assert_eq!(
+            indexes,
+            vec![
+                index(10, 15),
+                index(10, 20),
+                index(20, 30),
+                index(30, 40),
+                index(50, 60)
+            ]
+        );
+
+        //Indexes can be used as BTreeMap keys for chronological iteration
+        let mut counts = BTreeMap::new();
+        counts.insert(index(50, 60), 3);
+        counts.insert(index(10, 20), 1);
+        counts.insert(index(30, 40), 2);
+        assert_eq!(
+            counts.values().cloned().collect::<Vec<i32>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(counts.keys().next(), Some(&index(10, 20)));
+    }
+}
+
+mod until_tests {
+    #[test]
+    fn test_until_resolve() {
+        use crate::Until;
+        use chrono::{TimeZone, Utc};
+        use std::time::Duration;
+
+        let now = Utc.timestamp(1_628_776_890, 500);
+        let now_duration = Duration::new(1_628_776_890, 500);
+        assert_eq!(Until::Now.resolve_with(now_duration), now);
+        assert_eq!(Until::from(now).resolve_with(Duration::from_secs(0)), now);
+        assert_eq!(Until::from(now), Until::At(now));
+    }
+}
+
+mod consistency_tests {
+    #[test]
+    fn test_consistency_get_options() {
+        use crate::Consistency;
+        use hdk::prelude::{GetOptions, GetStrategy};
+
+        assert_eq!(Consistency::default(), Consistency::Latest);
+        assert_eq!(
+            GetOptions::from(&Consistency::default()),
+            GetOptions::latest()
+        );
+        assert_eq!(
+            GetOptions::from(&Consistency::Latest).strategy,
+            GetStrategy::Latest
+        );
+        assert_eq!(
+            GetOptions::from(&Consistency::Cached).strategy,
+            GetStrategy::Content
+        );
+    }
+}
+
+mod entry_chunk_index_tests {
+    #[test]
+    fn test_merge_entry_chunk_index() {
+        use crate::entries::Index;
+        use crate::test_utils::test_link;
+        use crate::EntryChunkIndex;
+        use std::time::Duration;
+
+        let link = |i: u8| test_link(i, "test");
+        let index = Index {
+            from: Duration::from_secs(10),
+            until: Duration::from_secs(20),
+        };
+        let first = EntryChunkIndex {
+            index: index.clone(),
+            links: vec![link(1), link(2)],
+            version: crate::ENTRY_CHUNK_INDEX_VERSION,
+        };
+        let second = EntryChunkIndex {
+            index: index.clone(),
+            links: vec![link(2), link(3)],
+            version: crate::ENTRY_CHUNK_INDEX_VERSION,
+        };
+        let merged = first.clone().merge(second).unwrap();
+        assert_eq!(merged.index, index);
+        assert_eq!(merged.links, vec![link(1), link(2), link(3)]);
+
+        let other = EntryChunkIndex {
+            index: Index {
+                from: Duration::from_secs(20),
+                until: Duration::from_secs(30),
+            },
+            links: vec![link(4)],
+            version: crate::ENTRY_CHUNK_INDEX_VERSION,
+        };
+        assert!(first.merge(other).is_err());
+    }
+
+    #[test]
+    fn test_sample_entry_chunk_index() {
+        use crate::entries::Index;
+        use crate::test_utils::test_link;
+        use crate::EntryChunkIndex;
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        let link = |i: u8| test_link(i, "test");
+        let chunk = EntryChunkIndex {
+            index: Index {
+                from: Duration::from_secs(10),
+                until: Duration::from_secs(20),
+            },
+            links: vec![5, 1, 4, 2, 3].into_iter().map(link).collect(),
+            version: crate::ENTRY_CHUNK_INDEX_VERSION,
+        };
+
+        //Count is of all links while sample is truncated to the oldest links
+        let sample = chunk.sample(3);
+        assert_eq!(sample.index, chunk.index);
+        assert_eq!(sample.count, 5);
+        assert_eq!(
+            sample.sample,
+            vec![1, 2, 3]
+                .into_iter()
+                .map(|i| link(i).target)
+                .collect::<Vec<EntryHash>>()
+        );
+
+        assert_eq!(chunk.sample(0).sample.len(), 0);
+        assert_eq!(chunk.sample(0).count, 5);
+        assert_eq!(chunk.sample(10).sample.len(), 5);
+    }
+}
+
+mod index_configuration_tests {
+    #[test]
+    fn test_index_configuration_interval() {
+        use crate::IndexConfiguration;
+        use std::time::Duration;
+
+        let config = |max_chunk_interval: usize| IndexConfiguration {
+            enforce_spam_limit: 20,
+            max_chunk_interval: max_chunk_interval,
+            genesis: None,
+            get_retry_attempts: None,
+            spam_limit_per_tag: false,
+            index_shards: None,
+            get_links_concurrency: None,
+            max_backdate: None,
+        };
+        let ten_seconds = config(10_000);
+        assert_eq!(ten_seconds.interval(), Duration::from_secs(10));
+        assert_eq!(ten_seconds.interval_millis(), 10_000);
+        assert_eq!(ten_seconds.interval_secs(), 10);
+
+        let day = config(24 * 60 * 60 * 1000);
+        assert_eq!(day.interval(), Duration::from_secs(86400));
+        assert_eq!(day.interval_millis(), 86_400_000);
+        assert_eq!(day.interval_secs(), 86400);
+
+        //Sub second intervals round down to whole seconds
+        let quarter_second = config(250);
+        assert_eq!(quarter_second.interval(), Duration::from_millis(250));
+        assert_eq!(quarter_second.interval_millis(), 250);
+        assert_eq!(quarter_second.interval_secs(), 0);
+    }
+
+    #[test]
+    fn test_validate_index_configuration() {
+        use crate::errors::IndexError;
+        use crate::IndexConfiguration;
+
+        let config = |enforce_spam_limit: usize, max_chunk_interval: usize| IndexConfiguration {
+            enforce_spam_limit: enforce_spam_limit,
+            max_chunk_interval: max_chunk_interval,
+            genesis: None,
+            get_retry_attempts: None,
+            spam_limit_per_tag: false,
+            index_shards: None,
+            get_links_concurrency: None,
+            max_backdate: None,
+        };
+        assert!(config(20, 10_000).validate().is_ok());
+        assert!(config(1, 250).validate().is_ok());
+        assert!(config(1, 24 * 60 * 60 * 1000).validate().is_ok());
+        let mut aligned_genesis = config(20, 10_000);
+        aligned_genesis.genesis = Some(1_628_776_800_000);
+        assert!(aligned_genesis.validate().is_ok());
+
+        let mut unaligned_genesis = config(20, 10_000);
+        unaligned_genesis.genesis = Some(1_628_776_805_000);
+        assert!(matches!(
+            unaligned_genesis.validate(),
+            Err(IndexError::InvalidConfiguration(_))
+        ));
+
+        assert!(matches!(
+            config(0, 10_000).validate(),
+            Err(IndexError::InvalidConfiguration(_))
+        ));
+        assert!(matches!(
+            config(20, 0).validate(),
+            Err(IndexError::InvalidConfiguration(_))
+        ));
+        let mut no_retries = config(20, 10_000);
+        no_retries.get_retry_attempts = Some(0);
+        assert!(matches!(
+            no_retries.validate(),
+            Err(IndexError::InvalidConfiguration(_))
+        ));
+        let mut no_shards = config(20, 10_000);
+        no_shards.index_shards = Some(0);
+        assert!(matches!(
+            no_shards.validate(),
+            Err(IndexError::InvalidConfiguration(_))
+        ));
+        let mut no_concurrency = config(20, 10_000);
+        no_concurrency.get_links_concurrency = Some(0);
+        assert!(matches!(
+            no_concurrency.validate(),
+            Err(IndexError::InvalidConfiguration(_))
+        ));
+
+        //Intervals which do not evenly divide the level they are hung from
+        for interval in vec![
+            7_000,
+            45 * 60 * 1000,
+            7 * 60 * 60 * 1000,
+            2 * 24 * 60 * 60 * 1000,
+        ] {
+            assert!(matches!(
+                config(20, interval).validate(),
+                Err(IndexError::DepthIntervalMismatch(_))
+            ));
+        }
+    }
+}
@@ -26,12 +26,23 @@
 //! ### Agent Link Validation
 //!
 //! For any given index an **agent** cannot make more than `ENFORCE_SPAM_LIMIT` links. This value is set by the properties of the host DNA which is using this library; this library will just read host DNA's properties and set its internal variables based on what it finds.
+//! If `spam_limit_per_tag` is set in host DNA's properties the limit is applied to each link tag separately; i.e an agent can make `ENFORCE_SPAM_LIMIT` `post` links and another `ENFORCE_SPAM_LIMIT` `reaction` links on one index.
+//! Host DNA's can run all link validation of this crate by calling `validate_create_link()` from their `validate_create_link` callback.
 //!
 //! ### DNA Lifecycle
 //!
 //! This DNA's variables mentioned above are expected to be static. That means its expected that the: `ENFORCE_SPAM_LIMIT` & `MAX_CHUNK_INTERVAL` should stay the same throughout the lifetime of the DHT. This is done to make validation possible in situations where DHT sharding could occur.
 //! If limits are able to change; we have no way to reliably know if an agent is operating on old limits by consequence of being out of touch with latest DHT state or if the agent is malicious and pretending they do not see the new limits. You can see this being an especially big problem when you have two areas of the DHT "merging" and the "outdated" area of the DHT having all of its links in-validated by the agents in the more current of the DHT space.
 //!
+//! Chunks are aligned relative to a genesis time which can be set with the `genesis` field (milliseconds since UNIX epoch) of the host DNA's properties. If not set, chunks are aligned to the UNIX epoch.
+//! Genesis must be a multiple of `max_chunk_interval` since the UNIX epoch so that chunks never straddle two nodes of the (calendar aligned) time tree; this is checked by `validate_configuration()`. For the same reason `max_chunk_interval` must evenly divide the time tree level chunks are hung from; an interval such as 7 hours would produce chunks crossing midnight which could be missed when traversing day paths, so it is rejected by `validate_configuration()` rather than linked under several days.
+//! Like the limits above, genesis must stay the same throughout the lifetime of the DHT. Genesis is never written to the DHT; every agent derives it from the same DNA properties,
+//! so agents indexing concurrently on a fresh DHT cannot race to create conflicting genesis chunks.
+//!
+//! Setting `index_shards` in the host DNA's properties spreads the root of each index across that many shards so a busy index does not make every write
+//! touch the same path entries. Entries are placed in a shard by their hash and every query function reads from every shard.
+//! `ENFORCE_SPAM_LIMIT` counts an agent's links on an index in every shard. The number of shards must stay the same throughout the lifetime of the DHT.
+//!
 //! Currently if we wish to update limits we will create a new DNA/DHT and link to the new one from the current.
 //!
 //! If you can guarantee that fragmentation of the DHT will not happen then its possible to implement limit updates. If this is something you wish to do its recommended that you enforce new limits at some given chunk in the future rather than instantly. This allows you to (hopefully) give enough time for other DHT agents to receive new limit information before its enforced.   
@@ -41,14 +52,37 @@
 //! This DNA exposes a few helper functions to make integrating with this time series data easy. Functions are:
 //!
 //! - `get_indexes_between()`: Gets links between two time periods
+//! - `get_indexes_for_time_span_across()`: Gets links between two time periods from an old & new time index; i.e after a DNA migration
+//! - `get_indexes_for_time_span_sized()`: Gets links between two time periods in pages of bounded size
+//! - `get_indexes_for_time_span_limited()`: Gets at most limit links between two time periods; either filling from the oldest index or spread across indexes
+//! - `get_indexes_for_time_span_where()`: Gets links between two time periods only on indexes matching a supplied filter
+//! - `get_indexes_bucketed()`: Gets link targets between two time periods grouped into buckets of equal length by entry time
+//! - `get_index_samples_for_time_span()`: Gets the number of links & a sample of link targets for each index between two time periods
+//! - `prefetch_span()`: Fetches indexes & link targets between two time periods into the conductors cache
+//! - `chunks_desc()`: Lazily iterates over indexes between two time periods, newest first
+//! - `existing_chunks_mask()`: Gets whether an index exists for each interval between two time periods without loading indexes
 //! - `get_current_index()`: Gets links on current index period
+//! - `get_current_or_recent_index()`: Gets links on current index period or most recent populated index if current index is empty
 //! - `get_most_recent_indexes()`: Gets the most recent links
+//! - `get_recent_indexes()`: Gets links from the n most recent populated indexes
+//! - `index_time_bounds()`: Gets the time span covered by populated indexes
+//! - `children_count()`: Gets the number of children of a time path without loading them
+//! - `ensure_current_chunk()`: Creates the current index if it does not yet exist
+//! - `ensure_chunk()`: Creates an index if it does not yet exist; optionally creating every missing index before it
 //! - `index_entry()`: Indexes an entry into time tree
+//! - `index_hash()`: Indexes an entry hash into time tree at a given time
+//! - `index_entry_with()`: Indexes an entry hash into time tree at a time resolved by supplied function
+//! - `reindex()`: Moves an indexed entry hash to the index of a new time; keeping its link tag
+//! - `when_indexed()`: Gets the time(s) an entry was indexed at
 //!
 //! ### hApp Usage
 //!
 //! Using the above methods, it's possible to build an application which places an emphasis on time ordered data (such as a group DM or news feed). Or you can use the time ordered nature of the data as a natural pagination for larger queries where you may wish to aggregate data over a given time period and then perform some further computations over it.
 //!
+//! Querying an index which has never been written to is not an error. Functions returning a single index return `None` and functions returning many indexes or links return an empty `Vec`. Errors are reserved for genuine failures such as invalid requests, malformed time trees or failing host calls.
+//!
+//! When querying without a link tag, an entry indexed under many tags on one index is only returned once. When querying with a link tag every link matching that tag is returned.
+//!
 //!
 //! ## Status
 //!
@@ -63,7 +97,7 @@
 //!
 //! ### Limitations
 //!
-//! - You cannot index at time before UNIX epoch (00:00:00 UTC on 1 January 1970)
+//! - You cannot index at time before UNIX epoch (00:00:00 UTC on 1 January 1970) or the configured genesis
 //! - Limit & interval variables must be static throughout lifetime of DHT
 //! - Calling `get_indexes_between()` with a large from & until value will take a long time to return
 //! - It is currently not possible to set library variables by adding appropriate variables to DHT properties. This crate must instead be forked, altered and then used inside your DNA.
@@ -76,6 +110,23 @@ use std::time::Duration;
 
 use hdk::prelude::*;
 
+/// Internal logging; compiled out unless the debug_log feature is enabled
+macro_rules! index_debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "debug_log")]
+        hdk::prelude::debug!($($arg)*);
+    };
+}
+
+/// Shadows the debug! macro from the hdk prelude inside this crate so any ungated logging fails to compile
+//Never invoked when the crate is correct; so it is always unused
+#[allow(unused_macros)]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        compile_error!("Logging must use index_debug! so it is compiled out by default")
+    };
+}
+
 mod bfs;
 mod convertions;
 mod dfs;
@@ -85,6 +136,8 @@ mod impl_utils;
 /// Public methods exposed by lib
 pub mod methods;
 mod search;
+#[cfg(test)]
+mod test_utils;
 mod traits;
 mod utils;
 mod validation;
@@ -95,13 +148,81 @@ pub mod entries;
 /// Trait to impl on entries that you want to add to time index
 pub use traits::IndexableEntry;
 
+/// Trait to impl on sources of index results which should be queried together
+pub use traits::IndexBackend;
+
+/// Validation functions to be called from host DNA's validation callbacks
+pub use validation::{
+    validate_chunk_link, validate_create_link, validate_index_link_age, validate_index_link_limit,
+    validate_index_link_reachable, validate_index_link_timing,
+};
+
 use entries::{Index, IndexType};
-use errors::{IndexError, IndexResult};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Helpers for converting times given by clients
+pub use utils::{from_unix_millis, from_unix_secs, parse_time};
+
+/// Helper for describing times relative to genesis when debugging
+pub use utils::format_relative_to_genesis;
+
+/// Error & result types returned by all public functions of this crate
+pub use errors::{IndexError, IndexResult};
+
+/// Suggested upper bound on the size of a response returned across the zome boundary; see [`get_indexes_for_time_span_sized()`]
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// Largest number of intervals [`existing_chunks_mask()`] will report on in one call; longer spans must be split into several calls
+pub const MAX_MASK_INTERVALS: usize = 10_000;
+
+/// Number of links per chunk which [`suggest_interval()`] aims for
+pub const SUGGESTED_LINKS_PER_CHUNK: usize = 100;
+
+/// Prefix of link tag used on links from an indexed entry back to its time path
+pub(crate) const TIME_PATH_LINK_TAG: &str = "time_path";
+
+/// Wire format version of [`EntryChunkIndex`]. Bumped whenever the serialized form of EntryChunkIndex changes
+pub const ENTRY_CHUNK_INDEX_VERSION: u8 = 1;
+
+/// Links found on an index. Field names & order form the wire format sent to clients and must not change without bumping
+/// [`ENTRY_CHUNK_INDEX_VERSION`]. Payloads made before versioning was added deserialize with a version of 0
+#[derive(Serialize, Deserialize, SerializedBytes, Debug, Clone)]
 pub struct EntryChunkIndex {
     pub index: Index,
     pub links: Vec<Link>,
+    #[serde(default)]
+    pub version: u8,
+}
+
+/// Metadata of a link on an index; passed to the filter of [`get_links_and_load_for_time_span_filtered()`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LinkData {
+    pub author: AgentPubKey,
+    pub target: EntryHash,
+    pub tag: LinkTag,
+    pub timestamp: Timestamp,
+}
+
+/// Number of links on an index alongside a sample of its link targets; see [`get_index_samples_for_time_span()`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IndexSample {
+    pub index: Index,
+    /// Total number of links on index
+    pub count: usize,
+    /// Targets of the oldest links on index
+    pub sample: Vec<EntryHash>,
+}
+
+/// Problem found with a link in the time tree by [`verify_index_integrity()`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum IntegrityIssue {
+    /// Link target could not be resolved
+    Dangling { index: Index, link: Link },
+    /// Link target has an entry time which does not fall inside the index it is linked from
+    Misplaced {
+        index: Index,
+        link: Link,
+        entry_time: DateTime<Utc>,
+    },
 }
 
 /// Configuration object that should be set in your host DNA's properties
@@ -109,6 +230,27 @@ pub struct EntryChunkIndex {
 pub struct IndexConfiguration {
     pub enforce_spam_limit: usize,
     pub max_chunk_interval: usize,
+    /// Time (as milliseconds since UNIX epoch) from which chunks are aligned. Defaults to UNIX epoch if not set
+    #[serde(default)]
+    pub genesis: Option<u64>,
+    /// Number of attempts made to get a link target during queries while the get errors; a target which resolves to nothing is absent at once. Defaults to 2 if not set
+    #[serde(default)]
+    pub get_retry_attempts: Option<usize>,
+    /// If true ENFORCE_SPAM_LIMIT is applied separately to each link tag; so links of one tag do not use up the budget of another.
+    /// Defaults to false; where all of an agents links on an index count towards one limit
+    #[serde(default)]
+    pub spam_limit_per_tag: bool,
+    /// Number of shards the root of each index is spread across; entries are placed in a shard by their hash and queries read
+    /// from every shard. Spreads writes of a busy index across multiple neighbourhoods. Defaults to 1 (no sharding) if not set
+    #[serde(default)]
+    pub index_shards: Option<usize>,
+    /// Number of indexes whose links are requested from the conductor in one call during time span queries. Defaults to 8 if not set
+    #[serde(default)]
+    pub get_links_concurrency: Option<usize>,
+    /// Maximum age (in milliseconds) of an index links can be made on; links on indexes which ended longer ago are rejected by validation.
+    /// Unbounded if not set
+    #[serde(default)]
+    pub max_backdate: Option<u64>,
 }
 
 pub enum SearchStrategy {
@@ -116,6 +258,66 @@ pub enum SearchStrategy {
     Bfs,
 }
 
+/// How a limited number of links is shared between the indexes of a time span
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Distribution {
+    /// Links are taken from the oldest index first; later indexes only receive links once earlier indexes are exhausted
+    Fill,
+    /// Links are taken one at a time from each index in turn so that a single busy index cannot use up the whole limit
+    RoundRobin,
+}
+
+impl Default for Distribution {
+    fn default() -> Self {
+        Distribution::Fill
+    }
+}
+
+/// Freshness of data read from the DHT. Only applies to gets of link targets; link reads in this version of the HDK take no options
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Consistency {
+    /// Data may be served from the local cache if present; cheaper but can miss recent updates & deletes.
+    /// Data missing from the local cache is fetched from the network
+    Cached,
+    /// Data is always fetched from the network so the most recent updates & deletes are seen
+    Latest,
+}
+
+impl Default for Consistency {
+    fn default() -> Self {
+        Consistency::Latest
+    }
+}
+
+/// Outcome of indexing an entry
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Indexed {
+    /// A new link was made from the time tree to the entry
+    Created,
+    /// Entry was already linked from the index with the same link tag, i.e by an earlier attempt which timed out; no link was made
+    AlreadyExisted,
+}
+
+/// Upper bound of a time span query
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Until {
+    /// Resolves to sys_time() at the time the query is made
+    Now,
+    At(DateTime<Utc>),
+}
+
+/// Position from which a partial query can be resumed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PageCursor {
+    /// Time from which results have not yet been returned. For budgeted & size bounded queries this is the start of the first index not
+    /// yet fully returned; when polling this is the timestamp of the newest link delivered
+    pub from: DateTime<Utc>,
+    /// Links created exactly at from which have already been delivered. For size bounded queries these are the links of the index
+    /// starting at from which have already been delivered
+    #[serde(default)]
+    pub seen: Vec<HeaderHash>,
+}
+
 #[derive(Debug)]
 pub(crate) enum Order {
     Desc,
@@ -124,35 +326,234 @@ pub(crate) enum Order {
 
 /// Gets all links with optional tag link_tag since last_seen time with option to limit number of results by limit
 /// Note: if last_seen is a long time ago in a popular DHT then its likely this function will take a very long time to run
+/// until can be given as [`Until::Now`] to query up until the current time
 /// TODO: would be cool to support DFS and BFS here
-pub fn get_indexes_for_time_span(
+/// Links which have been deleted are only returned when include_deleted is set; useful for moderation tools
+pub fn get_indexes_for_time_span<U: Into<Until>>(
     index: String,
     from: DateTime<Utc>,
-    until: DateTime<Utc>,
+    until: U,
     link_tag: Option<LinkTag>,
+    include_deleted: bool,
 ) -> IndexResult<Vec<EntryChunkIndex>> {
+    let until = until.into().resolve()?;
     //Check that timeframe specified is greater than the INDEX_DEPTH.
-    if until.timestamp_millis() - from.timestamp_millis() < MAX_CHUNK_INTERVAL.as_millis() as i64 {
+    if until.timestamp_millis() - from.timestamp_millis() < interval_millis() {
         return Err(IndexError::RequestError(
             "Time frame is smaller than index interval",
         ));
     };
 
     Ok(methods::get_indexes_for_time_span(
-        from, until, index, link_tag,
+        from,
+        until,
+        index,
+        link_tag,
+        include_deleted,
     )?)
 }
 
-/// Get links for index that exist between two timestamps
-pub fn get_links_for_time_span(
+/// Same as [`get_indexes_for_time_span()`] but only returning indexes for which chunk_filter returns true. Filter is applied before
+/// links of an index are fetched so queries which can rule out whole indexes by their time (i.e business hours only) skip fetching them
+pub fn get_indexes_for_time_span_where<U: Into<Until>, C: Fn(&Index) -> bool>(
     index: String,
     from: DateTime<Utc>,
+    until: U,
+    link_tag: Option<LinkTag>,
+    chunk_filter: C,
+) -> IndexResult<Vec<EntryChunkIndex>> {
+    let until = until.into().resolve()?;
+    if until.timestamp_millis() - from.timestamp_millis() < interval_millis() {
+        return Err(IndexError::RequestError(
+            "Time frame is smaller than index interval",
+        ));
+    };
+    methods::get_indexes_for_time_span_where(from, until, index, link_tag, false, chunk_filter)
+}
+
+/// Query two time indexes for the same time span and merge their results into one chronological list. Intended for apps which
+/// have migrated to a new DNA (i.e to change MAX_CHUNK_INTERVAL) and want to present a continuous feed spanning old & new DNA.
+/// Indexes of both backends are ordered by their start time; where indexes of both start at the same time, old indexes come first
+pub fn get_indexes_for_time_span_across<O: IndexBackend, N: IndexBackend>(
+    old: &O,
+    new: &N,
+    from: DateTime<Utc>,
     until: DateTime<Utc>,
+) -> IndexResult<Vec<EntryChunkIndex>> {
+    Ok(methods::merge_index_results(
+        old.get_indexes_for_time_span(from, until)?,
+        new.get_indexes_for_time_span(from, until)?,
+    ))
+}
+
+/// Best effort version of [`get_indexes_for_time_span()`] which retrieves links for at most budget indexes between from & until.
+/// Indexes are returned oldest first. If indexes remain unscanned a [`PageCursor`] is returned; the query can be resumed by calling
+/// again with the cursors from time
+pub fn get_indexes_for_time_span_within_budget<U: Into<Until>>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
+    link_tag: Option<LinkTag>,
+    budget: usize,
+) -> IndexResult<(Vec<EntryChunkIndex>, Option<PageCursor>)> {
+    let until = until.into().resolve()?;
+    if from >= until {
+        return Err(IndexError::RequestError("from must be before until"));
+    };
+    methods::get_indexes_for_time_span_within_budget(from, until, index, link_tag, budget)
+}
+
+/// Size bounded version of [`get_indexes_for_time_span()`] for windows which may be too busy to return in one response. Indexes are
+/// returned oldest first & links are added until the encoded size of returned indexes would exceed max_bytes (defaults to
+/// [`DEFAULT_MAX_RESPONSE_BYTES`]). If links remain a [`PageCursor`] is returned; calling again with that cursor returns the next page.
+/// A single busy index may be split across pages, in which case the cursor records its links which have already been returned.
+/// Start a query with a cursor whose from is the start of the window and whose seen is empty
+pub fn get_indexes_for_time_span_sized<U: Into<Until>>(
+    index: String,
+    cursor: PageCursor,
+    until: U,
+    link_tag: Option<LinkTag>,
+    max_bytes: Option<usize>,
+) -> IndexResult<(Vec<EntryChunkIndex>, Option<PageCursor>)> {
+    let until = until.into().resolve()?;
+    if cursor.from >= until {
+        return Err(IndexError::RequestError("from must be before until"));
+    };
+    methods::get_indexes_for_time_span_sized(
+        &cursor,
+        until,
+        index,
+        link_tag,
+        max_bytes.unwrap_or(DEFAULT_MAX_RESPONSE_BYTES),
+    )
+}
+
+/// Get links created after the position of cursor, along with an advanced cursor which can be passed to the next call.
+/// Polling with the returned cursor will never return the same link twice; useful for following the tail of an index.
+/// Only indexes from the cursor up until now are checked; entries indexed into older indexes after the cursor has passed them will not be returned
+pub fn poll_new_since(
+    index: String,
+    cursor: PageCursor,
+    link_tag: Option<LinkTag>,
+) -> IndexResult<(Vec<EntryChunkIndex>, PageCursor)> {
+    let now = Until::Now.resolve()?;
+    if cursor.from >= now {
+        return Ok((vec![], cursor));
+    };
+    let chunks = methods::get_indexes_for_time_span(cursor.from, now, index, link_tag, false)?;
+    utils::links_after_cursor(chunks, cursor)
+}
+
+/// Same as [`get_indexes_for_time_span()`] but with the link targets of each index grouped by link tag.
+/// Useful when many types of content are stored under one index and distinguished by tag
+pub fn get_indexes_for_time_span_grouped_by_tag<U: Into<Until>>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
+    link_tag: Option<LinkTag>,
+) -> IndexResult<Vec<(Index, std::collections::HashMap<LinkTag, Vec<EntryHash>>)>> {
+    Ok(
+        get_indexes_for_time_span(index, from, until, link_tag, false)?
+            .into_iter()
+            .map(|chunk| (chunk.index.clone(), chunk.group_by_tag()))
+            .collect(),
+    )
+}
+
+/// Same as [`get_indexes_for_time_span()`] but returning only the number of links on each index and the targets of its
+/// first sample_size links. Useful for summaries such as "143 messages (showing 3)"
+pub fn get_index_samples_for_time_span<U: Into<Until>>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
+    link_tag: Option<LinkTag>,
+    sample_size: usize,
+) -> IndexResult<Vec<IndexSample>> {
+    Ok(
+        get_indexes_for_time_span(index, from, until, link_tag, false)?
+            .into_iter()
+            .map(|chunk| chunk.sample(sample_size))
+            .collect(),
+    )
+}
+
+/// Same as [`get_indexes_for_time_span()`] but returning at most limit links shared between indexes according to distribution.
+/// Links taken from each index are its oldest; indexes from which no links were taken are omitted
+pub fn get_indexes_for_time_span_limited<U: Into<Until>>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
+    link_tag: Option<LinkTag>,
+    limit: usize,
+    distribution: Distribution,
+) -> IndexResult<Vec<EntryChunkIndex>> {
+    Ok(methods::distribute_links(
+        get_indexes_for_time_span(index, from, until, link_tag, false)?,
+        limit,
+        &distribution,
+    ))
+}
+
+/// Get all indexes which exist between two timestamps, oldest first. Links on indexes are not fetched; use [`get_indexes_for_time_span()`]
+/// when links are needed. until can be given as [`Until::Now`] to query up until the current time
+pub fn get_chunks_for_time_span<U: Into<Until>>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
+) -> IndexResult<Vec<Index>> {
+    let until = until.into().resolve()?;
+    methods::get_chunks_for_time_span(from, until, index)
+}
+
+/// Fetch all indexes between from & until, and the targets of their links if include_targets is set, from the network ahead of
+/// time so that following queries over the span are answered from the conductors cache. Returns the number of elements fetched
+pub fn prefetch_span<U: Into<Until>>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
+    link_tag: Option<LinkTag>,
+    include_targets: bool,
+) -> IndexResult<usize> {
+    let until = until.into().resolve()?;
+    methods::prefetch_span(from, until, index, link_tag, include_targets)
+}
+
+/// Lazily iterate over indexes which exist between from & until, newest first. Each index is only looked up when the iterator
+/// is advanced so feeds can load indexes on demand as the user scrolls. Iteration stops at from or genesis; whichever is later.
+/// Every interval in the span is checked so this is best suited to densely populated indexes. until can be given as [`Until::Now`]
+pub fn chunks_desc<U: Into<Until>>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
+) -> IndexResult<impl Iterator<Item = IndexResult<Index>>> {
+    let until = until.into().resolve()?;
+    methods::chunks_desc(from, until, index)
+}
+
+/// Get the start of every interval between from & until paired with whether an index exists there; oldest first. Intended for
+/// calendar style views which need to know which intervals are populated without loading them; indexes are not fetched and the
+/// links of each time path indexes hang from are fetched once. Indexes which exist but no longer hold links are reported as existing.
+/// Errors if the span covers more than [`MAX_MASK_INTERVALS`] intervals. until can be given as [`Until::Now`]
+pub fn existing_chunks_mask<U: Into<Until>>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
+) -> IndexResult<Vec<(DateTime<Utc>, bool)>> {
+    let until = until.into().resolve()?;
+    methods::existing_chunks_mask(from, until, index)
+}
+
+/// Get links for index that exist between two timestamps. until can be given as [`Until::Now`] to query up until the current time
+pub fn get_links_for_time_span<U: Into<Until>>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
     link_tag: Option<LinkTag>,
     limit: Option<usize>,
 ) -> IndexResult<Vec<Link>> {
+    let until = until.into().resolve()?;
     // //Check that timeframe specified is greater than the INDEX_DEPTH.
-    // if until.timestamp_millis() - from.timestamp_millis() < MAX_CHUNK_INTERVAL.as_millis() as i64 {
+    // if until.timestamp_millis() - from.timestamp_millis() < interval_millis() {
     //     return Err(IndexError::RequestError(
     //         "Time frame is smaller than index interval",
     //     ));
@@ -163,19 +564,22 @@ pub fn get_links_for_time_span(
     )?)
 }
 
-/// Get links for index that exist between two timestamps and attempt to serialize link targets to T
+/// Get links for index that exist between two timestamps and attempt to serialize link targets to T.
+/// until can be given as [`Until::Now`] to query up until the current time
 pub fn get_links_and_load_for_time_span<
     T: TryFrom<SerializedBytes, Error = SerializedBytesError> + IndexableEntry + std::fmt::Debug,
+    U: Into<Until>,
 >(
     index: String,
     from: DateTime<Utc>,
-    until: DateTime<Utc>,
+    until: U,
     link_tag: Option<LinkTag>,
     strategy: SearchStrategy,
     limit: Option<usize>,
 ) -> IndexResult<Vec<T>> {
+    let until = until.into().resolve()?;
     // //Check that timeframe specified is greater than the INDEX_DEPTH.
-    // if until.timestamp_millis() - from.timestamp_millis() < MAX_CHUNK_INTERVAL.as_millis() as i64 {
+    // if until.timestamp_millis() - from.timestamp_millis() < interval_millis() {
     //     return Err(IndexError::RequestError(
     //         "Time frame is smaller than index interval",
     //     ));
@@ -186,62 +590,408 @@ pub fn get_links_and_load_for_time_span<
     )?)
 }
 
+/// Same as [`get_links_and_load_for_time_span()`] but only loading link targets where filter returns true for the links metadata.
+/// Filter is applied before targets are fetched so selective queries (i.e by author or tag) avoid fetching entries which would be
+/// thrown away. Results are ordered oldest link first
+pub fn get_links_and_load_for_time_span_filtered<
+    T: TryFrom<SerializedBytes, Error = SerializedBytesError>,
+    U: Into<Until>,
+    P: Fn(&LinkData) -> bool,
+>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
+    link_tag: Option<LinkTag>,
+    filter: P,
+) -> IndexResult<Vec<T>> {
+    let until = until.into().resolve()?;
+    methods::get_links_and_load_for_time_span_filtered(from, until, index, link_tag, filter)
+}
+
+/// Get the targets of links between from & until grouped into bucket_count buckets of equal length; i.e 24 hourly buckets across a day.
+/// Targets are placed in buckets by their [`IndexableEntry::entry_time()`] rather than by the index they are linked on, so buckets need not
+/// line up with index boundaries. Returns (start, end, targets) for every bucket oldest first; empty buckets included & targets ordered by time.
+/// Targets are fetched to read their time; targets which cannot be resolved or hold no app entry are skipped & a target which fails to
+/// deserialize to T returns an error.
+/// until can be given as [`Until::Now`]
+pub fn get_indexes_bucketed<
+    T: TryFrom<SerializedBytes, Error = SerializedBytesError> + IndexableEntry,
+    U: Into<Until>,
+>(
+    index: String,
+    from: DateTime<Utc>,
+    until: U,
+    bucket_count: usize,
+    link_tag: Option<LinkTag>,
+) -> IndexResult<Vec<(DateTime<Utc>, DateTime<Utc>, Vec<EntryHash>)>> {
+    let until = until.into().resolve()?;
+    methods::get_indexes_bucketed::<T>(from, until, index, bucket_count, link_tag)
+}
+
+/// Samples link density of index between from & until and recommends a MAX_CHUNK_INTERVAL which keeps the busier chunks of the window
+/// near to [`SUGGESTED_LINKS_PER_CHUNK`] links while keeping the number of chunks a range query must visit low.
+/// This is read only & purely advisory; MAX_CHUNK_INTERVAL cannot be changed during the lifetime of a DHT but this can help
+/// choose an interval for a new DNA based on the usage of an existing one
+pub fn suggest_interval(
+    index: String,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    link_tag: Option<LinkTag>,
+) -> IndexResult<Duration> {
+    let chunks = get_indexes_for_time_span(index, from, until, link_tag, false)?;
+    let link_counts = chunks
+        .iter()
+        .map(|chunk| chunk.links.len())
+        .collect::<Vec<usize>>();
+    let window = (until - from)
+        .to_std()
+        .map_err(|_err| IndexError::RequestError("from must be before until"))?;
+    let chunks_in_window = ((window.as_nanos() + MAX_CHUNK_INTERVAL.as_nanos() - 1)
+        / MAX_CHUNK_INTERVAL.as_nanos()) as usize;
+    Ok(utils::recommend_interval(
+        &link_counts,
+        chunks_in_window,
+        &MAX_CHUNK_INTERVAL,
+        SUGGESTED_LINKS_PER_CHUNK,
+    ))
+}
+
 /// Uses sys_time to get links on current time index. Note: this is not guaranteed to return results. It will only look
 /// at the current time index which will cover as much time as the current system time - MAX_CHUNK_INTERVAL
 pub fn get_current_index(
     index: String,
     link_tag: Option<LinkTag>,
 ) -> IndexResult<Option<EntryChunkIndex>> {
-    match methods::get_current_index(index)? {
-        Some(index) => {
-            let links = get_links(index.path_entry_hash()?, link_tag)?;
+    match methods::get_current_index(index.clone())? {
+        Some(path) => {
+            let chunk = Index::try_from(path)?;
             Ok(Some(EntryChunkIndex {
-                index: Index::try_from(index)?,
-                links: links,
+                links: chunk.get_shard_links(&index, link_tag)?,
+                index: chunk,
+                version: ENTRY_CHUNK_INDEX_VERSION,
             }))
         }
         None => Ok(None),
     }
 }
 
-/// Searches time index for most recent index and returns links from that index
-/// Guaranteed to return results if some index's have been made
-pub fn get_most_recent_indexes(
+/// Uses sys_time to get links on current time index. If the current time index does not exist or has no links
+/// then falls back to searching for the most recent populated index; see [`get_most_recent_indexes()`]
+pub fn get_current_or_recent_index(
     index: String,
     link_tag: Option<LinkTag>,
 ) -> IndexResult<Option<EntryChunkIndex>> {
-    let recent_index = methods::get_latest_index(index)?;
-    match recent_index {
-        Some(index) => {
-            let links = get_links(index.path_entry_hash()?, link_tag)?;
-            Ok(Some(EntryChunkIndex {
-                index: Index::try_from(index)?,
-                links: links,
-            }))
+    match get_current_index(index.clone(), link_tag.clone())? {
+        Some(current) if current.links.len() > 0 => Ok(Some(current)),
+        _ => get_most_recent_indexes(index, link_tag, None),
+    }
+}
+
+/// Ensures the index covering the current sys_time exists; creating any missing time path nodes. Suitable to be called from
+/// a host DNA's init callback so that the first index_entry() call made does not have to pay the cost of creating the current time path.
+/// Calling this when the current index already exists is a no-op. Path entries are deterministic so concurrent calls by many agents
+/// will converge on the same entries and links
+pub fn ensure_current_chunk(index: String) -> IndexResult<Index> {
+    let current = utils::get_current_chunk()?;
+    current.new(index)?;
+    Ok(current)
+}
+
+/// Ensures the given index exists; creating any missing time path nodes. If max_backfill is set every index between the newest existing
+/// index & chunk is created too, so that indexes form a sequence without gaps regardless of which agent creates them. Nothing is created
+/// if more than max_backfill indexes are missing. Returns the indexes which were ensured; oldest first
+pub fn ensure_chunk(
+    index: String,
+    chunk: Index,
+    max_backfill: Option<usize>,
+) -> IndexResult<Vec<Index>> {
+    match max_backfill {
+        Some(max_backfill) => methods::create_backfilled(index, chunk, max_backfill),
+        None => {
+            chunk.new(index)?;
+            Ok(vec![chunk])
         }
-        None => Ok(None),
     }
 }
 
-/// Index a given entry. Uses ['IndexableEntry::entry_time()'] to get time it should be indexed under.
-/// Will create link from time path to entry with link_tag passed into fn
+/// Validate the IndexConfiguration found in host DNA's properties. Intended to be called from a host DNA's init callback
+/// so that misconfigured DNA's fail at install time rather than in subtle ways at runtime; see [`IndexConfiguration::validate()`]
+pub fn validate_configuration() -> IndexResult<()> {
+    let host_dna_config = dna_info()?.properties;
+    IndexConfiguration::try_from(host_dna_config)?.validate()
+}
+
+/// Chunk interval configured in host DNA's properties
+pub fn interval() -> Duration {
+    *MAX_CHUNK_INTERVAL
+}
+
+/// Chunk interval configured in host DNA's properties in milliseconds; directly comparable with [`DateTime::timestamp_millis()`]
+pub fn interval_millis() -> i64 {
+    MAX_CHUNK_INTERVAL.as_millis() as i64
+}
+
+/// Chunk interval configured in host DNA's properties in whole seconds; sub second intervals are rounded down.
+/// Directly comparable with [`DateTime::timestamp()`]
+pub fn interval_secs() -> i64 {
+    MAX_CHUNK_INTERVAL.as_secs() as i64
+}
+
+/// Searches time index for most recent index and returns links from that index
+/// Guaranteed to return results if some index's have been made. Indexes which no longer have any links
+/// (matching link_tag) are skipped in favour of the next most recent populated index.
+/// If not_older_than is set then None is returned when the most recent populated index ends before that time
+pub fn get_most_recent_indexes(
+    index: String,
+    link_tag: Option<LinkTag>,
+    not_older_than: Option<DateTime<Utc>>,
+) -> IndexResult<Option<EntryChunkIndex>> {
+    let recent_index = methods::get_latest_index(index, link_tag)?;
+    Ok(match (recent_index, not_older_than) {
+        (Some(recent_index), Some(bound)) if recent_index.index.ends_before(&bound) => None,
+        (recent_index, _) => recent_index,
+    })
+}
+
+/// Searches time index backwards from the most recent index and returns links from up to chunk_count of the most recent
+/// populated indexes; ordered newest first. Useful for "recent activity" views which need more than the single latest index
+pub fn get_recent_indexes(
+    index: String,
+    chunk_count: usize,
+    link_tag: Option<LinkTag>,
+) -> IndexResult<Vec<EntryChunkIndex>> {
+    methods::get_recent_indexes(index, link_tag, chunk_count)
+}
+
+/// Get the number of children of a time path; i.e how many days of a month path have indexes. Only the links of path are fetched;
+/// children are not loaded. Children linked from path more than once (by concurrent Path::ensure() calls) are counted once.
+/// Intended for time paths; when given an index path the entries indexed under it are counted
+pub fn children_count(path: Path) -> IndexResult<usize> {
+    Ok(utils::count_distinct_targets(&get_links(
+        path.path_entry_hash()?,
+        None,
+    )?))
+}
+
+/// Get the overall time bounds of populated indexes as (start of oldest populated index, end of newest populated index).
+/// Useful for choosing sensible from & until values before making a range query. Returns None if no index contains links
+pub fn index_time_bounds(index: String) -> IndexResult<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+    let oldest = match methods::get_oldest_index(index.clone(), None)? {
+        Some(oldest) => oldest,
+        None => return Ok(None),
+    };
+    Ok(methods::get_latest_index(index, None)?
+        .map(|newest| (oldest.index.window().0, newest.index.window().1)))
+}
+
+/// Get links on a given index grouped by the agent who authored them. Useful for showing who contributed to a given time period
+pub fn get_links_grouped_by_author(
+    index: String,
+    chunk: Index,
+    link_tag: Option<LinkTag>,
+    limit: Option<usize>,
+) -> IndexResult<std::collections::HashMap<AgentPubKey, Vec<EntryHash>>> {
+    Ok(chunk.get_links_grouped_by_author(index, link_tag, limit)?)
+}
+
+/// Index a given entry. Uses ['IndexableEntry::index_times()'] to get the time(s) it should be indexed under; by default
+/// this is only ['IndexableEntry::entry_time()']. Will create link from time path to entry with link_tag passed into fn for each time.
+/// Safe to retry; returns [`Indexed::AlreadyExisted`] if the entry was already linked under every time with link_tag.
+/// Errors with LinkLimitReached if the agent has reached the link limit of an index the entry would be linked on; see [`index_hash()`]
 pub fn index_entry<T: IndexableEntry, LT: Into<LinkTag>>(
     index: String,
     data: T,
     link_tag: LT,
-) -> IndexResult<()> {
-    let index = methods::create_for_timestamp(index, data.entry_time())?;
+) -> IndexResult<Indexed> {
+    let target = data.hash()?;
+    let link_tag = link_tag.into();
+    let mut indexed = Indexed::AlreadyExisted;
+    for time in data.index_times() {
+        if index_hash(index.clone(), target.clone(), time, link_tag.clone(), false)?
+            == Indexed::Created
+        {
+            indexed = Indexed::Created;
+        };
+    }
+    Ok(indexed)
+}
+
+/// Index an entry hash using a caller supplied function to resolve the time it should be indexed under. Useful for entries which
+/// store their time in a format that does not suit implementing [`IndexableEntry`] or when time extraction can only be decided at runtime.
+/// Will create link from time path to entry with link_tag passed into fn
+pub fn index_entry_with<F: Fn() -> DateTime<Utc>, LT: Into<LinkTag>>(
+    index: String,
+    entry_hash: EntryHash,
+    time_fn: F,
+    link_tag: LT,
+) -> IndexResult<Indexed> {
+    index_hash(index, entry_hash, time_fn(), link_tag, false)
+}
+
+/// Index an arbitrary entry hash at a given time. Useful when the entry to be indexed has already been committed and
+/// only its hash is at hand. Will create link from time path to target with link_tag passed into fn.
+/// Errors with TargetNotFound if target cannot be resolved; unless allow_dangling is set. Note that links are only valid once
+/// their target can be resolved by validators, so links made with allow_dangling will not validate until target is published.
+/// If target is already linked from the index with link_tag no link is made & [`Indexed::AlreadyExisted`] is returned.
+/// Errors with LinkLimitReached without making any links if the agent has already made ENFORCE_SPAM_LIMIT links on the index;
+/// such links would be rejected by [`validate_index_link_limit()`]
+pub fn index_hash<LT: Into<LinkTag>>(
+    index: String,
+    target: EntryHash,
+    at: DateTime<Utc>,
+    link_tag: LT,
+    allow_dangling: bool,
+) -> IndexResult<Indexed> {
+    if !allow_dangling && utils::get_target_with_retry(target.clone())?.is_none() {
+        return Err(IndexError::TargetNotFound);
+    };
+    let link_tag = link_tag.into();
+    let index_name = index.clone();
+    let shard = utils::shard_for(&target, *INDEX_SHARDS);
+    let index = methods::create_for_timestamp(utils::shard_index_name(&index, shard), at)?;
+    let existing = get_links(index.path_entry_hash()?, Some(link_tag.clone()))?;
+    if utils::has_link(&existing, &target, &link_tag) {
+        return Ok(Indexed::AlreadyExisted);
+    };
+    //Deleted links, & links made in any shard of the index, count towards the limit in validation
+    let chunk = Index::try_from(index.clone())?;
+    let authored = utils::read_shards(&index_name, *INDEX_SHARDS, |shard| {
+        utils::get_authored_link_details(chunk.path(shard)?.path_entry_hash()?, None, true)
+    })?;
+    let limit_tag = if *SPAM_LIMIT_PER_TAG {
+        Some(&link_tag)
+    } else {
+        None
+    };
+    if utils::at_link_limit(
+        &authored,
+        &agent_info()?.agent_latest_pubkey,
+        limit_tag,
+        *ENFORCE_SPAM_LIMIT,
+    ) {
+        return Err(IndexError::LinkLimitReached);
+    };
     //Create link from end of time path to entry that should be indexed
-    create_link(index.path_entry_hash()?, data.hash()?, HdkLinkType::Any, link_tag)?;
+    create_link(
+        index.path_entry_hash()?,
+        target.clone(),
+        HdkLinkType::Any,
+        link_tag,
+    )?;
     //Create link from entry that should be indexed back to time tree so tree links can be found when starting from entry
-    create_link(data.hash()?, index.path_entry_hash()?, HdkLinkType::Any, LinkTag::new("time_path"))?;
-    Ok(())
+    create_link(
+        target,
+        index.path_entry_hash()?,
+        HdkLinkType::Any,
+        utils::time_path_link_tag(&index_name),
+    )?;
+    Ok(Indexed::Created)
 }
 
-/// Removes a given indexed entry from the time tree
+/// Move an entry indexed at old_time with link_tag so that it is indexed at new_time instead; i.e after the date of an event is edited.
+/// The link at new_time is made before the link at old_time is deleted so the entry is never absent from the index; readers may briefly
+/// see it under both times until the delete has propagated. Link tag is preserved & the back link to the old index is only removed when
+/// no links with other tags still point at the entry from the old index. Errors if entry is not indexed at old_time with link_tag
+pub fn reindex<LT: Into<LinkTag>>(
+    index: String,
+    target: EntryHash,
+    old_time: DateTime<Utc>,
+    new_time: DateTime<Utc>,
+    link_tag: LT,
+) -> IndexResult<Indexed> {
+    let link_tag = link_tag.into();
+    let shard = utils::shard_for(&target, *INDEX_SHARDS);
+    let old_path = Index::new_aligned(old_time)?
+        .path(utils::shard_index_name(&index, shard))?
+        .path_entry_hash()?;
+    let (moved, other_tags_remain) =
+        utils::links_to_move(get_links(old_path.clone(), None)?, &target, &link_tag);
+    if moved.is_empty() {
+        return Err(IndexError::RequestError(
+            "Entry is not indexed at old time with given link tag",
+        ));
+    };
+    if Index::new_aligned(old_time)? == Index::new_aligned(new_time)? {
+        return Ok(Indexed::AlreadyExisted);
+    };
+    let indexed = index_hash(index.clone(), target.clone(), new_time, link_tag, true)?;
+    for link in moved {
+        delete_link(link.create_link_hash)?;
+    }
+    if !other_tags_remain {
+        let back_links = utils::get_time_path_links(target, &index)?;
+        for back_link in back_links
+            .into_iter()
+            .filter(|back_link| back_link.target == old_path)
+        {
+            delete_link(back_link.create_link_hash)?;
+        }
+    };
+    Ok(indexed)
+}
+
+/// Get the start time of each index a given entry has been indexed under. Resolved using the links made from indexed entry
+/// back to the time tree in [`index_entry()`] so no search of the time tree is required.
+/// Note: returned times are the start of the index the entry was placed in; not the exact entry time
+pub fn when_indexed(indexed_entry: EntryHash) -> IndexResult<Vec<DateTime<Utc>>> {
+    //Link tags are prefix matched; this will find back links made from all indexes
+    let time_paths = get_links(indexed_entry, Some(LinkTag::new(TIME_PATH_LINK_TAG)))?;
+    let mut times = time_paths
+        .into_iter()
+        .map(|time_path| Index::from_hash(time_path.target))
+        .collect::<IndexResult<Vec<Option<Index>>>>()?
+        .into_iter()
+        .flatten()
+        .map(|index| index.window().0)
+        .collect::<Vec<DateTime<Utc>>>();
+    times.sort();
+    times.dedup();
+    Ok(times)
+}
+
+/// Diagnostic which checks every link between from & until resolves to an entry of type T whose entry_time() falls inside
+/// the index it is linked from. Link targets which cannot be deserialized to T are assumed to be of some other indexed type and are not checked.
+/// Link targets are read with the given consistency; use [`Consistency::Latest`] unless stale results are acceptable. Link reads in this
+/// version of the HDK take no options, so consistency does not apply to them and each chunk's links are read once.
+/// Note: this will fetch every link target in the time span and is intended for operators looking for corruption; not for regular use
+pub fn verify_index_integrity<
+    T: TryFrom<SerializedBytes, Error = SerializedBytesError> + IndexableEntry,
+>(
+    index: String,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    consistency: Consistency,
+) -> IndexResult<Vec<IntegrityIssue>> {
+    let mut issues = vec![];
+    for (chunk, path) in methods::find_shard_chunks_for_time_span(from, until, &index)? {
+        let links = utils::get_authored_link_details(path.path_entry_hash()?, None, false)?;
+        //An entry indexed under several tags on one chunk is only checked once
+        let links =
+            utils::dedup_links_by_target(links.into_iter().map(|(_author, link)| link).collect());
+        for link in links {
+            let entry_time =
+                match utils::get_target_with_consistency(link.target.clone(), &consistency)? {
+                    Some(element) => match element.entry().to_app_option::<T>() {
+                        Ok(Some(entry)) => Some(entry.entry_time()),
+                        _ => continue,
+                    },
+                    None => None,
+                };
+            if let Some(issue) = utils::check_link_integrity(&chunk, link, entry_time) {
+                issues.push(issue);
+            };
+        }
+    }
+    Ok(issues)
+}
+
+/// Removes a given indexed entry from the time tree. Entry will be removed from every index it was indexed under
 pub fn remove_index(indexed_entry: EntryHash) -> IndexResult<()> {
-    let time_paths =
-        get_links(indexed_entry.clone(), Some(LinkTag::new("time_path")))?;
+    //Link tags are prefix matched; this will find back links made under the legacy un-namespaced tag as well as index namespaced tags
+    let time_paths = get_links(
+        indexed_entry.clone(),
+        Some(LinkTag::new(TIME_PATH_LINK_TAG)),
+    )?;
     for time_path in time_paths {
         let path_links = get_links(time_path.target.clone(), None)?;
         let path_links: Vec<Link> = path_links
@@ -262,39 +1012,53 @@ pub fn remove_index(indexed_entry: EntryHash) -> IndexResult<()> {
 
 // Library configuration setup
 lazy_static! {
-    //Point at which links are considered spam and linked expressions are not allowed
-    pub static ref ENFORCE_SPAM_LIMIT: usize = {
-        // debug!("Attempting to set spam limit from: {:#?}", zome_info());
+    //Index configuration read from host DNA's properties. Parsed once; every configured value below is derived from it
+    static ref CONFIG: IndexConfiguration = {
+        // debug!("Attempting to read index configuration from: {:#?}", dna_info());
         let host_dna_config = dna_info().expect("Could not get zome configuration").properties;
-        let properties = IndexConfiguration::try_from(host_dna_config)
-            .expect("Could not convert zome dna properties to IndexConfiguration. Please ensure that your dna properties contains a IndexConfiguration field.");
-        properties.enforce_spam_limit
-    };
-    pub static ref MAX_CHUNK_INTERVAL: Duration = {
-        let host_dna_config = dna_info().expect("Could not get zome configuration").properties;
-        let properties = IndexConfiguration::try_from(host_dna_config)
-            .expect("Could not convert zome dna properties to IndexConfiguration. Please ensure that your dna properties contains a IndexConfiguration field.");
-        Duration::from_millis(properties.max_chunk_interval as u64)
+        IndexConfiguration::try_from(host_dna_config)
+            .expect("Could not convert zome dna properties to IndexConfiguration. Please ensure that your dna properties contains a IndexConfiguration field.")
     };
+    //Point at which links are considered spam and linked expressions are not allowed
+    pub static ref ENFORCE_SPAM_LIMIT: usize = CONFIG.enforce_spam_limit;
+    pub static ref MAX_CHUNK_INTERVAL: Duration = CONFIG.interval();
+    //Time from which all chunks are aligned. Read from host DNA's properties so no DHT lookup is required on insert/validation
+    pub static ref GENESIS: Duration = utils::resolve_genesis(CONFIG.genesis);
+    //Whether spam limit is counted separately for each link tag
+    pub static ref SPAM_LIMIT_PER_TAG: bool = CONFIG.spam_limit_per_tag;
+    //Number of attempts made to resolve link targets before they are considered absent
+    pub static ref GET_RETRY_ATTEMPTS: usize = CONFIG.get_retry_attempts.unwrap_or(2);
+    //Number of shards each index root is spread across
+    pub static ref INDEX_SHARDS: usize = CONFIG.index_shards.unwrap_or(1);
+    //Number of indexes whose links are fetched in one host call
+    pub static ref GET_LINKS_CONCURRENCY: usize = CONFIG.get_links_concurrency.unwrap_or(8);
+    //Maximum age of an index links can be made on
+    pub static ref MAX_BACKDATE: Option<Duration> = CONFIG.max_backdate.map(Duration::from_millis);
     //Determine what depth of time index should be hung from
-    pub static ref INDEX_DEPTH: Vec<entries::IndexType> =
-        if *MAX_CHUNK_INTERVAL < Duration::from_secs(1) {
-            vec![
-                IndexType::Second,
-                IndexType::Minute,
-                IndexType::Hour,
-                IndexType::Day,
-            ]
-        } else if *MAX_CHUNK_INTERVAL < Duration::from_secs(60) {
-            vec![IndexType::Minute, IndexType::Hour, IndexType::Day]
-        } else if *MAX_CHUNK_INTERVAL < Duration::from_secs(3600) {
-            vec![IndexType::Hour, IndexType::Day]
-        } else {
-            vec![IndexType::Day]
-        };
+    pub static ref INDEX_DEPTH: Vec<entries::IndexType> = utils::index_depth_for_interval(&MAX_CHUNK_INTERVAL);
 
     pub static ref DEFAULT_INDEX_DEPTH: Vec<IndexType> = vec![IndexType::Second,
         IndexType::Month,
         IndexType::Year
     ];
 }
+
+#[cfg(test)]
+mod logging_tests {
+    #[test]
+    fn test_index_debug_compiled_out() {
+        use std::cell::Cell;
+
+        let evaluated = Cell::new(0);
+        #[allow(unused_variables)]
+        let traced = || {
+            evaluated.set(evaluated.get() + 1);
+            "traced"
+        };
+        index_debug!("{}", traced());
+        //Without the debug_log feature the call, including its arguments, is removed at compile time
+        if !cfg!(feature = "debug_log") {
+            assert_eq!(evaluated.get(), 0);
+        }
+    }
+}
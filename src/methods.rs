@@ -1,15 +1,29 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, Utc};
 use hdk::{hash_path::path::Component, prelude::*};
 
 use crate::bfs::find_paths_for_time_span;
 use crate::dfs::methods::make_dfs_search;
-use crate::search::find_newest_time_path;
-use crate::utils::{add_time_index_to_path, get_index_for_timestamp, get_time_path};
+use crate::search::{
+    find_newest_time_path, find_next_time_path, find_oldest_time_path, find_previous_time_path,
+};
+use crate::utils::{
+    bucket_bounds, bucket_by_time, count_links_by_tag, datetime_to_duration, distinct_authors,
+    ensure_paths, exclude_authors, filter_links_in_window, get_authored_link_details,
+    get_authored_links, get_children_paths, get_chunk_path, get_current_time_path,
+    get_index_for_timestamp, get_index_links, get_index_links_batched, get_target_with_retry,
+    group_by_chunk, group_links_by_author, index_for_timestamp, load_lazily, load_path,
+    mark_existing, merge_shard_results, now_since_epoch, page_by_size, page_links, read_shards,
+    shard_index_names, sort_links, time_path_depth, ChunksDesc,
+};
+use crate::validation::{validate_configured_index_depth, validate_index_alignment};
 use crate::{
-    entries::{Index, IndexType, StringIndex, TimeIndex},
-    EntryChunkIndex, IndexableEntry, SearchStrategy, MAX_CHUNK_INTERVAL,
+    entries::{Index, StringIndex},
+    Distribution, EntryChunkIndex, IndexableEntry, LinkData, PageCursor, SearchStrategy,
+    ENFORCE_SPAM_LIMIT, ENTRY_CHUNK_INDEX_VERSION, GENESIS, GET_LINKS_CONCURRENCY, INDEX_DEPTH,
+    INDEX_SHARDS, MAX_CHUNK_INTERVAL, MAX_MASK_INTERVALS,
 };
 use crate::{
     errors::{IndexError, IndexResult},
@@ -19,112 +33,483 @@ use crate::{
 impl Index {
     /// Create a new time index
     pub(crate) fn new(&self, index: String) -> IndexResult<Path> {
+        validate_configured_index_depth()?;
         //These validations are to help zome callers; but should also be present in validation rules
-        let now_since_epoch = sys_time()?
-            .checked_difference_signed(&Timestamp::from_micros(0))
-            .ok_or(IndexError::InternalError("Should not overflow"))?
-            .to_std()
-            .map_err(|_err| IndexError::InternalError("Should not overflow"))?;
+        let now_since_epoch = now_since_epoch()?;
         if self.from > now_since_epoch {
             return Err(IndexError::RequestError(
                 "Time index cannot start in the future",
             ));
         };
-        if self.until - self.from != *MAX_CHUNK_INTERVAL {
-            return Err(IndexError::RequestError(
-                "Time index should use period equal to max interval set by DNA",
-            ));
-        };
-        if self.from.as_millis() % MAX_CHUNK_INTERVAL.as_millis() != 0 {
-            return Err(IndexError::RequestError(
-                "Time index does not follow index interval ordering",
-            ));
-        };
-
-        let mut time_path = get_time_path(index, self.from)?;
-        time_path.push(SerializedBytes::try_from(self)?.bytes().to_owned().into());
+        validate_index_alignment(self, &MAX_CHUNK_INTERVAL, &GENESIS)?;
 
-        //Create time tree
-        let time_path = Path::from(time_path);
+        //Create time tree. Path::ensure() checks from the leaf upwards and stops at the first ancestor which already exists,
+        //so path nodes are created lazily: only the missing nodes down to this index are written and nodes are never created
+        //for times which have not been indexed
+        let time_path = self.path(index)?;
         time_path.ensure()?;
         Ok(time_path)
     }
+
+    /// Create many time indexes at once. Ancestors shared between indexes (i.e the year, month & day of indexes in the same day)
+    /// are only checked & created once rather than once for each index. Returns paths of created indexes in the order given
+    pub fn create_many(index: String, indexes: &[Index]) -> IndexResult<Vec<Path>> {
+        validate_configured_index_depth()?;
+        let now_since_epoch = now_since_epoch()?;
+        let paths = indexes
+            .iter()
+            .map(|chunk| {
+                if chunk.from > now_since_epoch {
+                    return Err(IndexError::RequestError(
+                        "Time index cannot start in the future",
+                    ));
+                };
+                validate_index_alignment(chunk, &MAX_CHUNK_INTERVAL, &GENESIS)?;
+                Ok(chunk.path(index.clone())?.into())
+            })
+            .collect::<IndexResult<Vec<Vec<Component>>>>()?;
+
+        ensure_paths(
+            &paths,
+            |node: &[Component]| Ok(Path::from(node.to_vec()).exists()?),
+            |node: &[Component]| {
+                //Mirrors Path::ensure(); create path entry & link it from its parent
+                let path = Path::from(node.to_vec());
+                create_entry(&path)?;
+                if let Some(parent) = path.parent() {
+                    create_link(
+                        parent.path_entry_hash()?,
+                        path.path_entry_hash()?,
+                        HdkLinkType::Paths,
+                        LinkTag::try_from(&path)?,
+                    )?;
+                };
+                Ok(())
+            },
+        )?;
+        Ok(paths.into_iter().map(Path::from).collect())
+    }
+
+    /// Load an index from the hash of its path entry. Returns None if no entry can be found for hash and errors if
+    /// hash does not point to an index path
+    pub fn from_hash(hash: EntryHash) -> IndexResult<Option<Index>> {
+        match load_path(hash)? {
+            Some(path) => Ok(Some(Index::try_from(path)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Load many indexes from the hashes of their path entries. Hashes which cannot be resolved are skipped
+    pub fn from_hashes(hashes: Vec<EntryHash>) -> IndexResult<Vec<Index>> {
+        resolve_all(hashes, Index::from_hash)
+    }
+
+    /// Get the path of this index in the time tree of a given index. Does not check that path exists
+    pub fn path(&self, index: String) -> IndexResult<Path> {
+        Ok(Path::from(get_chunk_path(index, self)?))
+    }
+
+    /// Get links on this index in every shard of a given index; see [`get_index_links()`]
+    pub(crate) fn get_shard_links(
+        &self,
+        index: &str,
+        link_tag: Option<LinkTag>,
+    ) -> IndexResult<Vec<Link>> {
+        read_shards(index, *INDEX_SHARDS, |shard| {
+            get_index_links(
+                self.path(shard)?.path_entry_hash()?,
+                link_tag.clone(),
+                false,
+            )
+        })
+    }
+
+    /// Get links on this index grouped by the agent who authored them. Each agent will have at most ENFORCE_SPAM_LIMIT links returned
+    /// and no more than limit links will be returned in total
+    pub fn get_links_grouped_by_author(
+        &self,
+        index: String,
+        link_tag: Option<LinkTag>,
+        limit: Option<usize>,
+    ) -> IndexResult<HashMap<AgentPubKey, Vec<EntryHash>>> {
+        let links = read_shards(&index, *INDEX_SHARDS, |shard| {
+            get_authored_links(self.path(shard)?.path_entry_hash()?, link_tag.clone())
+        })?;
+        Ok(group_links_by_author(links, *ENFORCE_SPAM_LIMIT, limit))
+    }
+
+    /// Get the distinct agents who have made links on this index; ordered by when each agent first linked. Only link headers are
+    /// read; targets are not fetched. Links which have since been deleted still count towards their author
+    pub fn contributing_authors(&self, index: String) -> IndexResult<Vec<AgentPubKey>> {
+        let links = read_shards(&index, *INDEX_SHARDS, |shard| {
+            get_authored_link_details(self.path(shard)?.path_entry_hash()?, None, true)
+        })?;
+        Ok(distinct_authors(links))
+    }
+
+    /// Get links on this index which were not authored by any of the blocked agents; i.e to hide links from agents blocked by
+    /// moderators. Links are returned in the order they were made and no more than limit links will be returned
+    pub fn get_links_excluding(
+        &self,
+        index: String,
+        link_tag: Option<LinkTag>,
+        blocked: &[AgentPubKey],
+        limit: Option<usize>,
+    ) -> IndexResult<Vec<Link>> {
+        let links = read_shards(&index, *INDEX_SHARDS, |shard| {
+            get_authored_links(self.path(shard)?.path_entry_hash()?, link_tag.clone())
+        })?;
+        Ok(exclude_authors(links, blocked, limit))
+    }
+
+    /// Count the links on this index by link tag; i.e to show "12 posts, 40 reactions" without loading anything. Only the links
+    /// of this index are fetched; targets are not dereferenced
+    pub fn links_summary(&self, index: String) -> IndexResult<HashMap<LinkTag, usize>> {
+        let links = read_shards(&index, *INDEX_SHARDS, |shard| {
+            Ok(get_links(self.path(shard)?.path_entry_hash()?, None)?)
+        })?;
+        Ok(count_links_by_tag(&links))
+    }
+
+    /// Get a page of links on this index. Links are ordered by timestamp then link hash so that successive offsets return
+    /// non overlapping pages with no gaps; as long as no links are added to the index between calls
+    pub fn get_links_paged(
+        &self,
+        index: String,
+        link_tag: Option<LinkTag>,
+        offset: usize,
+        limit: usize,
+    ) -> IndexResult<Vec<Link>> {
+        let links = self.get_shard_links(&index, link_tag)?;
+        Ok(page_links(links, offset, limit))
+    }
+
+    /// Iterate over the targets of links on this index loaded as T, in the order links were made. Links are fetched up front but
+    /// each target is only fetched when the iterator is advanced; so callers can stop early without paying to load a whole index.
+    /// Targets which cannot be resolved are skipped
+    pub fn iter_entries<T: TryFrom<SerializedBytes, Error = SerializedBytesError>>(
+        &self,
+        index: String,
+        link_tag: Option<LinkTag>,
+    ) -> IndexResult<impl Iterator<Item = IndexResult<T>>> {
+        let mut links = self.get_shard_links(&index, link_tag)?;
+        sort_links(&mut links);
+        Ok(load_lazily(
+            links.into_iter().map(|link| link.target).collect(),
+            |target| match get_target_with_retry(target)? {
+                Some(element) => Ok(Some(element.entry().to_app_option::<T>()?.ok_or(
+                    IndexError::InternalError("Expected element to contain app entry data"),
+                )?)),
+                None => Ok(None),
+            },
+        ))
+    }
+
+    /// Get links on this index whose targets have an entry_time() between from (inclusive) & until (exclusive). Useful with large
+    /// MAX_CHUNK_INTERVAL's when only a slice of an index is needed. Targets are fetched to read their time; targets which cannot be
+    /// resolved or deserialized to T are skipped. Links are returned in the order they were made & fetching stops once limit is reached
+    pub fn get_links_in_window<
+        T: TryFrom<SerializedBytes, Error = SerializedBytesError> + IndexableEntry,
+    >(
+        &self,
+        index: String,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+        link_tag: Option<LinkTag>,
+        limit: Option<usize>,
+    ) -> IndexResult<Vec<Link>> {
+        let links = self.get_shard_links(&index, link_tag)?;
+        filter_links_in_window(links, &from, &until, limit, |link| {
+            Ok(match get_target_with_retry(link.target.clone())? {
+                Some(element) => match element.entry().to_app_option::<T>() {
+                    Ok(Some(entry)) => Some(entry.entry_time()),
+                    _ => None,
+                },
+                None => None,
+            })
+        })
+    }
 }
 
-/// Get current index using sys_time as source for time
+/// Get current index using sys_time as source for time. The newest index under the current time path of any shard is returned,
+/// as found in the first shard it exists in
 pub fn get_current_index(index: String) -> IndexResult<Option<Path>> {
-    //Running with the asumption here that sys_time is always UTC
-    let now = sys_time()?.as_seconds_and_nanos();
-    let now = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(now.0, now.1), Utc);
+    let indexes = read_shards(&index, *INDEX_SHARDS, |shard| {
+        //Time path for current chunk is memoized so repeated calls within one chunk do not rebuild it
+        let time_path = get_current_time_path(shard)?;
+        get_children_paths(&time_path)?
+            .into_iter()
+            .map(|path| Ok((Index::try_from(path.clone())?, path)))
+            .collect()
+    })?;
 
-    //Create current time path
-    let mut time_path = vec![Component::try_from(
+    match newest_first(indexes).into_iter().next() {
+        Some((_, link)) => load_path(link.path_entry_hash()?),
+        None => Ok(None),
+    }
+}
+
+/// Get the newest index which exists in the time tree of any shard; whether or not it holds any links
+pub(crate) fn get_newest_chunk(index: String) -> IndexResult<Option<Index>> {
+    let newest = read_shards(&index, *INDEX_SHARDS, |shard| {
+        Ok(get_newest_shard_chunk(shard)?.into_iter().collect())
+    })?;
+    Ok(newest.into_iter().max())
+}
+
+/// Get the newest index which exists in the time tree of a single shard
+fn get_newest_shard_chunk(index: String) -> IndexResult<Option<Index>> {
+    let root = Path::from(vec![Component::from(
         StringIndex(index).get_sb()?.bytes().to_owned(),
-    )?];
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &now, IndexType::Year)?;
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &now, IndexType::Month)?;
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &now, IndexType::Day)?;
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &now, IndexType::Hour)?;
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &now, IndexType::Minute)?;
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &now, IndexType::Second)?;
-    let time_path = Path::from(time_path);
-
-    let indexes = time_path.children_paths()?;
-    let ser_path = indexes
-        .clone()
+    )]);
+    let depth = time_path_depth(&INDEX_DEPTH).ok_or(IndexError::InternalError(
+        "Index depth must contain at least one level",
+    ))?;
+    let time_path = match find_newest_time_path(root, depth + 1)? {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let indexes = get_children_paths(&time_path)?
         .into_iter()
-        .map(|path| Ok(Index::try_from(path)?.from))
-        .collect::<IndexResult<Vec<Duration>>>()?;
-    let permutation = permutation::sort_by(&ser_path[..], |a, b| a.partial_cmp(&b).unwrap());
-    let mut ordered_indexes = permutation.apply_slice(&indexes[..]);
-    ordered_indexes.reverse();
+        .map(|path| Ok((Index::try_from(path.clone())?, path)))
+        .collect::<IndexResult<Vec<(Index, Path)>>>()?;
+    Ok(newest_first(indexes)
+        .into_iter()
+        .next()
+        .map(|(chunk, _)| chunk))
+}
 
-    match ordered_indexes.pop() {
-        Some(link) => match get(link.path_entry_hash()?, GetOptions::latest())? {
-            Some(chunk) => Ok(Some(chunk.entry().to_app_option()?.ok_or(
-                IndexError::InternalError("Expected element to contain app entry data"),
-            )?)),
-            None => Ok(None),
-        },
-        None => Ok(None),
+/// Create chunk along with every chunk between the newest existing chunk & chunk so that the sequence of chunks has no gaps.
+/// Errors without creating anything if more than max_backfill chunks are missing. If chunk is not newer than the newest existing
+/// chunk only chunk is created. Returns created chunks oldest first
+pub(crate) fn create_backfilled(
+    index: String,
+    chunk: Index,
+    max_backfill: usize,
+) -> IndexResult<Vec<Index>> {
+    let newest = get_newest_chunk(index.clone())?;
+    let mut chunks = missing_chunks(newest.as_ref(), &chunk, &MAX_CHUNK_INTERVAL, &GENESIS)?;
+    if chunks.len() > max_backfill {
+        return Err(IndexError::RequestError(
+            "More chunks are missing than max backfill allows",
+        ));
+    };
+    chunks.push(chunk);
+    Index::create_many(index, &chunks)?;
+    Ok(chunks)
+}
+
+/// Chunks which sit strictly between newest & chunk; oldest first. Empty if there is no newest chunk or chunk is not newer than it
+pub(crate) fn missing_chunks(
+    newest: Option<&Index>,
+    chunk: &Index,
+    interval: &Duration,
+    genesis: &Duration,
+) -> IndexResult<Vec<Index>> {
+    let mut out = vec![];
+    let mut next = match newest {
+        Some(newest) if newest.from < chunk.from => newest.until,
+        _ => return Ok(out),
+    };
+    while next < chunk.from {
+        let missing = index_for_timestamp(next, interval, genesis)?;
+        next = missing.until;
+        out.push(missing);
+    }
+    Ok(out)
+}
+
+/// Order chunks found under a time path newest first. Several chunks can hang from one time path when the chunk interval
+/// is smaller than the deepest time path level; chunks are compared by their start time rather than the order of their path links
+pub(crate) fn newest_first<P>(mut chunks: Vec<(Index, P)>) -> Vec<(Index, P)> {
+    chunks.sort_by(|(a, _), (b, _)| b.cmp(a));
+    chunks
+}
+
+/// Traverses time tree following latest time links until it finds the latest index which contains links.
+/// If the newest index has no links (i.e they have been removed) the search will continue onto the next newest index/path
+pub fn get_latest_index(
+    index: String,
+    link_tag: Option<LinkTag>,
+) -> IndexResult<Option<EntryChunkIndex>> {
+    Ok(get_recent_indexes(index, link_tag, 1)?.pop())
+}
+
+/// Traverses time tree backwards from the latest time links collecting up to chunk_count indexes which contain links.
+/// Indexes without links are skipped. Returned indexes are ordered newest first
+pub fn get_recent_indexes(
+    index: String,
+    link_tag: Option<LinkTag>,
+    chunk_count: usize,
+) -> IndexResult<Vec<EntryChunkIndex>> {
+    if chunk_count == 0 {
+        return Ok(vec![]);
+    };
+    //An index among the newest chunk_count populated indexes is also among the newest chunk_count populated indexes of every
+    //shard it has links in; so reading that many from each shard & merging finds all of them along with all of their links
+    let mut shards = vec![];
+    for shard in shard_index_names(&index, *INDEX_SHARDS) {
+        shards.push(get_recent_shard_indexes(
+            shard,
+            link_tag.clone(),
+            chunk_count,
+        )?);
     }
+    let mut out = merge_shard_results(shards)?;
+    out.sort_by(|a, b| b.index.cmp(&a.index));
+    out.truncate(chunk_count);
+    Ok(out)
 }
 
-/// Traverses time tree following latest time links until it finds the latest index
-pub fn get_latest_index(index: String) -> IndexResult<Option<Path>> {
+/// Collect up to chunk_count populated indexes, newest first, from the time tree of a single shard
+fn get_recent_shard_indexes(
+    index: String,
+    link_tag: Option<LinkTag>,
+    chunk_count: usize,
+) -> IndexResult<Vec<EntryChunkIndex>> {
+    let mut out: Vec<EntryChunkIndex> = vec![];
     // This should also be smarter. We could at the least derive the index & current year and check that for paths before moving
     // to the previous year. This would help remove 2 get_link() calls from the DHT on source Index path & Index + Year path
-    let time_path = Path::from(vec![Component::from(
+    let root = Path::from(vec![Component::from(
         StringIndex(index).get_sb()?.bytes().to_owned(),
     )]);
-    let time_path = find_newest_time_path::<TimeIndex>(time_path, IndexType::Year)?;
-    let time_path = find_newest_time_path::<TimeIndex>(time_path, IndexType::Month)?;
-    let time_path = find_newest_time_path::<TimeIndex>(time_path, IndexType::Day)?;
-    let time_path = find_newest_time_path::<TimeIndex>(time_path, IndexType::Hour)?;
-    let time_path = find_newest_time_path::<TimeIndex>(time_path, IndexType::Minute)?;
-    let time_path = find_newest_time_path::<TimeIndex>(time_path, IndexType::Second)?;
-
-    let indexes = time_path.children_paths()?;
-    let ser_path = indexes
-        .clone()
+    let depth = time_path_depth(&INDEX_DEPTH).ok_or(IndexError::InternalError(
+        "Index depth must contain at least one level",
+    ))?;
+    let mut time_path = match find_newest_time_path(root, depth + 1)? {
+        Some(path) => path,
+        None => return Ok(out),
+    };
+
+    loop {
+        let indexes = get_children_paths(&time_path)?
+            .into_iter()
+            .map(|path| Ok((Index::try_from(path.clone())?, path)))
+            .collect::<IndexResult<Vec<(Index, Path)>>>()?;
+
+        //Candidate indexes under this path are fetched GET_LINKS_CONCURRENCY per host call
+        let mut found = take_populated(
+            indexes,
+            chunk_count - out.len(),
+            *GET_LINKS_CONCURRENCY,
+            |paths| {
+                let bases = paths
+                    .iter()
+                    .map(|path| Ok(path.path_entry_hash()?))
+                    .collect::<IndexResult<Vec<EntryHash>>>()?;
+                get_index_links_batched(bases, link_tag.clone(), false, *GET_LINKS_CONCURRENCY)
+            },
+        )?;
+        out.append(&mut found);
+        if out.len() == chunk_count {
+            return Ok(out);
+        };
+
+        //Move onto the next newest path
+        time_path = match find_previous_time_path(time_path)? {
+            Some(path) => path,
+            None => return Ok(out),
+        };
+    }
+}
+
+/// Collect, newest first, up to count chunks which have links. Chunks are fetched batch_size at a time and fetching stops once count
+/// chunks have been found; so at most one batch more than needed is fetched. fetch_links must return links in the order of the chunks given
+pub(crate) fn take_populated<P, F: FnMut(Vec<P>) -> IndexResult<Vec<Vec<Link>>>>(
+    chunks: Vec<(Index, P)>,
+    count: usize,
+    batch_size: usize,
+    mut fetch_links: F,
+) -> IndexResult<Vec<EntryChunkIndex>> {
+    let mut out = vec![];
+    let mut chunks = newest_first(chunks).into_iter().peekable();
+    while out.len() < count && chunks.peek().is_some() {
+        let (indexes, batch): (Vec<Index>, Vec<P>) =
+            chunks.by_ref().take(batch_size.max(1)).unzip();
+        let links = fetch_links(batch)?;
+        if links.len() != indexes.len() {
+            return Err(IndexError::InternalError(
+                "Expected links for every chunk of batch",
+            ));
+        };
+        out.extend(
+            indexes
+                .into_iter()
+                .zip(links)
+                .filter(|(_index, links)| !links.is_empty())
+                .map(|(index, links)| EntryChunkIndex {
+                    index: index,
+                    links: links,
+                    version: ENTRY_CHUNK_INDEX_VERSION,
+                }),
+        );
+    }
+    out.truncate(count);
+    Ok(out)
+}
+
+/// Traverses time tree following oldest time links until it finds the oldest index which contains links.
+/// Mirror of [`get_latest_index()`]; returns None if no index contains links
+pub(crate) fn get_oldest_index(
+    index: String,
+    link_tag: Option<LinkTag>,
+) -> IndexResult<Option<EntryChunkIndex>> {
+    let oldest = read_shards(&index, *INDEX_SHARDS, |shard| {
+        Ok(get_oldest_shard_index(shard, link_tag.clone())?
+            .into_iter()
+            .collect())
+    })?;
+    //The oldest populated index is also the oldest populated index of every shard it has links in
+    let oldest_index = match oldest.iter().map(|chunk| chunk.index.clone()).min() {
+        Some(oldest_index) => oldest_index,
+        None => return Ok(None),
+    };
+    Ok(merge_shard_results(vec![oldest
         .into_iter()
-        .map(|path| Ok(Index::try_from(path)?.from))
-        .collect::<IndexResult<Vec<Duration>>>()?;
-    let permutation = permutation::sort_by(&ser_path[..], |a, b| a.partial_cmp(&b).unwrap());
-    let mut ordered_indexes: Vec<Path> = permutation.apply_slice(&indexes[..]);
-    ordered_indexes.reverse();
+        .filter(|chunk| chunk.index == oldest_index)
+        .collect()])?
+    .pop())
+}
 
-    //TODO: dont error out if cant find link target; just use next link
-    match ordered_indexes.pop() {
-        Some(link) => match get(link.path_entry_hash()?, GetOptions::latest())? {
-            Some(chunk) => Ok(Some(chunk.entry().to_app_option()?.ok_or(
-                IndexError::InternalError("Expected element to contain app entry data"),
-            )?)),
-            None => Err(IndexError::InternalError(
-                "Expected link target to contain point to an entry",
-            )),
-        },
-        None => Ok(None),
+/// Find the oldest index which contains links in the time tree of a single shard
+fn get_oldest_shard_index(
+    index: String,
+    link_tag: Option<LinkTag>,
+) -> IndexResult<Option<EntryChunkIndex>> {
+    let root = Path::from(vec![Component::from(
+        StringIndex(index).get_sb()?.bytes().to_owned(),
+    )]);
+    let depth = time_path_depth(&INDEX_DEPTH).ok_or(IndexError::InternalError(
+        "Index depth must contain at least one level",
+    ))?;
+    let mut time_path = match find_oldest_time_path(root, depth + 1)? {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    loop {
+        let mut indexes = get_children_paths(&time_path)?
+            .into_iter()
+            .map(|path| Ok((Index::try_from(path.clone())?, path)))
+            .collect::<IndexResult<Vec<(Index, Path)>>>()?;
+        indexes.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (chunk, index_path) in indexes {
+            let links = get_index_links(index_path.path_entry_hash()?, link_tag.clone(), false)?;
+            if links.len() > 0 {
+                return Ok(Some(EntryChunkIndex {
+                    index: chunk,
+                    links: links,
+                    version: ENTRY_CHUNK_INDEX_VERSION,
+                }));
+            };
+        }
+
+        //No populated index under this path; move onto the next oldest path
+        time_path = match find_next_time_path(time_path)? {
+            Some(path) => path,
+            None => return Ok(None),
+        };
     }
 }
 
@@ -134,27 +519,39 @@ pub(crate) fn get_indexes_for_time_span(
     until: DateTime<Utc>,
     index: String,
     link_tag: Option<LinkTag>,
+    include_deleted: bool,
 ) -> IndexResult<Vec<EntryChunkIndex>> {
-    let paths = find_paths_for_time_span(from, until, index)?;
-    //debug!("Got paths after search: {:#?}", paths);
-    let mut out: Vec<EntryChunkIndex> = vec![];
+    get_indexes_for_time_span_where(from, until, index, link_tag, include_deleted, |_index| true)
+}
 
-    for path in paths {
-        let paths = path.children_paths()?;
-        let mut indexes = paths
-            .clone()
-            .into_iter()
-            .map(|path| {
-                let index = Index::try_from(path.clone())?;
-                let entry_chunk_index = EntryChunkIndex {
-                    index: index,
-                    links: get_links(path.path_entry_hash()?, link_tag.clone())?,
-                };
-                Ok(entry_chunk_index)
-            })
-            .collect::<IndexResult<Vec<EntryChunkIndex>>>()?;
-        out.append(&mut indexes);
+/// Get all links that exist for some time period between from -> until on indexes for which chunk_filter returns true.
+/// Links of indexes which are filtered out are never fetched
+pub(crate) fn get_indexes_for_time_span_where<C: Fn(&Index) -> bool>(
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    index: String,
+    link_tag: Option<LinkTag>,
+    include_deleted: bool,
+    chunk_filter: C,
+) -> IndexResult<Vec<EntryChunkIndex>> {
+    let mut shards = vec![];
+    for shard in shard_index_names(&index, *INDEX_SHARDS) {
+        let chunks = find_chunks_for_time_span(from, until, shard)?;
+        //Links of chunks hanging from all found paths are fetched together; GET_LINKS_CONCURRENCY chunks per host call
+        shards.push(fetch_matching_chunks(chunks, &chunk_filter, |paths| {
+            let bases = paths
+                .iter()
+                .map(|path| Ok(path.path_entry_hash()?))
+                .collect::<IndexResult<Vec<EntryHash>>>()?;
+            get_index_links_batched(
+                bases,
+                link_tag.clone(),
+                include_deleted,
+                *GET_LINKS_CONCURRENCY,
+            )
+        })?);
     }
+    let out = merge_shard_results(shards)?;
     //NOTE: untested logic
     let timestamps = out
         .clone()
@@ -168,6 +565,416 @@ pub(crate) fn get_indexes_for_time_span(
     Ok(ordered_indexes)
 }
 
+/// Find the chunks hanging from the time paths covering from -> until, along with the path of each chunk
+pub(crate) fn find_chunks_for_time_span(
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    index: String,
+) -> IndexResult<Vec<(Index, Path)>> {
+    let paths = find_paths_for_time_span(from, until, index)?;
+    //debug!("Got paths after search: {:#?}", paths);
+    let mut chunks = vec![];
+    for path in paths {
+        for path in get_children_paths(&path)? {
+            chunks.push((Index::try_from(path.clone())?, path));
+        }
+    }
+    Ok(chunks)
+}
+
+/// Find the chunks between from & until in the time tree of every shard of index along with the path of each chunk. A chunk which
+/// exists in several shards is returned once for each shard it exists in
+pub(crate) fn find_shard_chunks_for_time_span(
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    index: &str,
+) -> IndexResult<Vec<(Index, Path)>> {
+    read_shards(index, *INDEX_SHARDS, |shard| {
+        find_chunks_for_time_span(from, until, shard)
+    })
+}
+
+/// Get links on the paths of a chunk in each shard it exists in; see [`get_index_links()`]
+pub(crate) fn get_chunk_links(paths: &[Path], link_tag: Option<LinkTag>) -> IndexResult<Vec<Link>> {
+    let mut links = vec![];
+    for path in paths {
+        links.append(&mut get_index_links(
+            path.path_entry_hash()?,
+            link_tag.clone(),
+            false,
+        )?);
+    }
+    Ok(links)
+}
+
+/// Fetch links of each chunk for which chunk_filter returns true. fetch_links is given all matching chunks at once and must return
+/// their links in the same order; chunks which are filtered out are never passed to fetch_links
+pub(crate) fn fetch_matching_chunks<
+    P,
+    C: Fn(&Index) -> bool,
+    F: FnOnce(Vec<P>) -> IndexResult<Vec<Vec<Link>>>,
+>(
+    chunks: Vec<(Index, P)>,
+    chunk_filter: &C,
+    fetch_links: F,
+) -> IndexResult<Vec<EntryChunkIndex>> {
+    let (indexes, chunks): (Vec<Index>, Vec<P>) = chunks
+        .into_iter()
+        .filter(|(index, _)| chunk_filter(index))
+        .unzip();
+    let links = fetch_links(chunks)?;
+    if links.len() != indexes.len() {
+        return Err(IndexError::InternalError(
+            "Expected links for every matching chunk",
+        ));
+    };
+    Ok(indexes
+        .into_iter()
+        .zip(links)
+        .map(|(index, links)| EntryChunkIndex {
+            index: index,
+            links: links,
+            version: ENTRY_CHUNK_INDEX_VERSION,
+        })
+        .collect())
+}
+
+/// Get links for up to budget chunks between from -> until, oldest first, along with a cursor to resume from if chunks remain
+pub(crate) fn get_indexes_for_time_span_within_budget(
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    index: String,
+    link_tag: Option<LinkTag>,
+    budget: usize,
+) -> IndexResult<(Vec<EntryChunkIndex>, Option<PageCursor>)> {
+    let (from_since_epoch, until_since_epoch) =
+        (datetime_to_duration(&from)?, datetime_to_duration(&until)?);
+    //Budget is counted in chunks; a chunk which exists in several shards is counted once
+    let chunks = group_by_chunk(find_shard_chunks_for_time_span(from, until, &index)?);
+    let (scan, next) = select_within_budget(chunks, &from_since_epoch, &until_since_epoch, budget);
+
+    let out = scan
+        .into_iter()
+        .map(|(index, paths)| {
+            Ok(EntryChunkIndex {
+                index: index,
+                links: get_chunk_links(&paths, link_tag.clone())?,
+                version: ENTRY_CHUNK_INDEX_VERSION,
+            })
+        })
+        .collect::<IndexResult<Vec<EntryChunkIndex>>>()?;
+    Ok((
+        out,
+        next.map(|next| PageCursor {
+            from: next.window().0,
+            seen: vec![],
+        }),
+    ))
+}
+
+/// From a set of chunks select, oldest first, up to budget chunks which overlap from -> until. Also returns the next chunk
+/// which would have been selected had the budget allowed
+pub(crate) fn select_within_budget<T>(
+    chunks: Vec<(Index, T)>,
+    from: &Duration,
+    until: &Duration,
+    budget: usize,
+) -> (Vec<(Index, T)>, Option<Index>) {
+    let mut chunks = chunks
+        .into_iter()
+        .filter(|(index, _)| index.until > *from && index.from < *until)
+        .collect::<Vec<(Index, T)>>();
+    chunks.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let next = chunks.get(budget).map(|(index, _)| index.to_owned());
+    chunks.truncate(budget);
+    (chunks, next)
+}
+
+/// Get the targets of links between from & until grouped into bucket_count buckets of equal length by the entry_time() of each
+/// target; see [`bucket_by_time()`]. Each target is loaded once, even if it is linked on several indexes. Targets without an app entry are
+/// skipped; a target whose app entry fails to deserialize to T is an error
+pub(crate) fn get_indexes_bucketed<
+    T: TryFrom<SerializedBytes, Error = SerializedBytesError> + IndexableEntry,
+>(
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    index: String,
+    bucket_count: usize,
+    link_tag: Option<LinkTag>,
+) -> IndexResult<Vec<(DateTime<Utc>, DateTime<Utc>, Vec<EntryHash>)>> {
+    let buckets = bucket_bounds(&from, &until, bucket_count)?;
+    let mut seen = std::collections::HashSet::new();
+    let mut timed = vec![];
+    for chunk in get_indexes_for_time_span(from, until, index, link_tag, false)? {
+        for link in chunk.links {
+            if !seen.insert(link.target.clone()) {
+                continue;
+            };
+            if let Some(element) = get_target_with_retry(link.target.clone())? {
+                if let Some(entry) = element.entry().to_app_option::<T>()? {
+                    timed.push((entry.entry_time(), link.target));
+                };
+            };
+        }
+    }
+    Ok(bucket_by_time(timed, buckets))
+}
+
+/// Get all chunks that exist between from -> until, oldest first. Chunks are decoded from the time tree so no links on chunks are fetched
+pub(crate) fn get_chunks_for_time_span(
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    index: String,
+) -> IndexResult<Vec<Index>> {
+    Ok(
+        group_by_chunk(find_shard_chunks_for_time_span(from, until, &index)?)
+            .into_iter()
+            .map(|(chunk, _paths)| chunk)
+            .collect(),
+    )
+}
+
+/// Fetch indexes between from & until and optionally the targets of their links from the network so that they are held in the
+/// conductors cache. Returns the number of elements which could be resolved
+pub(crate) fn prefetch_span(
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    index: String,
+    link_tag: Option<LinkTag>,
+    include_targets: bool,
+) -> IndexResult<usize> {
+    let mut hashes = vec![];
+    for (_chunk, chunk_path) in find_shard_chunks_for_time_span(from, until, &index)? {
+        let chunk_hash = chunk_path.path_entry_hash()?;
+        if include_targets {
+            hashes.extend(
+                get_index_links(chunk_hash.clone(), link_tag.clone(), false)?
+                    .into_iter()
+                    .map(|link| link.target),
+            );
+        };
+        hashes.push(chunk_hash);
+    }
+    Ok(resolve_all(hashes, |hash| Ok(get(hash, GetOptions::latest())?))?.len())
+}
+
+/// Lazily iterate over indexes which exist between from & until, newest first. Existence of each index is only checked when
+/// the iterator is advanced; an index exists if it exists in any shard of index
+pub(crate) fn chunks_desc(
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    index: String,
+) -> IndexResult<impl Iterator<Item = IndexResult<Index>>> {
+    ChunksDesc::new(
+        datetime_to_duration(&from)?,
+        datetime_to_duration(&until)?,
+        &MAX_CHUNK_INTERVAL,
+        &GENESIS,
+        move |chunk: Index| {
+            for shard in shard_index_names(&index, *INDEX_SHARDS) {
+                if chunk.path(shard)?.exists()? {
+                    return Ok(Some(chunk));
+                };
+            }
+            Ok(None)
+        },
+    )
+}
+
+/// Page links of the chunks from cursor.from -> until by encoded size; see [`page_by_size()`]. Only chunks which exist in the time tree
+/// of some shard are visited, oldest first, and the links of each chunk are only fetched once paging reaches it
+pub(crate) fn get_indexes_for_time_span_sized(
+    cursor: &PageCursor,
+    until: DateTime<Utc>,
+    index: String,
+    link_tag: Option<LinkTag>,
+    max_bytes: usize,
+) -> IndexResult<(Vec<EntryChunkIndex>, Option<PageCursor>)> {
+    let chunks = find_shard_chunks_for_time_span(cursor.from, until, &index)?;
+    page_by_size(group_by_chunk(chunks), cursor, max_bytes, |paths| {
+        get_chunk_links(&paths, link_tag.clone())
+    })
+}
+
+/// Get every interval of the span from..until (from rounded down to the start of its chunk) paired with whether a chunk exists there in
+/// the time tree of index; oldest first. Chunks are never fetched; the links of each time path chunks hang from are fetched once and
+/// searched for the hash of each chunk's path. A chunk exists if it exists in any shard of index
+pub(crate) fn existing_chunks_mask(
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    index: String,
+) -> IndexResult<Vec<(DateTime<Utc>, bool)>> {
+    let chunks = ChunksDesc::new(
+        datetime_to_duration(&from)?,
+        datetime_to_duration(&until)?,
+        &MAX_CHUNK_INTERVAL,
+        &GENESIS,
+        |chunk: Index| Ok(Some(chunk)),
+    )?
+    .take(MAX_MASK_INTERVALS + 1)
+    .collect::<IndexResult<Vec<Index>>>()?;
+    if chunks.len() > MAX_MASK_INTERVALS {
+        return Err(IndexError::RequestError(
+            "Span covers more than MAX_MASK_INTERVALS intervals",
+        ));
+    };
+    let mut shard_chunks = vec![];
+    for shard_index in shard_index_names(&index, *INDEX_SHARDS) {
+        for chunk in chunks.iter() {
+            let mut components = get_chunk_path(shard_index.clone(), chunk)?;
+            let chunk_hash = Path::from(components.clone()).path_entry_hash()?;
+            components.pop();
+            let parent = Path::from(components).path_entry_hash()?;
+            shard_chunks.push((chunk.to_owned(), parent, chunk_hash));
+        }
+    }
+    let mut mask = std::collections::BTreeMap::new();
+    for (chunk, exists) in mark_existing(shard_chunks, |parents| {
+        get_index_links_batched(parents, None, false, *GET_LINKS_CONCURRENCY)
+    })? {
+        *mask.entry(chunk).or_insert(false) |= exists;
+    }
+    Ok(mask
+        .into_iter()
+        .map(|(chunk, exists)| (chunk.start(), exists))
+        .collect())
+}
+
+/// Get links between from & until, keep those matching filter and load their targets as T
+pub(crate) fn get_links_and_load_for_time_span_filtered<
+    T: TryFrom<SerializedBytes, Error = SerializedBytesError>,
+    P: Fn(&LinkData) -> bool,
+>(
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+    index: String,
+    link_tag: Option<LinkTag>,
+    filter: P,
+) -> IndexResult<Vec<T>> {
+    let mut out = vec![];
+    for (_, chunk_paths) in group_by_chunk(find_shard_chunks_for_time_span(from, until, &index)?) {
+        let mut links = vec![];
+        for chunk_path in chunk_paths {
+            links.extend(
+                get_authored_links(chunk_path.path_entry_hash()?, link_tag.clone())?
+                    .into_iter()
+                    .map(|(author, link)| LinkData {
+                        author: author,
+                        target: link.target,
+                        tag: link.tag,
+                        timestamp: link.timestamp,
+                    }),
+            );
+        }
+        out.append(&mut load_matching(
+            links,
+            &filter,
+            link_tag.is_none(),
+            |target| match get_target_with_retry(target)? {
+                Some(element) => Ok(Some(element.entry().to_app_option::<T>()?.ok_or(
+                    IndexError::InternalError("Expected element to contain app entry data"),
+                )?)),
+                None => Ok(None),
+            },
+        )?);
+    }
+    Ok(out)
+}
+
+/// Load the targets of links matching filter, oldest link first. Targets of links which do not match are never loaded. When
+/// dedup_targets is set each target is only loaded once; for the first matching link to it. Targets which do not resolve are skipped
+pub(crate) fn load_matching<
+    T,
+    P: Fn(&LinkData) -> bool,
+    F: FnMut(EntryHash) -> IndexResult<Option<T>>,
+>(
+    mut links: Vec<LinkData>,
+    filter: &P,
+    dedup_targets: bool,
+    load: F,
+) -> IndexResult<Vec<T>> {
+    links.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    let mut seen = std::collections::HashSet::new();
+    let targets = links
+        .into_iter()
+        .filter(|link| filter(link))
+        .filter(|link| !dedup_targets || seen.insert(link.target.clone()))
+        .map(|link| link.target)
+        .collect();
+    resolve_all(targets, load)
+}
+
+/// Merge results of two time indexes into one list ordered by index start time. Sort is stable so old results are placed
+/// before new results starting at the same time
+pub(crate) fn merge_index_results(
+    old: Vec<EntryChunkIndex>,
+    new: Vec<EntryChunkIndex>,
+) -> Vec<EntryChunkIndex> {
+    let mut out = old;
+    out.extend(new);
+    out.sort_by(|a, b| a.index.cmp(&b.index));
+    out
+}
+
+/// Take at most limit links from chunks according to distribution. Chunks are visited oldest first & links of each chunk
+/// are taken in the order they were made. Chunks from which no links are taken are dropped
+pub(crate) fn distribute_links(
+    mut chunks: Vec<EntryChunkIndex>,
+    limit: usize,
+    distribution: &Distribution,
+) -> Vec<EntryChunkIndex> {
+    chunks.sort_by(|a, b| a.index.cmp(&b.index));
+    let mut links = chunks
+        .iter_mut()
+        .map(|chunk| {
+            let mut links = std::mem::take(&mut chunk.links);
+            sort_links(&mut links);
+            links.into_iter()
+        })
+        .collect::<Vec<_>>();
+    let mut taken = 0;
+    match distribution {
+        Distribution::Fill => {
+            for (chunk, links) in chunks.iter_mut().zip(links) {
+                chunk.links = links.take(limit - taken).collect();
+                taken += chunk.links.len();
+            }
+        }
+        Distribution::RoundRobin => loop {
+            let mut took_any = false;
+            for (chunk, links) in chunks.iter_mut().zip(links.iter_mut()) {
+                if taken == limit {
+                    break;
+                }
+                if let Some(link) = links.next() {
+                    chunk.links.push(link);
+                    taken += 1;
+                    took_any = true;
+                }
+            }
+            if !took_any || taken == limit {
+                break;
+            }
+        },
+    }
+    chunks.retain(|chunk| !chunk.links.is_empty());
+    chunks
+}
+
+/// Resolve each of a set of hashes; skipping those which cannot be resolved
+pub(crate) fn resolve_all<T, F: FnMut(EntryHash) -> IndexResult<Option<T>>>(
+    hashes: Vec<EntryHash>,
+    resolve: F,
+) -> IndexResult<Vec<T>> {
+    Ok(hashes
+        .into_iter()
+        .map(resolve)
+        .collect::<IndexResult<Vec<Option<T>>>>()?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
 /// Get all links that exist for some time period between from -> until
 pub(crate) fn get_links_for_time_span(
     index: String,
@@ -183,25 +990,15 @@ pub(crate) fn get_links_for_time_span(
     };
 
     if limit.is_some() {
-        debug!("hc_time_index::get_links_for_time_span: WARNING: Limit not supported on Bfs strategy. All links between bounds will be retrieved and returned");
+        index_debug!("hc_time_index::get_links_for_time_span: WARNING: Limit not supported on Bfs strategy. All links between bounds will be retrieved and returned");
     };
-    let paths = find_paths_for_time_span(from, until, index)?;
-    //debug!("Got paths after search: {:#?}", paths);
     let mut out: Vec<Link> = vec![];
-    for path in paths {
-        let paths = path.children_paths()?;
-        let mut indexes = paths
-            .clone()
-            .into_iter()
-            .map(|path| {
-                let links = get_links(path.path_entry_hash()?, link_tag.clone())?;
-                Ok(links)
-            })
-            .collect::<IndexResult<Vec<Vec<Link>>>>()?
-            .into_iter()
-            .flatten()
-            .collect();
-        out.append(&mut indexes);
+    for (_chunk, path) in find_shard_chunks_for_time_span(from, until, &index)? {
+        out.append(&mut get_index_links(
+            path.path_entry_hash()?,
+            link_tag.clone(),
+            false,
+        )?);
     }
     //TODO: do sort based on path value
     match order {
@@ -234,40 +1031,34 @@ pub(crate) fn get_links_and_load_for_time_span<
 
     Ok(match strategy {
         SearchStrategy::Bfs => {
-            let paths = find_paths_for_time_span(from, until, index)?;
+            let chunks = find_shard_chunks_for_time_span(from, until, &index)?;
             let mut results: Vec<T> = vec![];
 
-            for path in paths {
-                let paths = path.children_paths()?;
-                let mut indexes = paths
-                    .clone()
-                    .into_iter()
-                    .map(|path_child| {
-                        let links = get_links(path_child.path_entry_hash()?, link_tag.clone())?;
-                        Ok(links)
-                    })
-                    .collect::<IndexResult<Vec<Vec<Link>>>>()?
-                    .into_iter()
-                    .flatten()
-                    .map(|link| match get(link.target, GetOptions::latest())? {
-                        Some(chunk) => Ok(Some(chunk.entry().to_app_option::<T>()?.ok_or(
-                            IndexError::InternalError("Expected element to contain app entry data"),
-                        )?)),
-                        None => Ok(None),
-                    })
-                    .filter_map(|val| {
-                        if val.is_ok() {
-                            let val = val.unwrap();
-                            if val.is_some() {
-                                Some(Ok(val.unwrap()))
+            for (_chunk, path) in chunks {
+                let mut indexes =
+                    get_index_links(path.path_entry_hash()?, link_tag.clone(), false)?
+                        .into_iter()
+                        .map(|link| match get_target_with_retry(link.target)? {
+                            Some(chunk) => Ok(Some(chunk.entry().to_app_option::<T>()?.ok_or(
+                                IndexError::InternalError(
+                                    "Expected element to contain app entry data",
+                                ),
+                            )?)),
+                            None => Ok(None),
+                        })
+                        .filter_map(|val| {
+                            if val.is_ok() {
+                                let val = val.unwrap();
+                                if val.is_some() {
+                                    Some(Ok(val.unwrap()))
+                                } else {
+                                    None
+                                }
                             } else {
-                                None
+                                Some(Err(val.err().unwrap()))
                             }
-                        } else {
-                            Some(Err(val.err().unwrap()))
-                        }
-                    })
-                    .collect::<IndexResult<Vec<T>>>()?;
+                        })
+                        .collect::<IndexResult<Vec<T>>>()?;
                 results.append(&mut indexes);
             }
             match order {
@@ -282,14 +1073,492 @@ pub(crate) fn get_links_and_load_for_time_span<
             results
         }
         SearchStrategy::Dfs => {
-            make_dfs_search::<T>(index, &from, &until, &order, limit, link_tag)?
+            //Each shard returns its first limit results in order; so the first limit of all shards results are the first limit overall
+            let mut results = read_shards(&index, *INDEX_SHARDS, |shard| {
+                make_dfs_search::<T>(shard, &from, &until, &order, limit, link_tag.clone())
+            })?;
+            if *INDEX_SHARDS > 1 {
+                match order {
+                    Order::Desc => {
+                        results
+                            .sort_by(|a, b| b.entry_time().partial_cmp(&a.entry_time()).unwrap());
+                    }
+                    Order::Asc => {
+                        results
+                            .sort_by(|a, b| a.entry_time().partial_cmp(&b.entry_time()).unwrap());
+                    }
+                };
+                if let Some(limit) = limit {
+                    results.truncate(limit);
+                };
+            };
+            results
         }
     })
 }
 
-/// Takes a timestamp and creates an index path
+/// Takes a timestamp and creates an index path. Only path nodes which do not already exist are created
 pub(crate) fn create_for_timestamp(index: String, time: DateTime<Utc>) -> IndexResult<Path> {
-    let time_index = get_index_for_timestamp(time);
+    let time_index = get_index_for_timestamp(time)?;
     let path = time_index.new(index)?;
     Ok(path)
 }
+
+mod methods_tests {
+    #[test]
+    fn test_select_within_budget() {
+        use crate::entries::Index;
+        use crate::methods::select_within_budget;
+        use std::time::Duration;
+
+        let interval = Duration::from_secs(10);
+        let index = |i: u64| Index {
+            from: Duration::from_secs(1_628_776_800) + interval * i as u32,
+            until: Duration::from_secs(1_628_776_800) + interval * (i + 1) as u32,
+        };
+        //Chunks are received out of order & include chunks outside of the queried span
+        let mut chunks = (0..12)
+            .map(|i| (index(i), i))
+            .collect::<Vec<(Index, u64)>>();
+        chunks.reverse();
+        let until = index(11).from;
+
+        let mut from = index(1).from;
+        let mut scanned = vec![];
+        let mut pages = 0;
+        loop {
+            let (page, next) = select_within_budget(chunks.clone(), &from, &until, 4);
+            assert!(page.len() <= 4);
+            scanned.extend(page.into_iter().map(|(_, i)| i));
+            pages += 1;
+            match next {
+                Some(next) => from = next.from,
+                None => break,
+            }
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(scanned, (1..11).collect::<Vec<u64>>());
+
+        //Budget covering span returns no cursor
+        let (page, next) = select_within_budget(chunks, &index(1).from, &until, 10);
+        assert_eq!(page.len(), 10);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn test_resolve_all() {
+        use crate::entries::Index;
+        use crate::methods::resolve_all;
+        use hdk::prelude::*;
+        use std::collections::HashMap;
+        use std::time::Duration;
+
+        let index = |i: u64| Index {
+            from: Duration::from_secs(i * 10),
+            until: Duration::from_secs((i + 1) * 10),
+        };
+        let hash = |i: u8| EntryHash::from_raw_36(vec![i; 36]);
+        let dht = vec![(hash(1), index(1)), (hash(3), index(3))]
+            .into_iter()
+            .collect::<HashMap<EntryHash, Index>>();
+
+        //Hash 2 is missing & should be omitted
+        let resolved = resolve_all(vec![hash(1), hash(2), hash(3)], |hash| {
+            Ok(dht.get(&hash).cloned())
+        })
+        .unwrap();
+        assert_eq!(resolved, vec![index(1), index(3)]);
+
+        //Errors are not swallowed
+        let resolved: Result<Vec<Index>, _> = resolve_all(vec![hash(1)], |_hash| {
+            Err(crate::errors::IndexError::InternalError(
+                "Could not resolve",
+            ))
+        });
+        assert!(resolved.is_err());
+    }
+
+    #[test]
+    fn test_get_indexes_for_time_span_across() {
+        use crate::entries::Index;
+        use crate::errors::IndexResult;
+        use crate::test_utils::test_link;
+        use crate::{get_indexes_for_time_span_across, EntryChunkIndex, IndexBackend};
+        use chrono::{DateTime, TimeZone, Utc};
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        //Backend holding indexes of a fixed interval; one link per index
+        struct MockBackend {
+            interval: Duration,
+            starts: Vec<u64>,
+        }
+        impl IndexBackend for MockBackend {
+            fn get_indexes_for_time_span(
+                &self,
+                from: DateTime<Utc>,
+                until: DateTime<Utc>,
+            ) -> IndexResult<Vec<EntryChunkIndex>> {
+                Ok(self
+                    .starts
+                    .iter()
+                    .rev()
+                    .filter(|start| {
+                        **start as i64 >= from.timestamp() && (**start as i64) < until.timestamp()
+                    })
+                    .map(|start| EntryChunkIndex {
+                        index: Index {
+                            from: Duration::from_secs(*start),
+                            until: Duration::from_secs(*start) + self.interval,
+                        },
+                        links: vec![Link {
+                            timestamp: Timestamp::from_micros(*start as i64 * 1_000_000),
+                            ..test_link((*start % 256) as u8, "test")
+                        }],
+                        version: crate::ENTRY_CHUNK_INDEX_VERSION,
+                    })
+                    .collect())
+            }
+        }
+
+        let genesis = 1_628_776_800;
+        //Old DNA used hour chunks until migration three hours after genesis; new DNA uses ten minute chunks
+        let old = MockBackend {
+            interval: Duration::from_secs(3600),
+            starts: vec![genesis, genesis + 3600, genesis + 7200],
+        };
+        let new = MockBackend {
+            interval: Duration::from_secs(600),
+            starts: vec![genesis + 10800, genesis + 11400, genesis + 12000],
+        };
+        let merged = get_indexes_for_time_span_across(
+            &old,
+            &new,
+            Utc.timestamp(genesis as i64, 0),
+            Utc.timestamp(genesis as i64 + 4 * 3600, 0),
+        )
+        .unwrap();
+        assert_eq!(
+            merged
+                .iter()
+                .map(|chunk| chunk.index.from.as_secs() - genesis)
+                .collect::<Vec<u64>>(),
+            vec![0, 3600, 7200, 10800, 11400, 12000]
+        );
+        assert_eq!(
+            merged[2].index.until - merged[2].index.from,
+            Duration::from_secs(3600)
+        );
+        assert_eq!(
+            merged[3].index.until - merged[3].index.from,
+            Duration::from_secs(600)
+        );
+
+        //Closures can be used as backends
+        let empty = |_from: DateTime<Utc>,
+                     _until: DateTime<Utc>|
+         -> IndexResult<Vec<EntryChunkIndex>> { Ok(vec![]) };
+        let merged = get_indexes_for_time_span_across(
+            &empty,
+            &new,
+            Utc.timestamp(genesis as i64 + 11400, 0),
+            Utc.timestamp(genesis as i64 + 4 * 3600, 0),
+        )
+        .unwrap();
+        assert_eq!(merged.len(), 2);
+        assert!(merged[0].index < merged[1].index);
+    }
+
+    #[test]
+    fn test_distribute_links() {
+        use crate::entries::Index;
+        use crate::methods::distribute_links;
+        use crate::test_utils::test_link;
+        use crate::{Distribution, EntryChunkIndex};
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        //Link n of chunk i targets hash i * 10 + n
+        let chunk = |i: u64, count: u8| EntryChunkIndex {
+            index: Index {
+                from: Duration::from_secs(i * 10),
+                until: Duration::from_secs((i + 1) * 10),
+            },
+            links: (0..count)
+                .map(|n| Link {
+                    timestamp: Timestamp::from_micros((i * 10 + n as u64) as i64 * 1_000_000),
+                    ..test_link(i as u8 * 10 + n, "test")
+                })
+                .rev()
+                .collect(),
+            version: crate::ENTRY_CHUNK_INDEX_VERSION,
+        };
+        let targets = |chunks: &Vec<EntryChunkIndex>| {
+            chunks
+                .iter()
+                .map(|chunk| {
+                    chunk
+                        .links
+                        .iter()
+                        .map(|link| link.target.get_raw_36()[0])
+                        .collect::<Vec<u8>>()
+                })
+                .collect::<Vec<Vec<u8>>>()
+        };
+        //First chunk is over full; chunks given newest first
+        let chunks = vec![chunk(3, 2), chunk(2, 1), chunk(1, 8)];
+
+        //Fill gives whole limit to the over full chunk
+        let filled = distribute_links(chunks.clone(), 4, &Distribution::Fill);
+        assert_eq!(targets(&filled), vec![vec![10, 11, 12, 13]]);
+
+        //Round robin spreads limit across chunks, oldest first
+        let spread = distribute_links(chunks.clone(), 4, &Distribution::RoundRobin);
+        assert_eq!(targets(&spread), vec![vec![10, 11], vec![20], vec![30]]);
+        let spread = distribute_links(chunks.clone(), 5, &Distribution::RoundRobin);
+        assert_eq!(targets(&spread), vec![vec![10, 11], vec![20], vec![30, 31]]);
+
+        //Both return all links when limit is not reached
+        let filled = distribute_links(chunks.clone(), 20, &Distribution::Fill);
+        let spread = distribute_links(chunks.clone(), 20, &Distribution::RoundRobin);
+        assert_eq!(targets(&filled), targets(&spread));
+        assert_eq!(filled.iter().map(|c| c.links.len()).sum::<usize>(), 11);
+
+        assert!(distribute_links(chunks, 0, &Distribution::RoundRobin).is_empty());
+        assert_eq!(Distribution::default(), Distribution::Fill);
+    }
+
+    #[test]
+    fn test_load_matching() {
+        use crate::methods::load_matching;
+        use crate::LinkData;
+        use hdk::prelude::*;
+        use std::cell::RefCell;
+
+        let alice = AgentPubKey::from_raw_36(vec![1; 36]);
+        let bob = AgentPubKey::from_raw_36(vec![2; 36]);
+        let link = |author: &AgentPubKey, target: u8, tag: &str| LinkData {
+            author: author.clone(),
+            target: EntryHash::from_raw_36(vec![target; 36]),
+            tag: LinkTag::new(tag),
+            timestamp: Timestamp::from_micros(target as i64),
+        };
+        let links = vec![
+            link(&bob, 4, "reaction:like"),
+            link(&alice, 1, "post"),
+            link(&bob, 2, "post"),
+            link(&alice, 3, "reaction:like"),
+            link(&alice, 5, "reaction:share"),
+        ];
+        let loaded = RefCell::new(vec![]);
+        let load = |target: EntryHash| {
+            let value = target.get_raw_36()[0];
+            loaded.borrow_mut().push(value);
+            Ok(Some(value))
+        };
+
+        //By author; bobs targets are never loaded
+        let by_alice = load_matching(
+            links.clone(),
+            &|data: &LinkData| data.author == alice,
+            true,
+            load,
+        )
+        .unwrap();
+        assert_eq!(by_alice, vec![1, 3, 5]);
+        assert_eq!(*loaded.borrow(), vec![1, 3, 5]);
+
+        //By tag prefix
+        loaded.borrow_mut().clear();
+        let reactions = load_matching(
+            links.clone(),
+            &|data: &LinkData| data.tag.0.starts_with(b"reaction:"),
+            true,
+            load,
+        )
+        .unwrap();
+        assert_eq!(reactions, vec![3, 4, 5]);
+        assert_eq!(*loaded.borrow(), vec![3, 4, 5]);
+
+        //Target linked under two tags is loaded once when de-duplicating
+        loaded.borrow_mut().clear();
+        let mut duplicated = links.clone();
+        duplicated.push(link(&alice, 1, "reaction:like"));
+        let all = load_matching(duplicated.clone(), &|_data: &LinkData| true, true, load).unwrap();
+        assert_eq!(all, vec![1, 2, 3, 4, 5]);
+        let all = load_matching(duplicated, &|_data: &LinkData| true, false, load).unwrap();
+        assert_eq!(all, vec![1, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_fetch_matching_chunks() {
+        use crate::entries::Index;
+        use crate::methods::fetch_matching_chunks;
+        use crate::test_utils::test_link;
+        use crate::utils::index_for_timestamp;
+        use chrono::{Datelike, Weekday};
+        use std::cell::RefCell;
+        use std::time::Duration;
+
+        //Six hour chunks over one week, starting Monday 2021-08-09
+        let interval = Duration::from_secs(6 * 3600);
+        let monday = Duration::from_secs(1_628_467_200);
+        let chunks = (0..28)
+            .map(|i| {
+                let index =
+                    index_for_timestamp(monday + interval * i, &interval, &Duration::from_secs(0))
+                        .unwrap();
+                (index, i as u8)
+            })
+            .collect::<Vec<(Index, u8)>>();
+        assert_eq!(chunks[0].0.start().weekday(), Weekday::Mon);
+
+        let fetched = RefCell::new(vec![]);
+        let fetch = |chunks: Vec<u8>| {
+            Ok(chunks
+                .into_iter()
+                .map(|chunk| {
+                    fetched.borrow_mut().push(chunk);
+                    vec![test_link(chunk, "test")]
+                })
+                .collect())
+        };
+        let weekdays =
+            |index: &Index| !matches!(index.start().weekday(), Weekday::Sat | Weekday::Sun);
+
+        let found = fetch_matching_chunks(chunks.clone(), &weekdays, fetch).unwrap();
+        assert_eq!(found.len(), 20);
+        assert!(found.iter().all(|chunk| weekdays(&chunk.index)));
+        //Weekend chunks were never fetched
+        assert_eq!(*fetched.borrow(), (0..20).collect::<Vec<u8>>());
+
+        fetched.borrow_mut().clear();
+        let all = fetch_matching_chunks(chunks, &|_index: &Index| true, fetch).unwrap();
+        assert_eq!(all.len(), 28);
+        assert_eq!(fetched.borrow().len(), 28);
+    }
+
+    #[test]
+    fn test_newest_first() {
+        use crate::entries::Index;
+        use crate::methods::newest_first;
+        use std::time::Duration;
+
+        let chunk = |from: u64| Index {
+            from: Duration::from_secs(from),
+            until: Duration::from_secs(from + 10),
+        };
+        //Two chunks linked under the same minute path; the later chunk was linked first
+        let ordered = newest_first(vec![
+            (chunk(1_628_776_870), "later"),
+            (chunk(1_628_776_860), "earlier"),
+        ]);
+        assert_eq!(ordered[0], (chunk(1_628_776_870), "later"));
+
+        let ordered = newest_first(vec![
+            (chunk(1_628_776_860), "earlier"),
+            (chunk(1_628_776_880), "latest"),
+            (chunk(1_628_776_870), "later"),
+        ]);
+        assert_eq!(
+            ordered
+                .into_iter()
+                .map(|(_, label)| label)
+                .collect::<Vec<&str>>(),
+            vec!["latest", "later", "earlier"]
+        );
+        assert!(newest_first::<&str>(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_take_populated() {
+        use crate::entries::Index;
+        use crate::methods::take_populated;
+        use crate::test_utils::test_link;
+        use std::cell::RefCell;
+        use std::time::Duration;
+
+        let chunk = |i: u8| Index {
+            from: Duration::from_secs(i as u64 * 10),
+            until: Duration::from_secs(i as u64 * 10 + 10),
+        };
+        //Chunks 0..10 linked in arbitrary order; chunks 8 & 6 have had their links removed
+        let chunks = vec![3, 9, 0, 8, 5, 1, 7, 2, 6, 4]
+            .into_iter()
+            .map(|i| (chunk(i), i))
+            .collect::<Vec<(Index, u8)>>();
+        let fetched = RefCell::new(vec![]);
+        let fetch = |batch: Vec<u8>| {
+            fetched.borrow_mut().push(batch.clone());
+            Ok(batch
+                .into_iter()
+                .map(|i| {
+                    if i == 8 || i == 6 {
+                        vec![]
+                    } else {
+                        vec![test_link(i, "test")]
+                    }
+                })
+                .collect())
+        };
+
+        let found = take_populated(chunks.clone(), 3, 3, fetch).unwrap();
+        assert_eq!(
+            found
+                .iter()
+                .map(|chunk| chunk.index.clone())
+                .collect::<Vec<Index>>(),
+            vec![chunk(9), chunk(7), chunk(5)]
+        );
+        assert_eq!(found[1].links, vec![test_link(7, "test")]);
+        //Newest chunks are fetched in batches & fetching stops once enough populated chunks are found
+        assert_eq!(*fetched.borrow(), vec![vec![9, 8, 7], vec![6, 5, 4]]);
+
+        fetched.borrow_mut().clear();
+        let found = take_populated(chunks.clone(), 20, 4, fetch).unwrap();
+        assert_eq!(found.len(), 8);
+        assert_eq!(fetched.borrow().len(), 3);
+        assert!(take_populated(chunks, 0, 3, fetch).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_missing_chunks() {
+        use crate::methods::missing_chunks;
+        use crate::utils::index_for_timestamp;
+        use std::time::Duration;
+
+        let interval = Duration::from_secs(10);
+        let genesis = Duration::from_secs(1_628_776_800);
+        let chunk = |offset: u32| {
+            index_for_timestamp(genesis + interval * offset, &interval, &genesis).unwrap()
+        };
+
+        //Chunk created several intervals ahead of the newest chunk
+        let missing = missing_chunks(Some(&chunk(2)), &chunk(7), &interval, &genesis).unwrap();
+        assert_eq!(missing, vec![chunk(3), chunk(4), chunk(5), chunk(6)]);
+        for pair in missing.windows(2) {
+            assert_eq!(pair[0].until, pair[1].from);
+        }
+
+        //Chunk directly after newest chunk, the newest chunk itself or older than newest
+        assert!(
+            missing_chunks(Some(&chunk(6)), &chunk(7), &interval, &genesis)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            missing_chunks(Some(&chunk(7)), &chunk(7), &interval, &genesis)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            missing_chunks(Some(&chunk(9)), &chunk(7), &interval, &genesis)
+                .unwrap()
+                .is_empty()
+        );
+        //No chunks exist yet
+        assert!(missing_chunks(None, &chunk(7), &interval, &genesis)
+            .unwrap()
+            .is_empty());
+    }
+}
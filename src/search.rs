@@ -1,143 +1,452 @@
-use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Timelike, Utc};
+use chrono::{DateTime, Datelike, NaiveDateTime, Timelike, Utc};
 use hdk::{hash_path::path::Component, prelude::*};
 
-use crate::entries::IndexType;
+use crate::entries::{IndexType, TimeIndex};
 use crate::errors::{IndexError, IndexResult};
-use crate::INDEX_DEPTH;
+use crate::utils::{checked_naivedatetime, get_children_paths};
+use crate::{Order, INDEX_DEPTH};
 
 pub(crate) fn get_naivedatetime(
     from: &DateTime<Utc>,
     until: &DateTime<Utc>,
     index_type: &IndexType,
-) -> Option<(NaiveDateTime, NaiveDateTime)> {
-    match index_type {
-        IndexType::Year => Some((
-            NaiveDate::from_ymd(from.year(), 1, 1).and_hms(1, 1, 1),
-            NaiveDate::from_ymd(until.year(), 1, 1).and_hms(1, 1, 1),
-        )),
-        IndexType::Month => Some((
-            NaiveDate::from_ymd(from.year(), from.month(), 1).and_hms(1, 1, 1),
-            NaiveDate::from_ymd(until.year(), until.month(), 1).and_hms(1, 1, 1),
-        )),
-        IndexType::Day => Some((
-            NaiveDate::from_ymd(from.year(), from.month(), from.day()).and_hms(1, 1, 1),
-            NaiveDate::from_ymd(until.year(), until.month(), until.day()).and_hms(1, 1, 1),
-        )),
+) -> IndexResult<Option<(NaiveDateTime, NaiveDateTime)>> {
+    let (from, until) = match index_type {
+        IndexType::Year => (
+            checked_naivedatetime(from.year(), 1, 1, 1, 1, 1)?,
+            checked_naivedatetime(until.year(), 1, 1, 1, 1, 1)?,
+        ),
+        IndexType::Month => (
+            checked_naivedatetime(from.year(), from.month(), 1, 1, 1, 1)?,
+            checked_naivedatetime(until.year(), until.month(), 1, 1, 1, 1)?,
+        ),
+        IndexType::Day => (
+            checked_naivedatetime(from.year(), from.month(), from.day(), 1, 1, 1)?,
+            checked_naivedatetime(until.year(), until.month(), until.day(), 1, 1, 1)?,
+        ),
         IndexType::Hour => {
             if INDEX_DEPTH.contains(&index_type) {
-                Some((
-                    NaiveDate::from_ymd(from.year(), from.month(), from.day()).and_hms(
+                (
+                    checked_naivedatetime(
+                        from.year(),
+                        from.month(),
+                        from.day(),
                         from.hour(),
                         1,
                         1,
-                    ),
-                    NaiveDate::from_ymd(until.year(), until.month(), until.day()).and_hms(
+                    )?,
+                    checked_naivedatetime(
+                        until.year(),
+                        until.month(),
+                        until.day(),
                         until.hour(),
                         1,
                         1,
-                    ),
-                ))
+                    )?,
+                )
             } else {
-                None
+                return Ok(None);
             }
         }
         IndexType::Minute => {
             if INDEX_DEPTH.contains(&index_type) {
-                Some((
-                    NaiveDate::from_ymd(from.year(), from.month(), from.day()).and_hms(
+                (
+                    checked_naivedatetime(
+                        from.year(),
+                        from.month(),
+                        from.day(),
                         from.hour(),
                         from.minute(),
                         1,
-                    ),
-                    NaiveDate::from_ymd(until.year(), until.month(), until.day()).and_hms(
+                    )?,
+                    checked_naivedatetime(
+                        until.year(),
+                        until.month(),
+                        until.day(),
                         until.hour(),
                         until.minute(),
                         1,
-                    ),
-                ))
+                    )?,
+                )
             } else {
-                None
+                return Ok(None);
             }
         }
         IndexType::Second => {
             if INDEX_DEPTH.contains(&index_type) {
-                Some((
-                    NaiveDate::from_ymd(from.year(), from.month(), from.day()).and_hms(
+                (
+                    checked_naivedatetime(
+                        from.year(),
+                        from.month(),
+                        from.day(),
                         from.hour(),
                         from.minute(),
                         from.second(),
-                    ),
-                    NaiveDate::from_ymd(until.year(), until.month(), until.day()).and_hms(
+                    )?,
+                    checked_naivedatetime(
+                        until.year(),
+                        until.month(),
+                        until.day(),
                         until.hour(),
                         until.minute(),
                         until.second(),
-                    ),
-                ))
+                    )?,
+                )
             } else {
-                None
+                return Ok(None);
             }
         }
+    };
+    Ok(Some((from, until)))
+}
+
+/// Finds the newest time path which sits depth components deep; following the newest children down from path.
+/// When the newest child of a level is a dead end (does not reach depth) the walk backtracks onto the next newest sibling,
+/// so the genuinely newest path at depth is found. Returns None if no path reaches depth
+pub(crate) fn find_newest_time_path(path: Path, depth: usize) -> IndexResult<Option<Path>> {
+    let path: Vec<Component> = path.into();
+    let levels = depth.saturating_sub(path.len());
+    Ok(
+        walk_to_depth(path, levels, &Order::Desc, &|node: &Vec<Component>| {
+            get_time_children(Path::from(node.clone()))
+        })?
+        .map(Path::from),
+    )
+}
+
+/// Depth first walk levels down from node; visiting children in order of their key. Branches which end before
+/// levels have been descended are backtracked out of. Returns the first node found levels deep
+pub(crate) fn walk_to_depth<N, K: Ord, F: Fn(&N) -> IndexResult<Vec<(K, N)>>>(
+    node: N,
+    levels: usize,
+    order: &Order,
+    get_children: &F,
+) -> IndexResult<Option<N>> {
+    if levels == 0 {
+        return Ok(Some(node));
+    };
+    let mut children = get_children(&node)?;
+    match order {
+        Order::Desc => children.sort_by(|(a, _), (b, _)| b.cmp(a)),
+        Order::Asc => children.sort_by(|(a, _), (b, _)| a.cmp(b)),
+    };
+    for (_, child) in children {
+        if let Some(found) = walk_to_depth(child, levels - 1, order, get_children)? {
+            return Ok(Some(found));
+        };
     }
+    Ok(None)
 }
 
-/// Tries to find the newest time period one level down from current path position
-/// Returns path passed in params if maximum depth has been reached
-pub(crate) fn find_newest_time_path<
-    T: TryFrom<SerializedBytes, Error = SerializedBytesError> + Into<u32>,
->(
-    path: Path,
-    time_index: IndexType,
-) -> IndexResult<Path> {
-    match time_index {
-        IndexType::Year => (),
-        IndexType::Month => (),
-        IndexType::Day => (),
-        IndexType::Hour => {
-            if INDEX_DEPTH.contains(&time_index) {
-                ()
-            } else {
-                return Ok(path);
+/// Sort items by a key which is decoded once per item, rather than once per comparison as when decoding inside a sort_by comparator.
+/// Order::Asc sorts smallest key first & Order::Desc largest first. Sort is stable; items with equal keys keep their order
+pub(crate) fn sort_by_decoded_key<T, K: Ord, F: FnMut(&T) -> IndexResult<K>>(
+    items: Vec<T>,
+    order: &Order,
+    mut decode: F,
+) -> IndexResult<Vec<T>> {
+    let mut keyed = items
+        .into_iter()
+        .map(|item| Ok((decode(&item)?, item)))
+        .collect::<IndexResult<Vec<(K, T)>>>()?;
+    match order {
+        Order::Desc => keyed.sort_by(|(a, _), (b, _)| b.cmp(a)),
+        Order::Asc => keyed.sort_by(|(a, _), (b, _)| a.cmp(b)),
+    };
+    Ok(keyed.into_iter().map(|(_, item)| item).collect())
+}
+
+/// Finds the newest time path which is older than the supplied path and sits at the same depth of the tree.
+/// Will step up the tree when the supplied path has no older siblings and back down again following the newest children.
+/// Branches which do not reach the depth of the supplied path are skipped. Returns None once the root of the index has been reached
+pub(crate) fn find_previous_time_path(path: Path) -> IndexResult<Option<Path>> {
+    find_adjacent_time_path(path, &Order::Desc)
+}
+
+/// Finds the oldest time path which is newer than the supplied path and sits at the same depth of the tree.
+/// Mirror of [`find_previous_time_path()`]
+pub(crate) fn find_next_time_path(path: Path) -> IndexResult<Option<Path>> {
+    find_adjacent_time_path(path, &Order::Asc)
+}
+
+/// Finds the closest time path in direction of order which sits at the same depth of the tree as path
+fn find_adjacent_time_path(path: Path, order: &Order) -> IndexResult<Option<Path>> {
+    let components: Vec<Component> = path.into();
+    let depth = components.len();
+    let mut current = components;
+
+    while current.len() > 1 {
+        let current_value: u32 = TimeIndex::try_from(current.last().unwrap().to_owned())?.into();
+        let parent = Path::from(current[..current.len() - 1].to_vec());
+        //Children are ordered by key so siblings sharing a time value resolve to the same sibling as walk_to_depth() would pick
+        let siblings = get_time_children(parent)?
+            .into_iter()
+            .map(|((value, _), sibling)| (value, Path::from(sibling)))
+            .collect::<Vec<(u32, Path)>>();
+        let sibling = match order {
+            Order::Desc => find_previous_sibling(siblings, current_value),
+            Order::Asc => find_next_sibling(siblings, current_value),
+        };
+
+        match sibling {
+            Some(sibling) => {
+                //Follow the closest children of the sibling back down to the depth we started at
+                let mut descend: Vec<Component> = sibling.into();
+                while descend.len() < depth {
+                    let children = get_time_children(Path::from(descend.clone()))?.into_iter();
+                    let child = match order {
+                        Order::Desc => children.max_by(|(a, _), (b, _)| a.cmp(b)),
+                        Order::Asc => children.min_by(|(a, _), (b, _)| a.cmp(b)),
+                    };
+                    match child {
+                        Some((_, child)) => descend = child,
+                        //Dead end branch; continue searching from this node
+                        None => break,
+                    };
+                }
+                if descend.len() == depth {
+                    return Ok(Some(Path::from(descend)));
+                };
+                current = descend;
             }
+            None => current = current[..current.len() - 1].to_vec(),
+        };
+    }
+    Ok(None)
+}
+
+/// Finds the oldest time path which sits depth components deep; following the oldest children down from path.
+/// Mirror of [`find_newest_time_path()`]
+pub(crate) fn find_oldest_time_path(path: Path, depth: usize) -> IndexResult<Option<Path>> {
+    let path: Vec<Component> = path.into();
+    let levels = depth.saturating_sub(path.len());
+    Ok(
+        walk_to_depth(path, levels, &Order::Asc, &|node: &Vec<Component>| {
+            get_time_children(Path::from(node.clone()))
+        })?
+        .map(Path::from),
+    )
+}
+
+/// Get the children of a time path keyed by the time value & component bytes of each child; ordered by key.
+/// Time values should be unique among siblings but a corrupted tree could hold two children encoding the same value. Siblings
+/// share every other component so comparing their last component bytes breaks such ties as comparing full path bytes would;
+/// every agent then walks the same branch whatever order the children's links were returned in.
+/// Errors with MalformedPath if a child is not exactly one component deeper than its parent
+fn get_time_children(path: Path) -> IndexResult<Vec<((u32, Vec<u8>), Vec<Component>)>> {
+    let parent_len = Vec::<Component>::from(path.clone()).len();
+    get_children_paths(&path)?
+        .into_iter()
+        .map(|child| {
+            let child_components: Vec<Component> = child.into();
+            if child_components.len() != parent_len + 1 {
+                return Err(IndexError::MalformedPath(
+                    "Child path should be one component deeper than its parent",
+                ));
+            };
+            let component = child_components
+                .last()
+                .ok_or(IndexError::MalformedPath(
+                    "Expected path to contain components",
+                ))?
+                .to_owned();
+            let value: u32 = TimeIndex::try_from(component.clone())?.into();
+            Ok(((value, component.into()), child_components))
+        })
+        .collect::<IndexResult<Vec<((u32, Vec<u8>), Vec<Component>)>>>()
+        .map(|mut children| {
+            children.sort_by(|(a, _), (b, _)| a.cmp(b));
+            children
+        })
+}
+
+/// From a set of sibling paths and their time values, choose the newest sibling which is older than current
+pub(crate) fn find_previous_sibling(siblings: Vec<(u32, Path)>, current: u32) -> Option<Path> {
+    siblings
+        .into_iter()
+        .filter(|(value, _)| *value < current)
+        .max_by_key(|(value, _)| *value)
+        .map(|(_, path)| path)
+}
+
+/// From a set of sibling paths and their time values, choose the oldest sibling which is newer than current
+pub(crate) fn find_next_sibling(siblings: Vec<(u32, Path)>, current: u32) -> Option<Path> {
+    siblings
+        .into_iter()
+        .filter(|(value, _)| *value > current)
+        .min_by_key(|(value, _)| *value)
+        .map(|(_, path)| path)
+}
+
+mod search_tests {
+    #[test]
+    fn test_find_previous_sibling() {
+        use crate::search::find_previous_sibling;
+        use hdk::hash_path::path::{Component, Path};
+
+        let path = |val: &str| Path::from(vec![Component::from(val)]);
+        let siblings = vec![
+            (3, path("3")),
+            (1, path("1")),
+            (5, path("5")),
+            (2, path("2")),
+        ];
+        assert_eq!(find_previous_sibling(siblings.clone(), 5), Some(path("3")));
+        assert_eq!(find_previous_sibling(siblings.clone(), 2), Some(path("1")));
+        assert_eq!(find_previous_sibling(siblings, 1), None);
+    }
+
+    #[test]
+    fn test_walk_to_depth_backtracks() {
+        use crate::errors::IndexResult;
+        use crate::search::walk_to_depth;
+        use crate::Order;
+        use std::collections::HashMap;
+
+        fn children(
+            tree: &HashMap<Vec<u32>, Vec<u32>>,
+            node: &Vec<u32>,
+        ) -> IndexResult<Vec<(u32, Vec<u32>)>> {
+            Ok(tree
+                .get(node)
+                .cloned()
+                .unwrap_or(vec![])
+                .into_iter()
+                .map(|value| {
+                    let mut child = node.clone();
+                    child.push(value);
+                    (value, child)
+                })
+                .collect())
         }
-        IndexType::Minute => {
-            if INDEX_DEPTH.contains(&time_index) {
-                ()
-            } else {
-                return Ok(path);
-            }
+
+        //Tree of time values; [2021, 9] is the newest month but is a dead end with no days
+        let mut tree: HashMap<Vec<u32>, Vec<u32>> = HashMap::new();
+        tree.insert(vec![], vec![2020, 2021]);
+        tree.insert(vec![2020], vec![12]);
+        tree.insert(vec![2020, 12], vec![31]);
+        tree.insert(vec![2021], vec![8, 9, 7]);
+        tree.insert(vec![2021, 7], vec![1, 20]);
+        tree.insert(vec![2021, 8], vec![3, 12]);
+
+        assert_eq!(
+            walk_to_depth(vec![], 3, &Order::Desc, &|node| children(&tree, node)).unwrap(),
+            Some(vec![2021, 8, 12])
+        );
+        assert_eq!(
+            walk_to_depth(vec![], 3, &Order::Asc, &|node| children(&tree, node)).unwrap(),
+            Some(vec![2020, 12, 31])
+        );
+        //Whole newest year is a dead end; older year is used
+        tree.insert(vec![2021], vec![9]);
+        assert_eq!(
+            walk_to_depth(vec![], 3, &Order::Desc, &|node| children(&tree, node)).unwrap(),
+            Some(vec![2020, 12, 31])
+        );
+        //No branch reaches depth
+        assert_eq!(
+            walk_to_depth(vec![], 4, &Order::Desc, &|node| children(&tree, node)).unwrap(),
+            None
+        );
+        //Zero levels returns the starting node
+        assert_eq!(
+            walk_to_depth(vec![2021], 0, &Order::Desc, &|node| children(&tree, node)).unwrap(),
+            Some(vec![2021])
+        );
+    }
+
+    #[test]
+    fn test_find_next_sibling() {
+        use crate::search::find_next_sibling;
+        use hdk::hash_path::path::{Component, Path};
+
+        let path = |val: &str| Path::from(vec![Component::from(val)]);
+        let siblings = vec![
+            (3, path("3")),
+            (1, path("1")),
+            (5, path("5")),
+            (2, path("2")),
+        ];
+        assert_eq!(find_next_sibling(siblings.clone(), 1), Some(path("2")));
+        assert_eq!(find_next_sibling(siblings.clone(), 3), Some(path("5")));
+        assert_eq!(find_next_sibling(siblings, 5), None);
+    }
+
+    #[test]
+    fn test_sort_by_decoded_key() {
+        use crate::entries::TimeIndex;
+        use crate::errors::IndexResult;
+        use crate::search::sort_by_decoded_key;
+        use crate::Order;
+        use hdk::hash_path::path::Component;
+        use std::cell::Cell;
+        use std::convert::TryFrom;
+
+        let components = vec![12, 3, 59, 0, 31, 7, 44, 18, 3, 26]
+            .into_iter()
+            .map(|value: u32| {
+                Component::from(TimeIndex(value).get_sb().unwrap().bytes().to_owned())
+            })
+            .collect::<Vec<Component>>();
+        let decodes = Cell::new(0);
+        let decode = |component: &Component| -> IndexResult<u32> {
+            decodes.set(decodes.get() + 1);
+            let value: u32 = TimeIndex::try_from(component.clone())?.into();
+            Ok(value)
+        };
+
+        for order in vec![Order::Asc, Order::Desc] {
+            //Decoding inside the comparator
+            decodes.set(0);
+            let mut expected = components.clone();
+            expected.sort_by(|a, b| {
+                let (a, b) = (decode(a).unwrap(), decode(b).unwrap());
+                match order {
+                    Order::Desc => b.cmp(&a),
+                    Order::Asc => a.cmp(&b),
+                }
+            });
+            let comparator_decodes = decodes.get();
+
+            decodes.set(0);
+            let sorted = sort_by_decoded_key(components.clone(), &order, decode).unwrap();
+            assert_eq!(sorted, expected);
+            assert_eq!(decodes.get(), components.len());
+            assert!(comparator_decodes > components.len());
         }
-        IndexType::Second => {
-            if INDEX_DEPTH.contains(&time_index) {
-                ()
-            } else {
-                return Ok(path);
-            }
+
+        //Decode errors are returned
+        let undecodable = vec![Component::from(vec![0xc1])];
+        assert!(sort_by_decoded_key(undecodable, &Order::Asc, decode).is_err());
+    }
+
+    #[test]
+    fn test_walk_to_depth_tie_break() {
+        use crate::errors::IndexResult;
+        use crate::search::walk_to_depth;
+        use crate::Order;
+
+        //Two children of the root encode the same time value; as a corrupted tree could
+        let tied = |node: &Vec<u8>, flipped: bool| -> IndexResult<Vec<((u32, Vec<u8>), Vec<u8>)>> {
+            if !node.is_empty() {
+                return Ok(vec![]);
+            };
+            let mut children = vec![((5, vec![1]), vec![1]), ((5, vec![2]), vec![2])];
+            if flipped {
+                children.reverse();
+            };
+            Ok(children)
+        };
+        //Same winner whatever order children are returned in
+        for flipped in vec![false, true] {
+            assert_eq!(
+                walk_to_depth(vec![], 1, &Order::Desc, &|node| tied(node, flipped)).unwrap(),
+                Some(vec![2])
+            );
+            assert_eq!(
+                walk_to_depth(vec![], 1, &Order::Asc, &|node| tied(node, flipped)).unwrap(),
+                Some(vec![1])
+            );
         }
-    };
-    //debug!("Finding links on IndexType: {:#?}\n\n", time_index);
-
-    //Pretty sure this filter and sort logic can be faster; first rough pass to get basic pieces in place
-    let mut links = path.children_paths()?;
-    if links.len() == 0 {
-        return Err(IndexError::InternalError(
-            "Could not find any time paths for path",
-        ));
-    };
-    links.sort_by(|a, b| {
-        let a_val: Vec<Component> = a.to_owned().into();
-        let b_val: Vec<Component> = b.to_owned().into();
-        let a_u32: u32 = T::try_from(SerializedBytes::from(UnsafeBytes::from(
-            a_val[1].as_ref().to_owned(),
-        )))
-        .unwrap()
-        .into();
-        let b_u32: u32 = T::try_from(SerializedBytes::from(UnsafeBytes::from(
-            b_val[1].as_ref().to_owned(),
-        )))
-        .unwrap()
-        .into();
-        a_u32.partial_cmp(&b_u32).unwrap()
-    });
-    let latest = links.pop().unwrap();
-    Ok(latest)
+    }
 }
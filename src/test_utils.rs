@@ -0,0 +1,12 @@
+use hdk::prelude::*;
+
+/// Link fixture for unit tests. Target, create link hash & timestamp (in microseconds) are all derived from i, so links are
+/// ordered by i & two links are equal only if i & tag are. Use struct update syntax to vary single fields
+pub(crate) fn test_link<T: Into<Vec<u8>>>(i: u8, tag: T) -> Link {
+    Link {
+        target: EntryHash::from_raw_36(vec![i; 36]),
+        timestamp: Timestamp::from_micros(i as i64),
+        tag: LinkTag::new(tag),
+        create_link_hash: HeaderHash::from_raw_36(vec![i; 36]),
+    }
+}
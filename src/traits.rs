@@ -1,8 +1,38 @@
 use chrono::{DateTime, Utc};
 use hdk::prelude::{EntryHash, ExternResult};
 
+use crate::errors::IndexResult;
+use crate::EntryChunkIndex;
+
 pub trait IndexableEntry {
     ///Time that entry type this trait is implemented on should be indexed under
     fn entry_time(&self) -> DateTime<Utc>;
     fn hash(&self) -> ExternResult<EntryHash>;
+    ///Times the entry should be indexed under by [`crate::index_entry()`]; one link is made for each. Override to index an entry
+    ///under more than one time, i.e both its created & updated time. Defaults to [`IndexableEntry::entry_time()`] only
+    fn index_times(&self) -> Vec<DateTime<Utc>> {
+        vec![self.entry_time()]
+    }
+}
+
+/// Source of index results for a time span. Allows results of time indexes with different configurations (i.e two DNA's
+/// with different MAX_CHUNK_INTERVAL's) to be queried together; see [`crate::get_indexes_for_time_span_across()`]
+pub trait IndexBackend {
+    ///Get indexes & their links between from & until
+    fn get_indexes_for_time_span(
+        &self,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> IndexResult<Vec<EntryChunkIndex>>;
+}
+
+/// Allows closures (i.e ones making a call to another DNA) to be used as a backend
+impl<F: Fn(DateTime<Utc>, DateTime<Utc>) -> IndexResult<Vec<EntryChunkIndex>>> IndexBackend for F {
+    fn get_indexes_for_time_span(
+        &self,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> IndexResult<Vec<EntryChunkIndex>> {
+        self(from, until)
+    }
 }
@@ -1,15 +1,21 @@
-use chrono::{DateTime, Datelike, NaiveDateTime, Timelike, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
 use hdk::{hash_path::path::Component, prelude::*};
+use std::collections::HashMap;
 
 use crate::entries::{Index, IndexType, StringIndex, TimeIndex};
 use crate::errors::{IndexError, IndexResult};
-use crate::{INDEX_DEPTH, MAX_CHUNK_INTERVAL};
+use crate::validation::{validate_configured_index_depth, validate_index_alignment};
+use crate::{
+    Consistency, EntryChunkIndex, IntegrityIssue, PageCursor, ENTRY_CHUNK_INDEX_VERSION, GENESIS,
+    GET_RETRY_ATTEMPTS, INDEX_DEPTH, MAX_CHUNK_INTERVAL, TIME_PATH_LINK_TAG,
+};
 
 /// Find the overlapping path between two times and return vec of queries at given IndexTypes which still need to be performed
 pub(crate) fn find_divergent_time(
     from: &DateTime<Utc>,
     until: &DateTime<Utc>,
 ) -> IndexResult<(Vec<Component>, Vec<IndexType>)> {
+    validate_configured_index_depth()?;
     //Make year comparison
     let mut path = if from.year() == until.year() {
         vec![Component::from(
@@ -113,97 +119,1574 @@ pub(crate) fn get_time_path(
     index: String,
     from: std::time::Duration,
 ) -> IndexResult<Vec<Component>> {
-    let from_timestamp = DateTime::<Utc>::from_utc(
-        NaiveDateTime::from_timestamp(from.as_secs_f64() as i64, from.subsec_nanos()),
-        Utc,
-    );
-    let mut time_path = vec![Component::from(
+    let depth = deepest_index_type(&INDEX_DEPTH).ok_or(IndexError::InternalError(
+        "Index depth must contain at least one level",
+    ))?;
+    build_time_path(index, &from, &depth, None)
+}
+
+/// Create the full path of chunk in the time tree of index; its time path followed by the chunk itself
+pub(crate) fn get_chunk_path(index: String, chunk: &Index) -> IndexResult<Vec<Component>> {
+    let depth = deepest_index_type(&INDEX_DEPTH).ok_or(IndexError::InternalError(
+        "Index depth must contain at least one level",
+    ))?;
+    build_time_path(index, &chunk.from, &depth, Some(chunk))
+}
+
+/// Build the components of a time path down to depth, optionally followed by a chunk. Components are written into a single
+/// vec sized up front for the whole path so building a path never reallocates
+pub(crate) fn build_time_path(
+    index: String,
+    from: &std::time::Duration,
+    depth: &IndexType,
+    chunk: Option<&Index>,
+) -> IndexResult<Vec<Component>> {
+    let levels = TIME_TREE_LEVELS
+        .iter()
+        .position(|level| level == depth)
+        .map(|position| position + 1)
+        .unwrap_or(TIME_TREE_LEVELS.len());
+    let mut time_path = Vec::with_capacity(1 + levels + chunk.is_some() as usize);
+    time_path.push(Component::from(
         StringIndex(index).get_sb()?.bytes().to_owned(),
-    )];
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &from_timestamp, IndexType::Year)?;
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &from_timestamp, IndexType::Month)?;
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &from_timestamp, IndexType::Day)?;
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &from_timestamp, IndexType::Hour)?;
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &from_timestamp, IndexType::Minute)?;
-    add_time_index_to_path::<TimeIndex>(&mut time_path, &from_timestamp, IndexType::Second)?;
+    ));
+    push_time_components(
+        &mut time_path,
+        &duration_to_datetime(from).naive_utc(),
+        depth,
+    )?;
+    if let Some(chunk) = chunk {
+        time_path.push(SerializedBytes::try_from(chunk)?.bytes().to_owned().into());
+    };
     // debug!("Indexing with path lenght: {:#?}", time_path.len());
 
     Ok(time_path)
 }
 
-/// Add TimeIndex component to time path whilst checking if time component depth is allowed as determined by libs configuration vars
-pub(crate) fn add_time_index_to_path<
-    T: TryInto<SerializedBytes, Error = SerializedBytesError> + From<u32>,
->(
-    time_path: &mut Vec<Component>,
-    from_timestamp: &DateTime<Utc>,
-    time_index: IndexType,
+/// Create a NaiveDateTime from its components. Returns an InvalidTime error instead of panicking if components are out of range
+/// or the time is before UNIX epoch
+pub(crate) fn checked_naivedatetime(
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> IndexResult<NaiveDateTime> {
+    if year < 1970 {
+        return Err(IndexError::InvalidTime("Time cannot be before UNIX epoch"));
+    };
+    NaiveDate::from_ymd_opt(year, month, day)
+        .ok_or(IndexError::InvalidTime("Date is out of range"))?
+        .and_hms_opt(hour, minute, second)
+        .ok_or(IndexError::InvalidTime("Time of day is out of range"))
+}
+
+/// Parse a time given by a client. Accepts RFC3339 strings (i.e `2021-08-12T14:01:30Z` or with an offset; converted to UTC) and UNIX
+/// times with an explicit unit; `1628776890s` for seconds or `1628776890000ms` for milliseconds. Bare numbers are rejected as their
+/// unit is ambiguous
+pub fn parse_time(input: &str) -> IndexResult<DateTime<Utc>> {
+    let input = input.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        let time = time.with_timezone(&Utc);
+        if time.timestamp() < 0 {
+            return Err(IndexError::InvalidTime("Time cannot be before UNIX epoch"));
+        };
+        return Ok(time);
+    };
+    let parse_number = |number: &str| {
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+            return Err(IndexError::InvalidTime("Could not parse time"));
+        };
+        number
+            .parse::<i64>()
+            .map_err(|_err| IndexError::InvalidTime("Time is out of range"))
+    };
+    if let Some(millis) = input.strip_suffix("ms") {
+        from_unix_millis(parse_number(millis)?)
+    } else if let Some(secs) = input.strip_suffix("s") {
+        from_unix_secs(parse_number(secs)?)
+    } else if !input.is_empty() && input.chars().all(|c| c.is_ascii_digit()) {
+        Err(IndexError::InvalidTime(
+            "UNIX time must have a unit suffix of s or ms",
+        ))
+    } else {
+        Err(IndexError::InvalidTime("Could not parse time"))
+    }
+}
+
+/// Convert milliseconds since UNIX epoch to a UTC time
+pub fn from_unix_millis(millis: i64) -> IndexResult<DateTime<Utc>> {
+    if millis < 0 {
+        return Err(IndexError::InvalidTime("Time cannot be before UNIX epoch"));
+    };
+    Utc.timestamp_opt(millis / 1000, (millis % 1000) as u32 * 1_000_000)
+        .single()
+        .ok_or(IndexError::InvalidTime("Time is out of range"))
+}
+
+/// Convert seconds since UNIX epoch to a UTC time
+pub fn from_unix_secs(secs: i64) -> IndexResult<DateTime<Utc>> {
+    if secs < 0 {
+        return Err(IndexError::InvalidTime("Time cannot be before UNIX epoch"));
+    };
+    Utc.timestamp_opt(secs, 0)
+        .single()
+        .ok_or(IndexError::InvalidTime("Time is out of range"))
+}
+
+/// Describe a time relative to the configured genesis as the chunk containing it & how far into that chunk it falls;
+/// i.e `chunk 3, +250ms into chunk, +30250ms from genesis`. Useful when debugging alignment issues. Errors if time is before genesis
+pub fn format_relative_to_genesis(time: DateTime<Utc>) -> IndexResult<String> {
+    format_relative(time, &MAX_CHUNK_INTERVAL, &GENESIS)
+}
+
+pub(crate) fn format_relative(
+    time: DateTime<Utc>,
+    interval: &std::time::Duration,
+    genesis: &std::time::Duration,
+) -> IndexResult<String> {
+    let time = datetime_to_duration(&time)?;
+    let index = index_for_timestamp(time, interval, genesis)?;
+    Ok(format!(
+        "chunk {}, +{}ms into chunk, +{}ms from genesis",
+        chunk_offset(&index, interval, genesis)?,
+        (time - index.from).as_millis(),
+        (time - *genesis).as_millis()
+    ))
+}
+
+/// Levels of the time tree ordered from the root downwards
+pub(crate) const TIME_TREE_LEVELS: [IndexType; 6] = [
+    IndexType::Year,
+    IndexType::Month,
+    IndexType::Day,
+    IndexType::Hour,
+    IndexType::Minute,
+    IndexType::Second,
+];
+
+/// Determine the depth of the time tree which chunks of the given interval should be hung from
+pub(crate) fn index_depth_for_interval(interval: &std::time::Duration) -> Vec<IndexType> {
+    if *interval < IndexType::Second.granularity() {
+        vec![
+            IndexType::Second,
+            IndexType::Minute,
+            IndexType::Hour,
+            IndexType::Day,
+        ]
+    } else if *interval < IndexType::Minute.granularity() {
+        vec![IndexType::Minute, IndexType::Hour, IndexType::Day]
+    } else if *interval < IndexType::Hour.granularity() {
+        vec![IndexType::Hour, IndexType::Day]
+    } else {
+        vec![IndexType::Day]
+    }
+}
+
+/// Number of time components in the time paths of a tree using the given index depth; excludes the index root & index components
+pub(crate) fn time_path_depth(depth: &[IndexType]) -> Option<usize> {
+    let deepest = deepest_index_type(depth)?;
+    TIME_TREE_LEVELS
+        .iter()
+        .position(|level| *level == deepest)
+        .map(|position| position + 1)
+}
+
+/// Get the finest grained level from a given index depth
+pub(crate) fn deepest_index_type(depth: &[IndexType]) -> Option<IndexType> {
+    depth
+        .iter()
+        .min_by_key(|index_type| index_type.granularity())
+        .cloned()
+}
+
+/// Create the time path components for a given time starting from year and going down to (and including) depth
+pub(crate) fn naivedatetime_to_components(
+    time: &NaiveDateTime,
+    depth: &IndexType,
+) -> IndexResult<Vec<Component>> {
+    let mut components = vec![];
+    push_time_components(&mut components, time, depth)?;
+    Ok(components)
+}
+
+/// Append the time path components for a given time starting from year and going down to (and including) depth onto components
+pub(crate) fn push_time_components(
+    components: &mut Vec<Component>,
+    time: &NaiveDateTime,
+    depth: &IndexType,
 ) -> IndexResult<()> {
-    let from_time = match time_index {
-        IndexType::Year => from_timestamp.year() as u32,
-        IndexType::Month => from_timestamp.month(),
-        IndexType::Day => from_timestamp.day(),
-        IndexType::Hour => {
-            if INDEX_DEPTH.contains(&time_index) {
-                from_timestamp.hour()
-            } else {
-                return Ok(());
+    for level in TIME_TREE_LEVELS.iter() {
+        let value = match level {
+            IndexType::Year => time.year() as u32,
+            IndexType::Month => time.month(),
+            IndexType::Day => time.day(),
+            IndexType::Hour => time.hour(),
+            IndexType::Minute => time.minute(),
+            IndexType::Second => time.second(),
+        };
+        components.push(Component::from(
+            TimeIndex(value).get_sb()?.bytes().to_owned(),
+        ));
+        if level == depth {
+            break;
+        };
+    }
+    Ok(())
+}
+
+/// Ensure that each of paths (and all of their ancestors) exist. Paths are given as their components; exists checks if a path
+/// exists and create creates a path whose parent is known to exist. Ancestors shared between paths are only checked &
+/// created once
+pub(crate) fn ensure_paths<N, E, C>(
+    paths: &[Vec<N>],
+    mut exists: E,
+    mut create: C,
+) -> IndexResult<()>
+where
+    N: Clone + Eq + std::hash::Hash,
+    E: FnMut(&[N]) -> IndexResult<bool>,
+    C: FnMut(&[N]) -> IndexResult<()>,
+{
+    let mut ensured: std::collections::HashSet<Vec<N>> = std::collections::HashSet::new();
+    for path in paths {
+        //Walk up from the leaf until an ancestor known to exist is found
+        let mut missing = vec![];
+        for len in (1..=path.len()).rev() {
+            let node = &path[..len];
+            if ensured.contains(node) {
+                break;
+            };
+            if exists(node)? {
+                ensured.insert(node.to_vec());
+                break;
+            };
+            missing.push(len);
+        }
+        //Create missing nodes from the top down so each node is linked from an existing parent
+        for len in missing.into_iter().rev() {
+            create(&path[..len])?;
+            ensured.insert(path[..len].to_vec());
+        }
+    }
+    Ok(())
+}
+
+/// Memoized value which is only valid for a given index & chunk. Used to avoid rebuilding the same time path on repeated
+/// calls made within the same chunk
+pub(crate) struct ChunkMemo<V> {
+    entry: Option<(String, std::time::Duration, V)>,
+}
+
+impl<V> Default for ChunkMemo<V> {
+    fn default() -> Self {
+        ChunkMemo { entry: None }
+    }
+}
+
+impl<V: Clone> ChunkMemo<V> {
+    /// Get memoized value for index & chunk start or create (and memoize) it with build
+    pub(crate) fn get_or_insert_with<F: FnOnce() -> IndexResult<V>>(
+        &mut self,
+        index: &str,
+        chunk_from: std::time::Duration,
+        build: F,
+    ) -> IndexResult<V> {
+        match &self.entry {
+            Some((memo_index, memo_from, value))
+                if memo_index == index && *memo_from == chunk_from =>
+            {
+                Ok(value.clone())
             }
+            _ => {
+                let value = build()?;
+                self.entry = Some((index.to_owned(), chunk_from, value.clone()));
+                Ok(value)
+            }
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT_TIME_PATH: std::cell::RefCell<ChunkMemo<Path>> = std::cell::RefCell::new(ChunkMemo::default());
+}
+
+/// Get the time path of the chunk covering the current sys_time. Path is memoized for the lifetime of the current chunk
+pub(crate) fn get_current_time_path(index: String) -> IndexResult<Path> {
+    let current = get_current_chunk()?;
+    CURRENT_TIME_PATH.with(|memo| {
+        memo.borrow_mut()
+            .get_or_insert_with(&index, current.from, || {
+                Ok(Path::from(get_time_path(index.clone(), current.from)?))
+            })
+    })
+}
+
+/// Get the chunk covering the current sys_time
+pub(crate) fn get_current_chunk() -> IndexResult<Index> {
+    current_chunk_for(now_since_epoch()?, &MAX_CHUNK_INTERVAL, &GENESIS)
+}
+
+/// Determine chunk covering now where chunks of interval length are aligned from genesis. A now before genesis means the
+/// agent's clock is wrong (or genesis is misconfigured) and is reported as ClockBeforeGenesis rather than as a bad request
+pub(crate) fn current_chunk_for(
+    now: std::time::Duration,
+    interval: &std::time::Duration,
+    genesis: &std::time::Duration,
+) -> IndexResult<Index> {
+    if now < *genesis {
+        return Err(IndexError::ClockBeforeGenesis);
+    };
+    index_for_timestamp(now, interval, genesis)
+}
+
+/// Get current sys_time as duration since UNIX epoch
+pub(crate) fn now_since_epoch() -> IndexResult<std::time::Duration> {
+    timestamp_to_duration(&sys_time()?)
+}
+
+/// Convert a holochain timestamp to a duration since UNIX epoch. Errors with InvalidTime if timestamp is before UNIX epoch
+pub(crate) fn timestamp_to_duration(time: &Timestamp) -> IndexResult<std::time::Duration> {
+    time.checked_difference_signed(&Timestamp::from_micros(0))
+        .ok_or(IndexError::InternalError("Should not overflow"))?
+        .to_std()
+        .map_err(|_err| IndexError::InvalidTime("Time cannot be before UNIX epoch"))
+}
+
+/// Convert a UTC time to a duration since UNIX epoch. Errors with InvalidTime if time is before UNIX epoch
+pub(crate) fn datetime_to_duration(time: &DateTime<Utc>) -> IndexResult<std::time::Duration> {
+    if time.timestamp() < 0 {
+        return Err(IndexError::InvalidTime("Time cannot be before UNIX epoch"));
+    };
+    Ok(std::time::Duration::new(
+        time.timestamp() as u64,
+        time.timestamp_subsec_nanos(),
+    ))
+}
+
+/// Convert a duration since UNIX epoch into a UTC timestamp
+pub(crate) fn duration_to_datetime(time: &std::time::Duration) -> DateTime<Utc> {
+    DateTime::<Utc>::from_utc(
+        NaiveDateTime::from_timestamp(time.as_secs() as i64, time.subsec_nanos()),
+        Utc,
+    )
+}
+
+/// Convert a UTC time to a holochain timestamp
+pub(crate) fn datetime_to_timestamp(time: &DateTime<Utc>) -> Timestamp {
+    Timestamp::from_micros(time.timestamp() * 1_000_000 + time.timestamp_subsec_micros() as i64)
+}
+
+/// Convert a holochain timestamp to a UTC time
+pub(crate) fn timestamp_to_datetime(time: &Timestamp) -> IndexResult<DateTime<Utc>> {
+    let since_epoch = time
+        .checked_difference_signed(&Timestamp::from_micros(0))
+        .ok_or(IndexError::InternalError("Should not overflow"))?;
+    Ok(DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc) + since_epoch)
+}
+
+/// Take, oldest first, as many links of chunks as fit in max_bytes of serialized EntryChunkIndex's. Links of each chunk are fetched with
+/// fetch only once paging reaches it; chunks ending at or before cursor.from are never fetched & no chunk is fetched after the page is full.
+/// The chunk containing cursor.from is returned in full, so a cursor need not be aligned to the start of a chunk. Links in cursor.seen are skipped. If links remain a cursor is returned positioned at the start of the first index not fully returned, with seen holding
+/// the links of that index which have been returned. Sizes are the sum of the encoded size of each index & its links; so a page
+/// may exceed max_bytes by a few bytes of MessagePack framing. Errors if max_bytes cannot fit a single link
+pub(crate) fn page_by_size<P, F: FnMut(P) -> IndexResult<Vec<Link>>>(
+    mut chunks: Vec<(Index, P)>,
+    cursor: &PageCursor,
+    max_bytes: usize,
+    mut fetch: F,
+) -> IndexResult<(Vec<EntryChunkIndex>, Option<PageCursor>)> {
+    chunks.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut out: Vec<EntryChunkIndex> = vec![];
+    let mut used = 0;
+    for (index, paths) in chunks {
+        if index.window().1 <= cursor.from {
+            continue;
+        };
+        let mut chunk = EntryChunkIndex {
+            index: index,
+            links: fetch(paths)?,
+            version: ENTRY_CHUNK_INDEX_VERSION,
+        };
+        chunk
+            .links
+            .retain(|link| !cursor.seen.contains(&link.create_link_hash));
+        if chunk.links.is_empty() {
+            continue;
+        };
+        sort_links(&mut chunk.links);
+        let links = std::mem::take(&mut chunk.links);
+        let header_size = SerializedBytes::try_from(&chunk)?.bytes().len();
+        used += header_size;
+        for link in links {
+            let size = encoded_link_size(&chunk, &link, header_size)?;
+            if used + size > max_bytes {
+                if out.is_empty() && chunk.links.is_empty() {
+                    return Err(IndexError::RequestError(
+                        "max_bytes is too small to return a single link",
+                    ));
+                };
+                let mut seen = chunk
+                    .links
+                    .iter()
+                    .map(|link| link.create_link_hash.to_owned())
+                    .collect::<Vec<HeaderHash>>();
+                if chunk.index.window().0 == cursor.from {
+                    seen.extend(cursor.seen.iter().cloned());
+                };
+                let next = PageCursor {
+                    from: chunk.index.window().0,
+                    seen: seen,
+                };
+                if !chunk.links.is_empty() {
+                    out.push(chunk);
+                };
+                return Ok((out, Some(next)));
+            };
+            used += size;
+            chunk.links.push(link);
         }
-        IndexType::Minute => {
-            if INDEX_DEPTH.contains(&time_index) {
-                from_timestamp.minute()
+        out.push(chunk);
+    }
+    Ok((out, None))
+}
+
+/// Number of bytes link adds to the encoding of an EntryChunkIndex
+fn encoded_link_size(
+    chunk: &EntryChunkIndex,
+    link: &Link,
+    header_size: usize,
+) -> IndexResult<usize> {
+    let with_link = EntryChunkIndex {
+        index: chunk.index.clone(),
+        links: vec![link.clone()],
+        version: chunk.version,
+    };
+    Ok(SerializedBytes::try_from(&with_link)?
+        .bytes()
+        .len()
+        .saturating_sub(header_size))
+}
+
+/// Remove links which are not newer than the cursor position; returning the remaining links along with a cursor advanced to the newest of them.
+/// Indexes left with no links are dropped. Cursor is returned unchanged if there are no new links
+pub(crate) fn links_after_cursor(
+    chunks: Vec<EntryChunkIndex>,
+    cursor: PageCursor,
+) -> IndexResult<(Vec<EntryChunkIndex>, PageCursor)> {
+    let position = datetime_to_timestamp(&cursor.from);
+    let mut chunks = chunks
+        .into_iter()
+        .map(|mut chunk| {
+            chunk.links = chunk
+                .links
+                .into_iter()
+                .filter(|link| {
+                    link.timestamp > position
+                        || (link.timestamp == position
+                            && !cursor.seen.contains(&link.create_link_hash))
+                })
+                .collect();
+            chunk
+        })
+        .filter(|chunk| chunk.links.len() > 0)
+        .collect::<Vec<EntryChunkIndex>>();
+    chunks.sort_by(|a, b| a.index.cmp(&b.index));
+
+    let newest = match chunks
+        .iter()
+        .flat_map(|chunk| chunk.links.iter())
+        .map(|link| link.timestamp)
+        .max()
+    {
+        Some(newest) => newest,
+        None => return Ok((chunks, cursor)),
+    };
+    let mut seen = chunks
+        .iter()
+        .flat_map(|chunk| chunk.links.iter())
+        .filter(|link| link.timestamp == newest)
+        .map(|link| link.create_link_hash.to_owned())
+        .collect::<Vec<HeaderHash>>();
+    if newest == position {
+        seen.extend(cursor.seen);
+    };
+    let next = PageCursor {
+        from: timestamp_to_datetime(&newest)?,
+        seen: seen,
+    };
+    Ok((chunks, next))
+}
+
+/// Get links on a base along with the agent who authored each link. Links which have been deleted are not returned
+pub(crate) fn get_authored_links(
+    base: EntryHash,
+    link_tag: Option<LinkTag>,
+) -> IndexResult<Vec<(AgentPubKey, Link)>> {
+    get_authored_link_details(base, link_tag, false)
+}
+
+/// Get links on a base along with the agent who authored each link. Links which have been deleted are only returned if include_deleted is set
+pub(crate) fn get_authored_link_details(
+    base: EntryHash,
+    link_tag: Option<LinkTag>,
+    include_deleted: bool,
+) -> IndexResult<Vec<(AgentPubKey, Link)>> {
+    let details = get_link_details(base, link_tag)?
+        .into_inner()
+        .into_iter()
+        .map(|(create, deletes)| (create, deletes.len()))
+        .collect::<Vec<(SignedHeaderHashed, usize)>>();
+    let mut out = vec![];
+    for create in filter_deleted(details, include_deleted) {
+        if let Header::CreateLink(create_link) = create.header() {
+            out.push((
+                create.header().author().to_owned(),
+                Link {
+                    target: create_link.target_address.to_owned(),
+                    timestamp: create_link.timestamp.to_owned(),
+                    tag: create_link.tag.to_owned(),
+                    create_link_hash: create.header_address().to_owned(),
+                },
+            ));
+        };
+    }
+    Ok(out)
+}
+
+/// Get the children of a path. Path entries are content addressed so concurrent Path::ensure() calls for the same path create
+/// one path entry but can each link it from the parent; children are de-duplicated so such paths are only visited once
+pub(crate) fn get_children_paths(path: &Path) -> IndexResult<Vec<Path>> {
+    Ok(dedup_paths(path.children_paths()?))
+}
+
+/// Number of distinct targets links point at. Path entries are content addressed so this is the number of distinct children of
+/// a path; matching the length of [`get_children_paths()`]
+pub(crate) fn count_distinct_targets(links: &[Link]) -> usize {
+    links
+        .iter()
+        .map(|link| &link.target)
+        .collect::<std::collections::HashSet<&EntryHash>>()
+        .len()
+}
+
+/// Pair each chunk with whether it exists; given as (chunk, parent, chunk path hash). A chunk exists if its path hash is the target of
+/// one of its parent time path's links. Links of each distinct parent are fetched once with fetch, which is given parents in order of
+/// first appearance and must return their links in the same order. Chunks are returned in the order given
+pub(crate) fn mark_existing<
+    K: Eq + std::hash::Hash + Clone,
+    F: FnOnce(Vec<K>) -> IndexResult<Vec<Vec<Link>>>,
+>(
+    chunks: Vec<(Index, K, EntryHash)>,
+    fetch: F,
+) -> IndexResult<Vec<(Index, bool)>> {
+    let mut parents = vec![];
+    for (_, parent, _) in chunks.iter() {
+        if !parents.contains(parent) {
+            parents.push(parent.clone());
+        };
+    }
+    let links = fetch(parents.clone())?;
+    if links.len() != parents.len() {
+        return Err(IndexError::InternalError(
+            "Expected links for every parent path",
+        ));
+    };
+    let children = parents
+        .into_iter()
+        .zip(links)
+        .map(|(parent, links)| {
+            (
+                parent,
+                links
+                    .into_iter()
+                    .map(|link| link.target)
+                    .collect::<std::collections::HashSet<EntryHash>>(),
+            )
+        })
+        .collect::<HashMap<K, std::collections::HashSet<EntryHash>>>();
+    Ok(chunks
+        .into_iter()
+        .map(|(chunk, parent, hash)| {
+            let exists = children
+                .get(&parent)
+                .map(|targets| targets.contains(&hash))
+                .unwrap_or(false);
+            (chunk, exists)
+        })
+        .collect())
+}
+
+/// Remove repeated paths keeping the first occurrence of each
+pub(crate) fn dedup_paths(paths: Vec<Path>) -> Vec<Path> {
+    let mut seen = std::collections::HashSet::new();
+    paths
+        .into_iter()
+        .filter(|path| seen.insert(Vec::<Component>::from(path.clone())))
+        .collect()
+}
+
+/// Get links on an index. Links which have been deleted are only returned if include_deleted is set.
+/// When no link_tag is given links are de-duplicated by target; so an entry indexed under many tags on one index is only
+/// returned once. When link_tag is given every link matching the tag is returned
+pub(crate) fn get_index_links(
+    base: EntryHash,
+    link_tag: Option<LinkTag>,
+    include_deleted: bool,
+) -> IndexResult<Vec<Link>> {
+    let tag_filtered = link_tag.is_some();
+    let links = if include_deleted {
+        get_authored_link_details(base, link_tag, true)?
+            .into_iter()
+            .map(|(_author, link)| link)
+            .collect()
+    } else {
+        get_links(base, link_tag)?
+    };
+    Ok(if tag_filtered {
+        links
+    } else {
+        dedup_links_by_target(links)
+    })
+}
+
+/// Get links on many indexes; see [`get_index_links()`]. Bases are fetched in batches of concurrency with each batch made as a
+/// single get_links host call so the conductor can resolve them in parallel. Links are returned in the same order as bases.
+/// Deleted links can only be fetched one base at a time so are not batched
+pub(crate) fn get_index_links_batched(
+    bases: Vec<EntryHash>,
+    link_tag: Option<LinkTag>,
+    include_deleted: bool,
+    concurrency: usize,
+) -> IndexResult<Vec<Vec<Link>>> {
+    if include_deleted {
+        return bases
+            .into_iter()
+            .map(|base| get_index_links(base, link_tag.clone(), true))
+            .collect();
+    };
+    let tag_filtered = link_tag.is_some();
+    let links = in_batches(bases, concurrency, |batch| {
+        let inputs = batch
+            .into_iter()
+            .map(|base| GetLinksInput::new(base, link_tag.clone()))
+            .collect();
+        Ok(HDK.with(|h| h.borrow().get_links(inputs))?)
+    })?;
+    Ok(links
+        .into_iter()
+        .map(|links| {
+            if tag_filtered {
+                links
             } else {
-                return Ok(());
+                dedup_links_by_target(links)
             }
+        })
+        .collect())
+}
+
+/// Split items into batches of at most batch_size (at least one) and fetch each batch in turn; results are returned in the order of items
+pub(crate) fn in_batches<T, R, F: FnMut(Vec<T>) -> IndexResult<Vec<R>>>(
+    items: Vec<T>,
+    batch_size: usize,
+    mut fetch: F,
+) -> IndexResult<Vec<R>> {
+    let batch_size = batch_size.max(1);
+    let mut out = Vec::with_capacity(items.len());
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        let batch = items.by_ref().take(batch_size).collect::<Vec<T>>();
+        let expected = batch.len();
+        let mut results = fetch(batch)?;
+        if results.len() != expected {
+            return Err(IndexError::InternalError(
+                "Expected a result for every item of batch",
+            ));
+        };
+        out.append(&mut results);
+    }
+    Ok(out)
+}
+
+/// Remove links which point at a target already linked to; keeping the oldest link for each target.
+/// Returned links are ordered by timestamp then link hash
+pub(crate) fn dedup_links_by_target(mut links: Vec<Link>) -> Vec<Link> {
+    sort_links(&mut links);
+    let mut seen = std::collections::HashSet::new();
+    links
+        .into_iter()
+        .filter(|link| seen.insert(link.target.clone()))
+        .collect()
+}
+
+/// Filter out items which have any deletes unless include_deleted is set
+pub(crate) fn filter_deleted<T>(items: Vec<(T, usize)>, include_deleted: bool) -> Vec<T> {
+    items
+        .into_iter()
+        .filter(|(_item, deletes)| include_deleted || *deletes == 0)
+        .map(|(item, _deletes)| item)
+        .collect()
+}
+
+/// Group link targets by author in order links were made. Each author is capped at author_cap links and no more than limit links are returned in total
+pub(crate) fn group_links_by_author(
+    mut links: Vec<(AgentPubKey, Link)>,
+    author_cap: usize,
+    limit: Option<usize>,
+) -> HashMap<AgentPubKey, Vec<EntryHash>> {
+    links.sort_by(|a, b| a.1.timestamp.partial_cmp(&b.1.timestamp).unwrap());
+    let mut out: HashMap<AgentPubKey, Vec<EntryHash>> = HashMap::new();
+    let mut count = 0;
+    for (author, link) in links {
+        if limit.map(|limit| count >= limit).unwrap_or(false) {
+            break;
+        };
+        let targets = out.entry(author).or_insert_with(Vec::new);
+        if targets.len() < author_cap {
+            targets.push(link.target);
+            count += 1;
+        };
+    }
+    out
+}
+
+/// Split links found on an index into the links from the index to target with exactly link_tag, which are to be moved, and
+/// whether any links to target with other tags remain on the index
+pub(crate) fn links_to_move(
+    links: Vec<Link>,
+    target: &EntryHash,
+    link_tag: &LinkTag,
+) -> (Vec<Link>, bool) {
+    let (moved, remaining): (Vec<Link>, Vec<Link>) = links
+        .into_iter()
+        .filter(|link| link.target == *target)
+        .partition(|link| link.tag == *link_tag);
+    (moved, !remaining.is_empty())
+}
+
+/// Drop links authored by any blocked agent. Remaining links are returned in the order they were made, truncated to limit;
+/// so blocked links never use up the limit
+pub(crate) fn exclude_authors(
+    links: Vec<(AgentPubKey, Link)>,
+    blocked: &[AgentPubKey],
+    limit: Option<usize>,
+) -> Vec<Link> {
+    let mut out = links
+        .into_iter()
+        .filter(|(author, _)| !blocked.contains(author))
+        .map(|(_, link)| link)
+        .collect::<Vec<Link>>();
+    sort_links(&mut out);
+    if let Some(limit) = limit {
+        out.truncate(limit);
+    };
+    out
+}
+
+/// Distinct authors of links; ordered by when each author first linked
+pub(crate) fn distinct_authors(mut links: Vec<(AgentPubKey, Link)>) -> Vec<AgentPubKey> {
+    links.sort_by(|(_, a), (_, b)| {
+        a.timestamp.cmp(&b.timestamp).then_with(|| {
+            a.create_link_hash
+                .get_raw_39()
+                .cmp(b.create_link_hash.get_raw_39())
+        })
+    });
+    let mut seen = std::collections::HashSet::new();
+    links
+        .into_iter()
+        .map(|(author, _)| author)
+        .filter(|author| seen.insert(author.clone()))
+        .collect()
+}
+
+/// Split from..until into count buckets of equal length; oldest first. The last bucket ends exactly at until.
+/// Errors if count is 0 or until is not after from
+pub(crate) fn bucket_bounds(
+    from: &DateTime<Utc>,
+    until: &DateTime<Utc>,
+    count: usize,
+) -> IndexResult<Vec<(DateTime<Utc>, DateTime<Utc>)>> {
+    if count == 0 {
+        return Err(IndexError::RequestError("Bucket count must be at least 1"));
+    };
+    let span = (*until - *from)
+        .num_nanoseconds()
+        .ok_or(IndexError::RequestError("Time span is too large to bucket"))?;
+    if span <= 0 {
+        return Err(IndexError::RequestError("Until must be after from"));
+    };
+    let boundary = |i: usize| {
+        *from + chrono::Duration::nanoseconds((span as i128 * i as i128 / count as i128) as i64)
+    };
+    Ok((0..count).map(|i| (boundary(i), boundary(i + 1))).collect())
+}
+
+/// Place each (time, target) into the bucket whose start (inclusive) & end (exclusive) cover its time; items outside every bucket
+/// are dropped. Returns (start, end, targets) for every bucket with targets ordered by time
+pub(crate) fn bucket_by_time(
+    mut items: Vec<(DateTime<Utc>, EntryHash)>,
+    buckets: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>, Vec<EntryHash>)> {
+    items.sort_by(|(a, _), (b, _)| a.cmp(b));
+    buckets
+        .into_iter()
+        .map(|(start, end)| {
+            let targets = items
+                .iter()
+                .filter(|(time, _)| *time >= start && *time < end)
+                .map(|(_, target)| target.to_owned())
+                .collect();
+            (start, end, targets)
+        })
+        .collect()
+}
+
+/// Whether author has already made limit links among links; counting only links with tag if given. Mirrors the count made by
+/// [`crate::validate_index_link_limit()`] so that links which would fail validation are not attempted
+pub(crate) fn at_link_limit(
+    links: &[(AgentPubKey, Link)],
+    author: &AgentPubKey,
+    tag: Option<&LinkTag>,
+    limit: usize,
+) -> bool {
+    links
+        .iter()
+        .filter(|(link_author, link)| {
+            link_author == author && tag.map(|tag| *tag == link.tag).unwrap_or(true)
+        })
+        .count()
+        >= limit
+}
+
+/// Load a path entry from the hash of the path
+pub(crate) fn load_path(hash: EntryHash) -> IndexResult<Option<Path>> {
+    match get(hash, GetOptions::latest())? {
+        Some(element) => Ok(Some(element.entry().to_app_option()?.ok_or(
+            IndexError::InternalError("Expected element to contain app entry data"),
+        )?)),
+        None => Ok(None),
+    }
+}
+
+/// Resolve a link target. Since a get can transiently fail in a large DHT, a get which errors is retried up to GET_RETRY_ATTEMPTS
+/// times; a target which resolves to None is absent and is not retried. Zome calls cannot sleep so retries are made immediately
+pub(crate) fn get_target_with_retry(target: EntryHash) -> IndexResult<Option<Element>> {
+    get_target_with_consistency(target, &Consistency::Latest)
+}
+
+/// Same as [`get_target_with_retry()`] but reading with the given consistency. Cached reads which miss locally are retried
+/// against the network before the target is considered absent; so cold nodes do not report targets which exist as missing
+pub(crate) fn get_target_with_consistency(
+    target: EntryHash,
+    consistency: &Consistency,
+) -> IndexResult<Option<Element>> {
+    retry_resolve(*GET_RETRY_ATTEMPTS, || match consistency {
+        Consistency::Cached => resolve_with_fallback(
+            || Ok(get(target.clone(), GetOptions::content())?),
+            || Ok(get(target.clone(), GetOptions::latest())?),
+        ),
+        Consistency::Latest => Ok(get(target.clone(), GetOptions::latest())?),
+    })
+}
+
+/// Resolve using local; only calling network if local resolves to None
+pub(crate) fn resolve_with_fallback<T, L, N>(local: L, network: N) -> IndexResult<Option<T>>
+where
+    L: FnOnce() -> IndexResult<Option<T>>,
+    N: FnOnce() -> IndexResult<Option<T>>,
+{
+    match local()? {
+        Some(value) => Ok(Some(value)),
+        None => network(),
+    }
+}
+
+/// Call resolve up to attempts times while it errors (temporarily unresolved). A resolved value or None (definitely absent) is
+/// returned at once; if every attempt errored the last error is returned
+pub(crate) fn retry_resolve<T, F: FnMut() -> IndexResult<Option<T>>>(
+    attempts: usize,
+    mut resolve: F,
+) -> IndexResult<Option<T>> {
+    let mut last_error = None;
+    for _ in 0..attempts.max(1) {
+        match resolve() {
+            Ok(resolved) => return Ok(resolved),
+            Err(err) => last_error = Some(err),
+        };
+    }
+    Err(last_error.expect("At least one attempt is made"))
+}
+
+/// Link tag used for links from an indexed entry back to the time path it was indexed under. Tag is namespaced by index
+/// so that entries indexed under multiple indexes keep a separate set of back links for each index
+pub(crate) fn time_path_link_tag(index: &str) -> LinkTag {
+    LinkTag::new(format!("{}:{}", TIME_PATH_LINK_TAG, index))
+}
+
+/// Get the links from an indexed entry back to the time paths of index. Link tags are prefix matched, so back links of other indexes
+/// whose name starts with index (i.e `posts` & `posts_archive`) are filtered out
+pub(crate) fn get_time_path_links(target: EntryHash, index: &str) -> IndexResult<Vec<Link>> {
+    let tag = time_path_link_tag(index);
+    Ok(with_exact_tag(get_links(target, Some(tag.clone()))?, &tag))
+}
+
+/// Keep only links whose tag is exactly tag
+pub(crate) fn with_exact_tag(links: Vec<Link>, tag: &LinkTag) -> Vec<Link> {
+    links.into_iter().filter(|link| link.tag == *tag).collect()
+}
+
+/// Shard an entry is placed in when the root of an index is spread across shards. Uses the DHT location of hash so that an
+/// entry always lands in the same shard
+pub(crate) fn shard_for(hash: &EntryHash, shards: usize) -> usize {
+    if shards <= 1 {
+        return 0;
+    };
+    let raw = hash.get_raw_36();
+    let loc = u32::from_le_bytes([raw[32], raw[33], raw[34], raw[35]]);
+    loc as usize % shards
+}
+
+/// Name of the index root for a given shard. Shard 0 uses the unsharded index name so that entries indexed before sharding
+/// was configured remain readable
+pub(crate) fn shard_index_name(index: &str, shard: usize) -> String {
+    if shard == 0 {
+        index.to_owned()
+    } else {
+        format!("{}#shard{}", index, shard)
+    }
+}
+
+/// Name of the index a shard's index root belongs to; inverse of [`shard_index_name()`]
+pub(crate) fn unsharded_index_name(shard_index: &str) -> &str {
+    match shard_index.rfind("#shard") {
+        Some(at) if shard_index[at + "#shard".len()..].parse::<usize>().is_ok() => {
+            &shard_index[..at]
         }
-        IndexType::Second => {
-            if INDEX_DEPTH.contains(&time_index) {
-                from_timestamp.second()
-            } else {
-                return Ok(());
+        _ => shard_index,
+    }
+}
+
+/// Names of the index roots of every shard of index
+pub(crate) fn shard_index_names(index: &str, shards: usize) -> Vec<String> {
+    (0..shards.max(1))
+        .map(|shard| shard_index_name(index, shard))
+        .collect()
+}
+
+/// Read from the time tree of every shard of index in turn, concatenating the results. Entries are placed in a single shard by their
+/// hash; so results read from different shards never hold links to the same entry
+pub(crate) fn read_shards<T, F: FnMut(String) -> IndexResult<Vec<T>>>(
+    index: &str,
+    shards: usize,
+    mut read: F,
+) -> IndexResult<Vec<T>> {
+    let mut out = vec![];
+    for shard in shard_index_names(index, shards) {
+        out.append(&mut read(shard)?);
+    }
+    Ok(out)
+}
+
+/// Group the paths of chunks found in several shards by chunk; oldest chunk first. Paths of each chunk are kept in the order given
+pub(crate) fn group_by_chunk<P>(chunks: Vec<(Index, P)>) -> Vec<(Index, Vec<P>)> {
+    let mut grouped = std::collections::BTreeMap::new();
+    for (chunk, path) in chunks {
+        grouped.entry(chunk).or_insert_with(Vec::new).push(path);
+    }
+    grouped.into_iter().collect()
+}
+
+/// Combine results read from each shard of an index. Results for the same index found in multiple shards are merged into one
+pub(crate) fn merge_shard_results(
+    shards: Vec<Vec<EntryChunkIndex>>,
+) -> IndexResult<Vec<EntryChunkIndex>> {
+    let mut out: Vec<EntryChunkIndex> = vec![];
+    for chunk in shards.into_iter().flatten() {
+        match out
+            .iter()
+            .position(|existing| existing.index == chunk.index)
+        {
+            Some(position) => {
+                let existing = out.remove(position);
+                out.insert(position, existing.merge(chunk)?);
             }
+            None => out.push(chunk),
         }
-    };
-    time_path.push(Component::from(
-        T::try_into(T::from(from_time))?.bytes().to_owned(),
-    ));
-    Ok(())
+    }
+    Ok(out)
+}
+
+/// Resolve the time chunks are aligned from. Uses genesis declared in DNA properties and falls back to UNIX epoch
+pub(crate) fn resolve_genesis(declared: Option<u64>) -> std::time::Duration {
+    declared
+        .map(std::time::Duration::from_millis)
+        .unwrap_or(std::time::Duration::from_secs(0))
+}
+
+/// Number of intervals between genesis and the start of index. Errors if index is not aligned to interval boundaries from genesis
+pub(crate) fn chunk_offset(
+    index: &Index,
+    interval: &std::time::Duration,
+    genesis: &std::time::Duration,
+) -> IndexResult<u64> {
+    validate_index_alignment(index, interval, genesis)?;
+    Ok(((index.from - *genesis).as_nanos() / interval.as_nanos()) as u64)
 }
 
 /// Determine correct chunk position for a given timestamp
-pub(crate) fn get_index_for_timestamp(time: DateTime<Utc>) -> Index {
-    let now = std::time::Duration::new(time.timestamp() as u64, time.timestamp_subsec_nanos());
-    let time_frame = MAX_CHUNK_INTERVAL.as_nanos() as f64;
+pub(crate) fn get_index_for_timestamp(time: DateTime<Utc>) -> IndexResult<Index> {
+    index_for_timestamp(datetime_to_duration(&time)?, &MAX_CHUNK_INTERVAL, &GENESIS)
+}
+
+/// Determine chunk position for a given time (as duration since UNIX epoch) where chunks of interval length are aligned from genesis
+pub(crate) fn index_for_timestamp(
+    time: std::time::Duration,
+    interval: &std::time::Duration,
+    genesis: &std::time::Duration,
+) -> IndexResult<Index> {
+    if time < *genesis {
+        return Err(IndexError::RequestError(
+            "Cannot index at a time before genesis",
+        ));
+    };
+    let time_frame = interval.as_nanos();
+    let since_genesis = (time - *genesis).as_nanos();
 
-    let chunk_index_start = (now.as_nanos() as f64 / time_frame).floor() as u64;
-    let chunk_start = time_frame as u64 * chunk_index_start;
-    let chunk_end = time_frame as u64 * (chunk_index_start + 1_u64);
+    let chunk_index_start = since_genesis / time_frame;
+    let chunk_start = genesis.as_nanos() + time_frame * chunk_index_start;
+    let chunk_end = chunk_start + time_frame;
 
-    let chunk_start = std::time::Duration::from_nanos(chunk_start);
-    let chunk_end = std::time::Duration::from_nanos(chunk_end);
-    Index {
+    let chunk_start = std::time::Duration::from_nanos(chunk_start as u64);
+    let chunk_end = std::time::Duration::from_nanos(chunk_end as u64);
+    Ok(Index {
         from: chunk_start,
         until: chunk_end,
+    })
+}
+
+/// Iterator stepping backwards one interval at a time over the chunks which overlap from..until; stopping at from or genesis.
+/// Chunks are only loaded as the iterator is advanced; chunks which load to None are skipped. Iteration ends after an error
+pub(crate) struct ChunksDesc<T, F: FnMut(Index) -> IndexResult<Option<T>>> {
+    next: Option<Index>,
+    lowest: std::time::Duration,
+    interval: std::time::Duration,
+    load: F,
+}
+
+impl<T, F: FnMut(Index) -> IndexResult<Option<T>>> ChunksDesc<T, F> {
+    /// Create iterator over chunks overlapping from..until; until is exclusive
+    pub(crate) fn new(
+        from: std::time::Duration,
+        until: std::time::Duration,
+        interval: &std::time::Duration,
+        genesis: &std::time::Duration,
+        load: F,
+    ) -> IndexResult<ChunksDesc<T, F>> {
+        let lowest = from.max(*genesis);
+        let next = if until > lowest {
+            Some(index_for_timestamp(
+                until - std::time::Duration::from_nanos(1),
+                interval,
+                genesis,
+            )?)
+        } else {
+            None
+        };
+        Ok(ChunksDesc {
+            next: next,
+            lowest: lowest,
+            interval: *interval,
+            load: load,
+        })
+    }
+}
+
+impl<T, F: FnMut(Index) -> IndexResult<Option<T>>> Iterator for ChunksDesc<T, F> {
+    type Item = IndexResult<T>;
+
+    fn next(&mut self) -> Option<IndexResult<T>> {
+        loop {
+            let chunk = self.next.take()?;
+            if chunk.from > self.lowest {
+                self.next = Some(Index {
+                    from: chunk.from - self.interval,
+                    until: chunk.from,
+                });
+            };
+            match (self.load)(chunk) {
+                Ok(Some(value)) => return Some(Ok(value)),
+                Ok(None) => continue,
+                Err(err) => {
+                    self.next = None;
+                    return Some(Err(err));
+                }
+            };
+        }
+    }
+}
+
+/// Lazily load each target; load is only called as the iterator is advanced. Targets which load to None are skipped
+pub(crate) fn load_lazily<T, F: FnMut(EntryHash) -> IndexResult<Option<T>>>(
+    targets: Vec<EntryHash>,
+    mut load: F,
+) -> impl Iterator<Item = IndexResult<T>> {
+    targets
+        .into_iter()
+        .filter_map(move |target| load(target).transpose())
+}
+
+/// Keep links whose entry time, as given by entry_time, falls between from (inclusive) & until (exclusive). Links are visited in the order
+/// they were made and entry_time is not called once limit links have been kept. Links with no entry time are dropped
+pub(crate) fn filter_links_in_window<F: FnMut(&Link) -> IndexResult<Option<DateTime<Utc>>>>(
+    mut links: Vec<Link>,
+    from: &DateTime<Utc>,
+    until: &DateTime<Utc>,
+    limit: Option<usize>,
+    mut entry_time: F,
+) -> IndexResult<Vec<Link>> {
+    sort_links(&mut links);
+    let mut out = vec![];
+    for link in links {
+        if limit.map(|limit| out.len() >= limit).unwrap_or(false) {
+            break;
+        };
+        match entry_time(&link)? {
+            Some(time) if time >= *from && time < *until => out.push(link),
+            _ => (),
+        };
+    }
+    Ok(out)
+}
+
+/// Does links contain a link to target with exactly tag. Needed as link tags given to get_links are prefix matched
+pub(crate) fn has_link(links: &[Link], target: &EntryHash, tag: &LinkTag) -> bool {
+    links
+        .iter()
+        .any(|link| link.target == *target && link.tag == *tag)
+}
+
+/// Order links deterministically by timestamp; ties are broken by create link hash. Returns window of ordered links starting at offset
+pub(crate) fn page_links(mut links: Vec<Link>, offset: usize, limit: usize) -> Vec<Link> {
+    sort_links(&mut links);
+    links.into_iter().skip(offset).take(limit).collect()
+}
+
+/// Sort links into the order they were made; ties are broken by create link hash so ordering is deterministic
+pub(crate) fn sort_links(links: &mut Vec<Link>) {
+    links.sort_by(|a, b| {
+        a.timestamp.cmp(&b.timestamp).then_with(|| {
+            a.create_link_hash
+                .get_raw_39()
+                .cmp(b.create_link_hash.get_raw_39())
+        })
+    });
+}
+
+/// Group link targets by link tag. Targets in each group are in the order links were made
+pub(crate) fn group_links_by_tag(mut links: Vec<Link>) -> HashMap<LinkTag, Vec<EntryHash>> {
+    sort_links(&mut links);
+    let mut out: HashMap<LinkTag, Vec<EntryHash>> = HashMap::new();
+    for link in links {
+        out.entry(link.tag)
+            .or_insert_with(Vec::new)
+            .push(link.target);
+    }
+    out
+}
+
+/// Count links by their tag. Every link is counted; an entry linked twice under one tag is counted twice
+pub(crate) fn count_links_by_tag(links: &[Link]) -> HashMap<LinkTag, usize> {
+    let mut out: HashMap<LinkTag, usize> = HashMap::new();
+    for link in links {
+        *out.entry(link.tag.clone()).or_insert(0) += 1;
+    }
+    out
+}
+
+/// Get the index which sits chunks intervals before index. Errors if the resulting index would start before genesis
+pub(crate) fn step_back(
+    index: &Index,
+    chunks: u32,
+    interval: &std::time::Duration,
+    genesis: &std::time::Duration,
+) -> IndexResult<Index> {
+    let from = interval
+        .checked_mul(chunks)
+        .and_then(|offset| index.from.checked_sub(offset))
+        .ok_or(IndexError::RequestError(
+            "Cannot step back to a time before UNIX epoch",
+        ))?;
+    index_for_timestamp(from, interval, genesis)
+}
+
+/// Check a link made from index points at an entry with an entry time inside of index. entry_time should be None if link target could not be resolved
+pub(crate) fn check_link_integrity(
+    index: &Index,
+    link: Link,
+    entry_time: Option<DateTime<Utc>>,
+) -> Option<IntegrityIssue> {
+    match entry_time {
+        None => Some(IntegrityIssue::Dangling {
+            index: index.to_owned(),
+            link: link,
+        }),
+        Some(entry_time) => {
+            let (from, until) = index.window();
+            if entry_time < from || entry_time >= until {
+                Some(IntegrityIssue::Misplaced {
+                    index: index.to_owned(),
+                    link: link,
+                    entry_time: entry_time,
+                })
+            } else {
+                None
+            }
+        }
     }
 }
 
+/// Intervals which can be recommended by [`recommend_interval()`]. Each evenly divides the deepest level of the index depth it resolves to
+pub(crate) const SUGGESTED_INTERVALS: [std::time::Duration; 19] = [
+    std::time::Duration::from_millis(100),
+    std::time::Duration::from_millis(250),
+    std::time::Duration::from_millis(500),
+    std::time::Duration::from_secs(1),
+    std::time::Duration::from_secs(5),
+    std::time::Duration::from_secs(10),
+    std::time::Duration::from_secs(15),
+    std::time::Duration::from_secs(30),
+    std::time::Duration::from_secs(60),
+    std::time::Duration::from_secs(5 * 60),
+    std::time::Duration::from_secs(10 * 60),
+    std::time::Duration::from_secs(15 * 60),
+    std::time::Duration::from_secs(30 * 60),
+    std::time::Duration::from_secs(60 * 60),
+    std::time::Duration::from_secs(2 * 60 * 60),
+    std::time::Duration::from_secs(3 * 60 * 60),
+    std::time::Duration::from_secs(6 * 60 * 60),
+    std::time::Duration::from_secs(12 * 60 * 60),
+    std::time::Duration::from_secs(24 * 60 * 60),
+];
+
+/// Recommend a chunk interval given the number of links found in each populated chunk of a window which is chunks_in_window
+/// intervals long. The 90th percentile chunk (counting unpopulated chunks as empty) is used so that busy periods are kept close to
+/// target_links_per_chunk without a single burst shrinking the interval for the whole DHT. The largest suggested interval which
+/// keeps chunks at or below target is returned; since larger intervals mean less chunks must be visited by range queries
+pub(crate) fn recommend_interval(
+    link_counts: &[usize],
+    chunks_in_window: usize,
+    interval: &std::time::Duration,
+    target_links_per_chunk: usize,
+) -> std::time::Duration {
+    let chunks = std::cmp::max(chunks_in_window, link_counts.len());
+    if chunks == 0 {
+        return *interval;
+    };
+    let mut counts = link_counts.to_vec();
+    counts.resize(chunks, 0);
+    counts.sort();
+    let percentile = counts[(chunks * 9 + 9) / 10 - 1];
+    if percentile == 0 {
+        return SUGGESTED_INTERVALS[SUGGESTED_INTERVALS.len() - 1];
+    };
+
+    let ideal = interval.as_nanos() * target_links_per_chunk as u128 / percentile as u128;
+    SUGGESTED_INTERVALS
+        .iter()
+        .rev()
+        .find(|suggested| suggested.as_nanos() <= ideal)
+        .unwrap_or(&SUGGESTED_INTERVALS[0])
+        .to_owned()
+}
+
 mod util_tests {
     #[test]
     fn test_get_chunk_time() {
-        use crate::utils::get_index_for_timestamp;
+        use crate::utils::index_for_timestamp;
+        use std::time::Duration;
 
         //Hard coded interval
         let interval = 10;
-        let chunk = get_index_for_timestamp(chrono::Utc::now());
+        let now = chrono::Utc::now();
+        let now = Duration::new(now.timestamp() as u64, now.timestamp_subsec_nanos());
+        let chunk =
+            index_for_timestamp(now, &Duration::from_secs(interval), &Duration::from_secs(0))
+                .unwrap();
         assert_eq!(chunk.from.as_secs() % interval, 0);
         assert_eq!(chunk.until.as_secs() % interval, 0);
     }
 
     #[test]
-    fn translate_sort() {
-        let str_nums = vec!["2", "1"];
-        let nums = str_nums
+    fn test_genesis_aligned_chunk_time() {
+        use crate::utils::{index_for_timestamp, resolve_genesis};
+        use std::time::Duration;
+
+        //Genesis not declared in properties aligns chunks to UNIX epoch
+        assert_eq!(resolve_genesis(None), Duration::from_secs(0));
+        //Genesis declared in properties
+        let genesis = resolve_genesis(Some(1_628_776_805_000));
+        assert_eq!(genesis, Duration::from_millis(1_628_776_805_000));
+
+        let interval = Duration::from_secs(10);
+        let chunk = index_for_timestamp(
+            Duration::from_millis(1_628_776_833_250),
+            &interval,
+            &genesis,
+        )
+        .unwrap();
+        assert_eq!(chunk.from, Duration::from_secs(1_628_776_825));
+        assert_eq!(chunk.until, Duration::from_secs(1_628_776_835));
+
+        assert!(
+            index_for_timestamp(Duration::from_secs(1_628_776_800), &interval, &genesis).is_err()
+        );
+    }
+
+    #[test]
+    fn test_genesis_with_skewed_clocks_and_properties() {
+        use crate::errors::IndexError;
+        use crate::utils::{chunk_offset, current_chunk_for, resolve_genesis};
+        use std::time::Duration;
+
+        let interval = Duration::from_secs(10);
+        let genesis = resolve_genesis(Some(1_628_776_800_000));
+
+        //Alice's clock is 4s ahead of Bob's; both land in the same chunk or in neighbouring chunks on the same grid
+        let skew = Duration::from_secs(4);
+        let bob_now = Duration::from_millis(1_628_776_803_000);
+        let alice_chunk = current_chunk_for(bob_now + skew, &interval, &genesis).unwrap();
+        let bob_chunk = current_chunk_for(bob_now, &interval, &genesis).unwrap();
+        assert_eq!(alice_chunk, bob_chunk);
+        let bob_now = Duration::from_millis(1_628_776_807_000);
+        let alice_chunk = current_chunk_for(bob_now + skew, &interval, &genesis).unwrap();
+        let bob_chunk = current_chunk_for(bob_now, &interval, &genesis).unwrap();
+        assert_eq!(chunk_offset(&bob_chunk, &interval, &genesis).unwrap(), 0);
+        assert_eq!(chunk_offset(&alice_chunk, &interval, &genesis).unwrap(), 1);
+
+        //Carol's clock is behind genesis; she is told her clock is wrong instead of indexing into a chunk no one else uses
+        assert!(matches!(
+            current_chunk_for(genesis - skew, &interval, &genesis),
+            Err(IndexError::ClockBeforeGenesis)
+        ));
+
+        //An agent running with different properties which fall back to UNIX epoch shares the grid only if the declared
+        //genesis sits on an interval boundary; otherwise its chunks are rejected as misaligned
+        let epoch = resolve_genesis(None);
+        let dave_chunk = current_chunk_for(bob_now, &interval, &epoch).unwrap();
+        assert_eq!(dave_chunk, bob_chunk);
+        let offset_genesis = resolve_genesis(Some(1_628_776_805_000));
+        let erin_chunk = current_chunk_for(bob_now, &interval, &offset_genesis).unwrap();
+        assert_ne!(erin_chunk, bob_chunk);
+        assert!(chunk_offset(&erin_chunk, &interval, &genesis).is_err());
+    }
+
+    #[test]
+    fn test_time_path_link_tag() {
+        use crate::utils::time_path_link_tag;
+        use hdk::prelude::LinkTag;
+
+        assert_eq!(time_path_link_tag("a"), LinkTag::new("time_path:a"));
+        assert_ne!(time_path_link_tag("a"), time_path_link_tag("b"));
+        //Legacy tag should still prefix match namespaced tags so removal can find old & new back links
+        assert!(time_path_link_tag("a")
+            .0
+            .starts_with(&LinkTag::new("time_path").0));
+    }
+
+    #[test]
+    fn test_time_path_links_do_not_leak_between_indexes() {
+        use crate::entries::IndexType;
+        use crate::test_utils::test_link;
+        use crate::utils::{build_time_path, time_path_link_tag, with_exact_tag};
+        use std::time::Duration;
+
+        //Index names where one is a prefix of the other
+        let (a, ab) = ("a", "ab");
+        let from = Duration::from_secs(1_628_776_880);
+        let link = |i: u8, index: &str| test_link(i, time_path_link_tag(index).0);
+
+        //Time trees of the two indexes share no path
+        let path_a = build_time_path(a.to_owned(), &from, &IndexType::Second, None).unwrap();
+        let path_ab = build_time_path(ab.to_owned(), &from, &IndexType::Second, None).unwrap();
+        assert_ne!(path_a[0], path_ab[0]);
+        assert_eq!(path_a[1..], path_ab[1..]);
+
+        //Prefix matched query for a also returns back links of ab; these are filtered out
+        let queried = vec![link(1, a), link(2, ab), link(3, a)];
+        assert!(queried
+            .iter()
+            .all(|link| link.tag.0.starts_with(&time_path_link_tag(a).0)));
+        assert_eq!(
+            with_exact_tag(queried.clone(), &time_path_link_tag(a)),
+            vec![link(1, a), link(3, a)]
+        );
+        assert_eq!(
+            with_exact_tag(queried, &time_path_link_tag(ab)),
+            vec![link(2, ab)]
+        );
+    }
+
+    #[test]
+    fn test_group_links_by_author() {
+        use crate::test_utils::test_link;
+        use crate::utils::group_links_by_author;
+        use hdk::prelude::*;
+
+        let alice = AgentPubKey::from_raw_36(vec![1; 36]);
+        let bob = AgentPubKey::from_raw_36(vec![2; 36]);
+        let carol = AgentPubKey::from_raw_36(vec![3; 36]);
+        let link = |i: u8| test_link(i, "test");
+        let links = vec![
+            (bob.clone(), link(4)),
+            (alice.clone(), link(1)),
+            (carol.clone(), link(3)),
+            (alice.clone(), link(2)),
+            (bob.clone(), link(5)),
+            (bob.clone(), link(6)),
+        ];
+
+        let grouped = group_links_by_author(links.clone(), 10, None);
+        assert_eq!(grouped.len(), 3);
+        assert_eq!(grouped[&alice], vec![link(1).target, link(2).target]);
+        assert_eq!(
+            grouped[&bob],
+            vec![link(4).target, link(5).target, link(6).target]
+        );
+        assert_eq!(grouped[&carol], vec![link(3).target]);
+
+        //Per author cap
+        let grouped = group_links_by_author(links.clone(), 2, None);
+        assert_eq!(grouped[&bob].len(), 2);
+
+        //Total limit
+        let grouped = group_links_by_author(links, 10, Some(3));
+        assert_eq!(
+            grouped.values().map(|targets| targets.len()).sum::<usize>(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_naivedatetime_to_components() {
+        use crate::entries::{IndexType, TimeIndex};
+        use crate::utils::{
+            deepest_index_type, naivedatetime_to_components, time_path_depth, TIME_TREE_LEVELS,
+        };
+        use chrono::NaiveDate;
+        use std::convert::TryFrom;
+
+        let time = NaiveDate::from_ymd(2021, 8, 12).and_hms(14, 1, 30);
+        let expected = vec![2021, 8, 12, 14, 1, 30];
+        for (i, depth) in TIME_TREE_LEVELS.iter().enumerate() {
+            let components = naivedatetime_to_components(&time, depth).unwrap();
+            assert_eq!(components.len(), i + 1);
+            let values = components
+                .into_iter()
+                .map(|component| TimeIndex::try_from(component).unwrap().0)
+                .collect::<Vec<u32>>();
+            assert_eq!(values, expected[..i + 1].to_vec());
+        }
+
+        assert_eq!(
+            deepest_index_type(&[IndexType::Minute, IndexType::Hour, IndexType::Day]),
+            Some(IndexType::Minute)
+        );
+        assert_eq!(deepest_index_type(&[]), None);
+
+        assert_eq!(
+            time_path_depth(&[IndexType::Minute, IndexType::Hour, IndexType::Day]),
+            Some(5)
+        );
+        assert_eq!(time_path_depth(&[IndexType::Day]), Some(3));
+        assert_eq!(time_path_depth(&[]), None);
+    }
+
+    #[test]
+    fn test_retry_resolve() {
+        use crate::errors::IndexError;
+        use crate::utils::retry_resolve;
+
+        //Resolves on second attempt
+        let mut calls = 0;
+        let resolved = retry_resolve(3, || {
+            calls += 1;
+            if calls < 2 {
+                Err(IndexError::InternalError("Could not resolve"))
+            } else {
+                Ok(Some(calls))
+            }
+        });
+        assert_eq!(resolved.unwrap(), Some(2));
+        assert_eq!(calls, 2);
+
+        //Definitely absent
+        let mut calls = 0;
+        let resolved = retry_resolve::<(), _>(3, || {
+            calls += 1;
+            Ok(None)
+        });
+        assert_eq!(resolved.unwrap(), None);
+        assert_eq!(calls, 1);
+
+        //Absent after a temporary failure
+        let mut calls = 0;
+        let resolved = retry_resolve::<(), _>(3, || {
+            calls += 1;
+            if calls < 2 {
+                Err(IndexError::InternalError("Could not resolve"))
+            } else {
+                Ok(None)
+            }
+        });
+        assert_eq!(resolved.unwrap(), None);
+        assert_eq!(calls, 2);
+
+        //Temporarily unresolved on every attempt
+        let mut calls = 0;
+        let resolved = retry_resolve::<(), _>(3, || {
+            calls += 1;
+            Err(IndexError::InternalError("Could not resolve"))
+        });
+        assert!(resolved.is_err());
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_chunk_offset() {
+        use crate::entries::Index;
+        use crate::utils::chunk_offset;
+        use std::time::Duration;
+
+        let interval = Duration::from_secs(10);
+        let genesis = Duration::from_millis(1_628_776_805_000);
+        let genesis_chunk = Index {
+            from: genesis,
+            until: genesis + interval,
+        };
+        assert_eq!(
+            chunk_offset(&genesis_chunk, &interval, &genesis).unwrap(),
+            0
+        );
+
+        let later_chunk = Index {
+            from: genesis + interval * 3,
+            until: genesis + interval * 4,
+        };
+        assert_eq!(chunk_offset(&later_chunk, &interval, &genesis).unwrap(), 3);
+
+        let unaligned_chunk = Index {
+            from: genesis + Duration::from_secs(5),
+            until: genesis + Duration::from_secs(15),
+        };
+        assert!(chunk_offset(&unaligned_chunk, &interval, &genesis).is_err());
+    }
+
+    #[test]
+    fn translate_sort() {
+        let str_nums = vec!["2", "1"];
+        let nums = str_nums
             .clone()
             .into_iter()
             .map(|val| val.parse::<i32>().unwrap())
@@ -213,4 +1696,1512 @@ mod util_tests {
         let ordered_nums = permutation.apply_slice(&str_nums[..]);
         assert_eq!(ordered_nums, vec!["1", "2"]);
     }
+
+    #[test]
+    fn test_recommend_interval() {
+        use crate::utils::{recommend_interval, SUGGESTED_INTERVALS};
+        use std::time::Duration;
+
+        let interval = Duration::from_secs(60);
+        //Dense; every chunk holds 4x target
+        let dense = vec![400; 60];
+        assert_eq!(
+            recommend_interval(&dense, 60, &interval, 100),
+            Duration::from_secs(15)
+        );
+        //Sparse; 1 link per chunk
+        let sparse = vec![1; 60];
+        assert_eq!(
+            recommend_interval(&sparse, 60, &interval, 100),
+            Duration::from_secs(60 * 60)
+        );
+        //Mostly empty window should recommend largest interval
+        assert_eq!(
+            recommend_interval(&[5], 60, &interval, 100),
+            Duration::from_secs(24 * 60 * 60)
+        );
+        //Single burst does not shrink the interval
+        let mut burst = vec![100; 60];
+        burst[0] = 10_000;
+        assert_eq!(
+            recommend_interval(&burst, 60, &interval, 100),
+            Duration::from_secs(60)
+        );
+        //Extremely dense falls back to smallest suggested interval
+        assert_eq!(
+            recommend_interval(&[1_000_000], 1, &interval, 100),
+            SUGGESTED_INTERVALS[0]
+        );
+        //Empty window leaves interval unchanged
+        assert_eq!(recommend_interval(&[], 0, &interval, 100), interval);
+    }
+
+    #[test]
+    fn test_suggested_intervals_valid() {
+        use crate::utils::{index_depth_for_interval, SUGGESTED_INTERVALS};
+        use crate::validation::validate_index_depth;
+
+        for interval in SUGGESTED_INTERVALS.iter() {
+            let depth = index_depth_for_interval(interval);
+            assert!(validate_index_depth(&depth, interval).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_page_links() {
+        use crate::test_utils::test_link;
+        use crate::utils::page_links;
+        use hdk::prelude::*;
+
+        //Every third link shares a timestamp so ordering must fall back onto link hash
+        let link = |i: u8| Link {
+            timestamp: Timestamp::from_micros((i / 3) as i64),
+            ..test_link(i, "test")
+        };
+        let links = (0..30).map(link).collect::<Vec<Link>>();
+        let mut shuffled = links.clone();
+        shuffled.reverse();
+        shuffled.swap(3, 17);
+
+        let hashes = |links: Vec<Link>| {
+            links
+                .into_iter()
+                .map(|link| link.create_link_hash)
+                .collect::<Vec<HeaderHash>>()
+        };
+        let mut pages = vec![];
+        for offset in vec![0, 10, 20] {
+            let page = hashes(page_links(shuffled.clone(), offset, 10));
+            assert_eq!(page.len(), 10);
+            //Same page is returned regardless of order links are received in
+            assert_eq!(page, hashes(page_links(links.clone(), offset, 10)));
+            pages.extend(page);
+        }
+        //Pages do not overlap & have no gaps
+        assert_eq!(pages, hashes(links.clone()));
+        assert!(page_links(links.clone(), 30, 10).is_empty());
+        assert_eq!(page_links(links, 25, 10).len(), 5);
+    }
+
+    #[test]
+    fn test_links_after_cursor() {
+        use crate::entries::Index;
+        use crate::test_utils::test_link;
+        use crate::utils::links_after_cursor;
+        use crate::{EntryChunkIndex, PageCursor};
+        use chrono::{TimeZone, Utc};
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        let start = 1_628_776_800;
+        let chunk = |i: u64| Index {
+            from: Duration::from_secs(start + i * 10),
+            until: Duration::from_secs(start + (i + 1) * 10),
+        };
+        //Link i is made i seconds after start; links 7 & 8 are made at the same instant
+        let link = |i: u8| {
+            let seconds = if i == 8 { 7 } else { i as i64 };
+            Link {
+                timestamp: Timestamp::from_micros((start as i64 + seconds) * 1_000_000),
+                ..test_link(i, "test")
+            }
+        };
+        //State of the DHT as seen by each poll; links are added between polls
+        let dht = |links: Vec<u8>| {
+            vec![EntryChunkIndex {
+                index: chunk(0),
+                links: links.into_iter().map(link).collect(),
+                version: crate::ENTRY_CHUNK_INDEX_VERSION,
+            }]
+        };
+        let targets = |chunks: Vec<EntryChunkIndex>| {
+            chunks
+                .into_iter()
+                .flat_map(|chunk| chunk.links.into_iter().map(|link| link.target))
+                .collect::<Vec<EntryHash>>()
+        };
+
+        let cursor = PageCursor {
+            from: Utc.timestamp(start as i64, 0),
+            seen: vec![],
+        };
+        //First poll
+        let (new, cursor) = links_after_cursor(dht(vec![1, 2, 7]), cursor).unwrap();
+        assert_eq!(
+            targets(new),
+            vec![link(1).target, link(2).target, link(7).target]
+        );
+        assert_eq!(cursor.from, Utc.timestamp(start as i64 + 7, 0));
+
+        //Second poll; new link made at the same instant as the newest seen link
+        let (new, cursor) = links_after_cursor(dht(vec![1, 2, 7, 8]), cursor).unwrap();
+        assert_eq!(targets(new), vec![link(8).target]);
+        assert_eq!(cursor.seen.len(), 2);
+
+        //Third poll; another link made
+        let (new, cursor) = links_after_cursor(dht(vec![1, 2, 7, 8, 9]), cursor).unwrap();
+        assert_eq!(targets(new), vec![link(9).target]);
+        assert_eq!(cursor.from, Utc.timestamp(start as i64 + 9, 0));
+        assert_eq!(cursor.seen, vec![link(9).create_link_hash]);
+
+        //Polling again without new links delivers nothing & leaves cursor in place
+        let (new, next) = links_after_cursor(dht(vec![1, 2, 7, 8, 9]), cursor.clone()).unwrap();
+        assert!(new.is_empty());
+        assert_eq!(next, cursor);
+    }
+
+    #[test]
+    fn test_step_back_sub_second() {
+        use crate::entries::{IndexType, TimeIndex};
+        use crate::utils::{
+            duration_to_datetime, index_for_timestamp, naivedatetime_to_components, step_back,
+        };
+        use std::convert::TryFrom;
+        use std::time::Duration;
+
+        let interval = Duration::from_millis(250);
+        let genesis = Duration::from_secs(0);
+        let index = index_for_timestamp(
+            Duration::new(1_628_776_890, 300_000_000),
+            &interval,
+            &genesis,
+        )
+        .unwrap();
+        assert_eq!(index.from, Duration::new(1_628_776_890, 250_000_000));
+
+        let previous = step_back(&index, 4, &interval, &genesis).unwrap();
+        assert_eq!(previous.from, Duration::new(1_628_776_889, 250_000_000));
+        assert_eq!(previous.until, Duration::new(1_628_776_889, 500_000_000));
+        let previous = step_back(&index, 2, &interval, &genesis).unwrap();
+        assert_eq!(previous.from, Duration::new(1_628_776_889, 750_000_000));
+
+        //Path is derived from the whole second the index starts in; even when close to the next second
+        let second = |from: Duration| {
+            let components = naivedatetime_to_components(
+                &duration_to_datetime(&from).naive_utc(),
+                &IndexType::Second,
+            )
+            .unwrap();
+            TimeIndex::try_from(components.last().unwrap().to_owned())
+                .unwrap()
+                .0
+        };
+        assert_eq!(second(previous.from), 29);
+        assert_eq!(second(Duration::new(1_628_776_889, 999_999_999)), 29);
+
+        //Cannot step back past genesis
+        let genesis = Duration::new(1_628_776_889, 500_000_000);
+        assert!(step_back(&index, 3, &interval, &genesis).is_ok());
+        assert!(step_back(&index, 4, &interval, &genesis).is_err());
+    }
+
+    #[test]
+    fn test_check_link_integrity() {
+        use crate::entries::Index;
+        use crate::test_utils::test_link;
+        use crate::utils::check_link_integrity;
+        use crate::IntegrityIssue;
+        use chrono::{TimeZone, Utc};
+        use std::time::Duration;
+
+        let index = Index {
+            from: Duration::from_secs(1_628_776_880),
+            until: Duration::from_secs(1_628_776_890),
+        };
+        let link = |i: u8| test_link(i, "test");
+
+        //Correctly placed
+        assert_eq!(
+            check_link_integrity(&index, link(1), Some(Utc.timestamp(1_628_776_880, 0))),
+            None
+        );
+        assert_eq!(
+            check_link_integrity(&index, link(1), Some(Utc.timestamp(1_628_776_889, 999))),
+            None
+        );
+        //Dangling
+        assert!(matches!(
+            check_link_integrity(&index, link(2), None),
+            Some(IntegrityIssue::Dangling { .. })
+        ));
+        //Misplaced
+        assert!(matches!(
+            check_link_integrity(&index, link(3), Some(Utc.timestamp(1_628_776_890, 0))),
+            Some(IntegrityIssue::Misplaced { .. })
+        ));
+        assert!(matches!(
+            check_link_integrity(&index, link(3), Some(Utc.timestamp(1_628_776_000, 0))),
+            Some(IntegrityIssue::Misplaced { .. })
+        ));
+    }
+
+    #[test]
+    fn test_group_links_by_tag() {
+        use crate::test_utils::test_link;
+        use crate::utils::group_links_by_tag;
+        use hdk::prelude::*;
+
+        let link = test_link::<&str>;
+        let links = vec![
+            link(5, "reaction"),
+            link(2, "post"),
+            link(4, "post"),
+            link(1, "post"),
+            link(3, "reaction"),
+        ];
+
+        let grouped = group_links_by_tag(links);
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(
+            grouped[&LinkTag::new("post")],
+            vec![
+                link(1, "post").target,
+                link(2, "post").target,
+                link(4, "post").target
+            ]
+        );
+        assert_eq!(
+            grouped[&LinkTag::new("reaction")],
+            vec![link(3, "reaction").target, link(5, "reaction").target]
+        );
+    }
+
+    #[test]
+    fn test_count_links_by_tag() {
+        use crate::test_utils::test_link;
+        use crate::utils::count_links_by_tag;
+        use hdk::prelude::*;
+
+        let link = test_link::<&str>;
+        let links = vec![
+            link(1, "post"),
+            link(2, "reaction"),
+            link(3, "post"),
+            link(4, "reaction"),
+            link(5, "reaction"),
+            link(6, "comment"),
+            //Entry 1 is linked again under the same tag
+            link(1, "post"),
+        ];
+
+        let summary = count_links_by_tag(&links);
+        assert_eq!(summary.len(), 3);
+        assert_eq!(summary[&LinkTag::new("post")], 3);
+        assert_eq!(summary[&LinkTag::new("reaction")], 3);
+        assert_eq!(summary[&LinkTag::new("comment")], 1);
+        assert!(count_links_by_tag(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_filter_deleted() {
+        use crate::utils::filter_deleted;
+
+        let links = vec![
+            ("live", 0),
+            ("deleted", 1),
+            ("deleted twice", 2),
+            ("also live", 0),
+        ];
+        assert_eq!(
+            filter_deleted(links.clone(), false),
+            vec!["live", "also live"]
+        );
+        assert_eq!(
+            filter_deleted(links, true),
+            vec!["live", "deleted", "deleted twice", "also live"]
+        );
+    }
+
+    #[test]
+    fn test_checked_naivedatetime() {
+        use crate::errors::IndexError;
+        use crate::utils::checked_naivedatetime;
+        use chrono::NaiveDate;
+
+        assert_eq!(
+            checked_naivedatetime(2021, 8, 12, 14, 1, 30).unwrap(),
+            NaiveDate::from_ymd(2021, 8, 12).and_hms(14, 1, 30)
+        );
+        //Extreme years
+        assert!(matches!(
+            checked_naivedatetime(0, 1, 1, 1, 1, 1),
+            Err(IndexError::InvalidTime(_))
+        ));
+        assert!(matches!(
+            checked_naivedatetime(i32::MAX, 1, 1, 1, 1, 1),
+            Err(IndexError::InvalidTime(_))
+        ));
+        assert!(matches!(
+            checked_naivedatetime(300_000, 1, 1, 1, 1, 1),
+            Err(IndexError::InvalidTime(_))
+        ));
+        //Out of range components
+        assert!(checked_naivedatetime(2021, 13, 1, 1, 1, 1).is_err());
+        assert!(checked_naivedatetime(2021, 2, 30, 1, 1, 1).is_err());
+        assert!(checked_naivedatetime(2021, 8, 12, 24, 1, 1).is_err());
+        assert!(checked_naivedatetime(2021, 8, 12, 14, 60, 1).is_err());
+    }
+
+    #[test]
+    fn test_chunk_memo_reuses_value() {
+        use crate::utils::ChunkMemo;
+        use std::cell::Cell;
+        use std::time::Duration;
+
+        let builds = Cell::new(0);
+        let build = |value: u32| {
+            builds.set(builds.get() + 1);
+            Ok(value)
+        };
+        let mut memo = ChunkMemo::default();
+        let chunk = Duration::from_secs(1_628_776_800);
+
+        //Repeated calls within one chunk only build once
+        for _ in 0..100 {
+            assert_eq!(
+                memo.get_or_insert_with("test_index", chunk, || build(1))
+                    .unwrap(),
+                1
+            );
+        }
+        assert_eq!(builds.get(), 1);
+
+        //Moving onto the next chunk or another index rebuilds
+        let next_chunk = chunk + Duration::from_secs(10);
+        assert_eq!(
+            memo.get_or_insert_with("test_index", next_chunk, || build(2))
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            memo.get_or_insert_with("other_index", next_chunk, || build(3))
+                .unwrap(),
+            3
+        );
+        assert_eq!(builds.get(), 3);
+
+        //Failed builds are not memoized
+        assert!(memo
+            .get_or_insert_with("test_index", chunk, || Err(
+                crate::errors::IndexError::InternalError("test")
+            ))
+            .is_err());
+        assert_eq!(
+            memo.get_or_insert_with("test_index", chunk, || build(4))
+                .unwrap(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_current_chunk_for_clock_before_genesis() {
+        use crate::errors::IndexError;
+        use crate::utils::current_chunk_for;
+        use std::time::Duration;
+
+        let interval = Duration::from_secs(10);
+        let genesis = Duration::from_secs(1_628_776_800);
+
+        //Clock set to before genesis
+        assert!(matches!(
+            current_chunk_for(genesis - Duration::from_secs(1), &interval, &genesis),
+            Err(IndexError::ClockBeforeGenesis)
+        ));
+        assert!(matches!(
+            current_chunk_for(Duration::from_secs(0), &interval, &genesis),
+            Err(IndexError::ClockBeforeGenesis)
+        ));
+
+        //Clock at or after genesis
+        let chunk = current_chunk_for(genesis, &interval, &genesis).unwrap();
+        assert_eq!(chunk.from, genesis);
+        let chunk =
+            current_chunk_for(genesis + Duration::from_secs(15), &interval, &genesis).unwrap();
+        assert_eq!(chunk.from, genesis + interval);
+    }
+
+    #[test]
+    fn test_current_time_path_at_hour_depth() {
+        use crate::entries::{IndexType, TimeIndex};
+        use crate::utils::{
+            deepest_index_type, duration_to_datetime, index_depth_for_interval,
+            index_for_timestamp, naivedatetime_to_components,
+        };
+        use chrono::NaiveDate;
+        use hdk::hash_path::path::Component;
+        use std::time::Duration;
+
+        //Hour interval indexes are hung from the hour level of the tree
+        let interval = Duration::from_secs(60 * 60);
+        let depth = deepest_index_type(&index_depth_for_interval(&interval)).unwrap();
+        assert_eq!(depth, IndexType::Hour);
+
+        //Path for the current chunk is built from the chunk start & stops at the hour level; matching the path indexes are written under
+        let now = NaiveDate::from_ymd(2021, 8, 12).and_hms(14, 37, 12);
+        let current = index_for_timestamp(
+            Duration::from_secs(now.timestamp() as u64),
+            &interval,
+            &Duration::from_secs(0),
+        )
+        .unwrap();
+        let components =
+            naivedatetime_to_components(&duration_to_datetime(&current.from).naive_utc(), &depth)
+                .unwrap();
+        let expected = vec![2021, 8, 12, 14]
+            .into_iter()
+            .map(|value| Component::from(TimeIndex(value).get_sb().unwrap().bytes().to_owned()))
+            .collect::<Vec<Component>>();
+        assert_eq!(components, expected);
+    }
+
+    #[test]
+    fn test_ensure_paths_shares_ancestry() {
+        use crate::utils::ensure_paths;
+        use std::cell::RefCell;
+        use std::collections::HashSet;
+
+        //Only the index root exists
+        let existing: RefCell<HashSet<Vec<u32>>> =
+            RefCell::new(vec![vec![0]].into_iter().collect());
+        let checks: RefCell<Vec<Vec<u32>>> = RefCell::new(vec![]);
+        let created: RefCell<Vec<Vec<u32>>> = RefCell::new(vec![]);
+        //Hour indexes under one day; [root, year, month, day, hour]
+        let paths = (10..15)
+            .map(|hour| vec![0, 2021, 8, 12, hour])
+            .collect::<Vec<Vec<u32>>>();
+        ensure_paths(
+            &paths,
+            |node: &[u32]| {
+                checks.borrow_mut().push(node.to_vec());
+                Ok(existing.borrow().contains(node))
+            },
+            |node: &[u32]| {
+                //Parent must exist before a node is created
+                assert!(existing.borrow().contains(&node[..node.len() - 1]));
+                existing.borrow_mut().insert(node.to_vec());
+                created.borrow_mut().push(node.to_vec());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        //Year, month & day are created once; each hour is created
+        assert_eq!(
+            created.borrow().clone(),
+            vec![
+                vec![0, 2021],
+                vec![0, 2021, 8],
+                vec![0, 2021, 8, 12],
+                vec![0, 2021, 8, 12, 10],
+                vec![0, 2021, 8, 12, 11],
+                vec![0, 2021, 8, 12, 12],
+                vec![0, 2021, 8, 12, 13],
+                vec![0, 2021, 8, 12, 14],
+            ]
+        );
+        //Shared ancestors are only checked when creating the first hour
+        for ancestor in vec![
+            vec![0],
+            vec![0, 2021],
+            vec![0, 2021, 8],
+            vec![0, 2021, 8, 12],
+        ] {
+            assert_eq!(
+                checks
+                    .borrow()
+                    .iter()
+                    .filter(|node| **node == ancestor)
+                    .count(),
+                1
+            );
+        }
+        assert_eq!(checks.borrow().len(), 9);
+
+        //Paths which already exist are not created again
+        created.borrow_mut().clear();
+        ensure_paths(
+            &paths,
+            |node: &[u32]| Ok(existing.borrow().contains(node)),
+            |node: &[u32]| {
+                created.borrow_mut().push(node.to_vec());
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert!(created.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_dedup_links_by_target() {
+        use crate::test_utils::test_link;
+        use crate::utils::dedup_links_by_target;
+        use hdk::prelude::*;
+
+        let link = |target: u8, seq: u8, tag: &str| Link {
+            target: EntryHash::from_raw_36(vec![target; 36]),
+            ..test_link(seq, tag)
+        };
+        //Target 1 is indexed under two tags on one index
+        let links = vec![
+            link(1, 3, "reaction"),
+            link(2, 2, "post"),
+            link(1, 1, "post"),
+        ];
+        let deduped = dedup_links_by_target(links);
+        assert_eq!(deduped, vec![link(1, 1, "post"), link(2, 2, "post")]);
+        assert!(dedup_links_by_target(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_dedup_paths() {
+        use crate::utils::dedup_paths;
+        use hdk::hash_path::path::{Component, Path};
+
+        let path = |minute: u32| {
+            Path::from(vec![
+                Component::from("test_index"),
+                Component::from(minute.to_string()),
+            ])
+        };
+        //Two concurrent ensures of minute 1 each linked it from the parent
+        let children = vec![path(1), path(2), path(1)];
+        assert_eq!(dedup_paths(children), vec![path(1), path(2)]);
+        assert!(dedup_paths(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_count_distinct_targets() {
+        use crate::test_utils::test_link;
+        use crate::utils::{count_distinct_targets, dedup_paths};
+        use hdk::hash_path::path::{Component, Path};
+        use hdk::prelude::*;
+
+        let path = |day: u32| {
+            Path::from(vec![
+                Component::from("test_index"),
+                Component::from(day.to_string()),
+            ])
+        };
+        //Links from a month path to its day paths; day 3 was linked by two concurrent ensures
+        let days = vec![1, 3, 3, 12];
+        let links = days
+            .iter()
+            .enumerate()
+            .map(|(i, day)| Link {
+                target: EntryHash::from_raw_36(vec![*day as u8; 36]),
+                ..test_link(i as u8, day.to_string())
+            })
+            .collect::<Vec<Link>>();
+        assert_eq!(count_distinct_targets(&links), 3);
+        assert_eq!(
+            count_distinct_targets(&links),
+            dedup_paths(days.into_iter().map(path).collect()).len()
+        );
+        assert_eq!(count_distinct_targets(&[]), 0);
+    }
+
+    #[test]
+    fn test_mark_existing() {
+        use crate::entries::Index;
+        use crate::test_utils::test_link;
+        use crate::utils::mark_existing;
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        let chunk = |n: u64| Index {
+            from: Duration::from_secs(n * 10),
+            until: Duration::from_secs(n * 10 + 10),
+        };
+        let hash = |n: u64| EntryHash::from_raw_36(vec![n as u8; 36]);
+        let link = |n: u64| test_link(n as u8, "time_path");
+        //Six chunks hung from two parent time paths; every other chunk exists
+        let chunks = (0..6)
+            .map(|n| (chunk(n), n / 3, hash(n)))
+            .collect::<Vec<(Index, u64, EntryHash)>>();
+        let mut fetched = vec![];
+        let mask = mark_existing(chunks, |parents| {
+            fetched = parents.clone();
+            Ok(parents
+                .into_iter()
+                .map(|parent| {
+                    (parent * 3..parent * 3 + 3)
+                        .filter(|n| n % 2 == 0)
+                        .map(link)
+                        .collect()
+                })
+                .collect())
+        })
+        .unwrap();
+        //Links of each parent are fetched once
+        assert_eq!(fetched, vec![0, 1]);
+        assert_eq!(
+            mask,
+            (0..6)
+                .map(|n| (chunk(n), n % 2 == 0))
+                .collect::<Vec<(Index, bool)>>()
+        );
+
+        //A path linked under a different parent does not count as existing
+        let mask = mark_existing(vec![(chunk(0), 0, hash(0))], |_| Ok(vec![vec![]])).unwrap();
+        assert_eq!(mask, vec![(chunk(0), false)]);
+        //Missing parent links are an error rather than reported as absent chunks
+        assert!(mark_existing(vec![(chunk(0), 0, hash(0))], |_| Ok(vec![])).is_err());
+    }
+
+    #[test]
+    fn test_chunks_desc() {
+        use crate::entries::Index;
+        use crate::errors::IndexError;
+        use crate::utils::ChunksDesc;
+        use std::cell::Cell;
+        use std::collections::HashSet;
+        use std::time::Duration;
+
+        let genesis = Duration::from_secs(1_628_776_800);
+        let interval = Duration::from_secs(10);
+        let at = |secs: u64| genesis + Duration::from_secs(secs);
+        //Chunks starting at these offsets exist
+        let existing = vec![0, 20, 30, 70]
+            .into_iter()
+            .map(at)
+            .collect::<HashSet<Duration>>();
+        let gets = Cell::new(0);
+        let load = |chunk: Index| {
+            gets.set(gets.get() + 1);
+            Ok(if existing.contains(&chunk.from) {
+                Some(chunk)
+            } else {
+                None
+            })
+        };
+
+        //Nothing is loaded until iterator is advanced
+        let mut chunks = ChunksDesc::new(at(5), at(75), &interval, &genesis, load).unwrap();
+        assert_eq!(gets.get(), 0);
+        let newest = chunks.next().unwrap().unwrap();
+        assert_eq!(newest.from, at(70));
+        assert_eq!(gets.get(), 1);
+        //Empty chunks 60, 50 & 40 are stepped over to reach 30
+        assert_eq!(chunks.next().unwrap().unwrap().from, at(30));
+        assert_eq!(gets.get(), 5);
+        //Iteration stops at from; chunk 0 overlaps from so is included
+        let rest = chunks.map(|chunk| chunk.unwrap().from).collect::<Vec<_>>();
+        assert_eq!(rest, vec![at(20), at(0)]);
+        assert_eq!(gets.get(), 8);
+
+        //Iteration stops at genesis when from is before it; until is exclusive
+        let all = ChunksDesc::new(
+            Duration::from_secs(0),
+            at(70),
+            &interval,
+            &genesis,
+            |chunk: Index| Ok(Some(chunk.from)),
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        assert_eq!(all, (0..7).rev().map(|i| at(i * 10)).collect::<Vec<_>>());
+
+        //Span before genesis is empty
+        let mut before = ChunksDesc::new(
+            Duration::from_secs(0),
+            genesis,
+            &interval,
+            &genesis,
+            |chunk: Index| Ok(Some(chunk)),
+        )
+        .unwrap();
+        assert!(before.next().is_none());
+
+        //Errors end iteration
+        let mut failing = ChunksDesc::new(at(0), at(30), &interval, &genesis, |_chunk: Index| {
+            Err::<Option<Index>, _>(IndexError::InternalError("Could not load"))
+        })
+        .unwrap();
+        assert!(failing.next().unwrap().is_err());
+        assert!(failing.next().is_none());
+    }
+
+    #[test]
+    fn test_filter_links_in_window() {
+        use crate::test_utils::test_link;
+        use crate::utils::filter_links_in_window;
+        use chrono::{Duration, TimeZone, Utc};
+        use hdk::prelude::*;
+        use std::cell::Cell;
+        use std::collections::HashMap;
+
+        //Hour long chunk with one entry every five minutes; link n targets entry made at minute n * 5
+        let start = Utc.timestamp(1_628_776_800, 0);
+        let link = |n: u8| Link {
+            timestamp: Timestamp::from_micros((1_628_776_800 + n as i64 * 300) * 1_000_000),
+            ..test_link(n, "test")
+        };
+        let links = (0..12).rev().map(link).collect::<Vec<Link>>();
+        let times = (0..12)
+            .map(|n| (link(n).target, start + Duration::minutes(n as i64 * 5)))
+            .collect::<HashMap<EntryHash, _>>();
+        let fetches = Cell::new(0);
+        let entry_time = |link: &Link| {
+            fetches.set(fetches.get() + 1);
+            Ok(times.get(&link.target).cloned())
+        };
+
+        //Middle third of chunk
+        let from = start + Duration::minutes(20);
+        let until = start + Duration::minutes(40);
+        let middle =
+            filter_links_in_window(links.clone(), &from, &until, None, entry_time).unwrap();
+        assert_eq!(middle, (4..8).map(link).collect::<Vec<Link>>());
+        assert_eq!(fetches.get(), 12);
+
+        //Fetching stops once limit is reached
+        fetches.set(0);
+        let limited =
+            filter_links_in_window(links.clone(), &from, &until, Some(2), entry_time).unwrap();
+        assert_eq!(limited, vec![link(4), link(5)]);
+        assert_eq!(fetches.get(), 6);
+
+        //Links without an entry time are dropped
+        let unresolved =
+            filter_links_in_window(links, &from, &until, None, |_link: &Link| Ok(None)).unwrap();
+        assert!(unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_has_link() {
+        use crate::test_utils::test_link;
+        use crate::utils::has_link;
+        use hdk::prelude::*;
+
+        let link = test_link::<&str>;
+        let target = EntryHash::from_raw_36(vec![1; 36]);
+        //get_links for tag "post" also returns links tagged "posted"
+        let links = vec![link(1, "posted"), link(2, "post")];
+        assert!(!has_link(&links, &target, &LinkTag::new("post")));
+        assert!(has_link(&links, &target, &LinkTag::new("posted")));
+
+        let links = vec![link(1, "post")];
+        assert!(has_link(&links, &target, &LinkTag::new("post")));
+        assert!(!has_link(&[], &target, &LinkTag::new("post")));
+    }
+
+    #[test]
+    fn test_load_lazily() {
+        use crate::errors::IndexResult;
+        use crate::utils::load_lazily;
+        use hdk::prelude::*;
+        use std::cell::Cell;
+
+        let targets = (1..=6)
+            .map(|i| EntryHash::from_raw_36(vec![i; 36]))
+            .collect::<Vec<EntryHash>>();
+        let gets = Cell::new(0);
+        //Target 3 does not resolve
+        let load = |target: EntryHash| -> IndexResult<Option<u8>> {
+            gets.set(gets.get() + 1);
+            let value = target.get_raw_36()[0];
+            Ok(if value == 3 { None } else { Some(value) })
+        };
+
+        let mut entries = load_lazily(targets.clone(), load);
+        assert_eq!(gets.get(), 0);
+        assert_eq!(entries.next().unwrap().unwrap(), 1);
+        assert_eq!(gets.get(), 1);
+        //Unresolved target is skipped over
+        assert_eq!(entries.next().unwrap().unwrap(), 2);
+        assert_eq!(entries.next().unwrap().unwrap(), 4);
+        assert_eq!(gets.get(), 4);
+
+        //Every resolvable target is returned
+        gets.set(0);
+        let all = load_lazily(targets, load)
+            .collect::<IndexResult<Vec<u8>>>()
+            .unwrap();
+        assert_eq!(all, vec![1, 2, 4, 5, 6]);
+        assert_eq!(gets.get(), 6);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        use crate::errors::IndexError;
+        use crate::utils::{from_unix_millis, from_unix_secs, parse_time};
+        use chrono::{TimeZone, Utc};
+
+        let time = Utc.timestamp(1_628_776_890, 0);
+        //RFC3339
+        assert_eq!(parse_time("2021-08-12T14:01:30Z").unwrap(), time);
+        assert_eq!(parse_time(" 2021-08-12T16:01:30+02:00 ").unwrap(), time);
+        assert_eq!(
+            parse_time("2021-08-12T14:01:30.250Z").unwrap(),
+            Utc.timestamp(1_628_776_890, 250_000_000)
+        );
+        //UNIX seconds & milliseconds
+        assert_eq!(parse_time("1628776890s").unwrap(), time);
+        assert_eq!(parse_time("1628776890000ms").unwrap(), time);
+        assert_eq!(
+            parse_time("1628776890250ms").unwrap(),
+            Utc.timestamp(1_628_776_890, 250_000_000)
+        );
+        assert_eq!(from_unix_secs(1_628_776_890).unwrap(), time);
+        assert_eq!(from_unix_millis(1_628_776_890_000).unwrap(), time);
+
+        //Ambiguous, malformed & pre epoch inputs
+        for input in vec![
+            "1628776890",
+            "",
+            "s",
+            "ms",
+            "-5s",
+            "1.5s",
+            "12h",
+            "2021-08-12",
+            "2021-08-12 14:01:30",
+            "1969-12-31T23:59:59Z",
+            "99999999999999999999s",
+        ] {
+            assert!(
+                matches!(parse_time(input), Err(IndexError::InvalidTime(_))),
+                "{} should not parse",
+                input
+            );
+        }
+        assert!(from_unix_millis(-1).is_err());
+        assert!(from_unix_secs(i64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_index_shards() {
+        use crate::entries::Index;
+        use crate::test_utils::test_link;
+        use crate::utils::{merge_shard_results, shard_for, shard_index_names};
+        use crate::EntryChunkIndex;
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        assert_eq!(shard_index_names("test", 1), vec![String::from("test")]);
+        assert_eq!(
+            shard_index_names("test", 3),
+            vec![
+                String::from("test"),
+                String::from("test#shard1"),
+                String::from("test#shard2")
+            ]
+        );
+
+        //Entries written by many agents are spread across every shard & always land in the same shard
+        let hashes = (0..64u8)
+            .map(|i| {
+                let mut raw = vec![i; 32];
+                raw.extend(&(i as u32 * 2_654_435_761u32).to_le_bytes());
+                EntryHash::from_raw_36(raw)
+            })
+            .collect::<Vec<EntryHash>>();
+        let mut counts = vec![0; 4];
+        for hash in &hashes {
+            assert_eq!(shard_for(hash, 4), shard_for(hash, 4));
+            assert_eq!(shard_for(hash, 1), 0);
+            counts[shard_for(hash, 4)] += 1;
+        }
+        assert!(counts.iter().all(|count| *count > 0));
+        assert_eq!(counts.iter().sum::<usize>(), 64);
+
+        //Reads from all shards find every chunk & links of a chunk found in several shards are combined
+        let chunk = |from: u64, links: Vec<u8>| EntryChunkIndex {
+            index: Index {
+                from: Duration::from_secs(from),
+                until: Duration::from_secs(from + 10),
+            },
+            links: links.into_iter().map(|i| test_link(i, "test")).collect(),
+            version: crate::ENTRY_CHUNK_INDEX_VERSION,
+        };
+        let merged = merge_shard_results(vec![
+            vec![chunk(0, vec![1, 2]), chunk(10, vec![3])],
+            vec![chunk(10, vec![4])],
+            vec![chunk(20, vec![5])],
+        ])
+        .unwrap();
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[1].index, chunk(10, vec![]).index);
+        assert_eq!(merged[1].links.len(), 2);
+        assert_eq!(
+            merged.iter().map(|chunk| chunk.links.len()).sum::<usize>(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_unsharded_index_name() {
+        use crate::utils::{shard_index_name, unsharded_index_name};
+
+        for shard in 0..4 {
+            assert_eq!(
+                unsharded_index_name(&shard_index_name("posts", shard)),
+                "posts"
+            );
+        }
+        assert_eq!(unsharded_index_name("posts#shard"), "posts#shard");
+        assert_eq!(unsharded_index_name("posts#shardx"), "posts#shardx");
+        assert_eq!(unsharded_index_name("a#shard1#shard2"), "a#shard1");
+    }
+
+    #[test]
+    fn test_read_shards() {
+        use crate::entries::Index;
+        use crate::utils::{group_by_chunk, read_shards};
+        use std::time::Duration;
+
+        //Every shard is read once, in order
+        let mut read = vec![];
+        let found = read_shards("test", 3, |shard| {
+            read.push(shard.clone());
+            Ok(vec![format!("{}/a", shard), format!("{}/b", shard)])
+        })
+        .unwrap();
+        assert_eq!(read, vec!["test", "test#shard1", "test#shard2"]);
+        assert_eq!(found.len(), 6);
+        assert_eq!(found[2], "test#shard1/a");
+        assert!(read_shards::<u8, _>("test", 2, |shard| {
+            if shard == "test#shard1" {
+                Err(crate::errors::IndexError::InternalError("unreachable"))
+            } else {
+                Ok(vec![1])
+            }
+        })
+        .is_err());
+
+        //Chunks found in several shards are grouped oldest first
+        let chunk = |from: u64| Index {
+            from: Duration::from_secs(from),
+            until: Duration::from_secs(from + 10),
+        };
+        assert_eq!(
+            group_by_chunk(vec![
+                (chunk(20), "test/20"),
+                (chunk(0), "test/0"),
+                (chunk(20), "test#shard1/20"),
+                (chunk(10), "test#shard1/10"),
+            ]),
+            vec![
+                (chunk(0), vec!["test/0"]),
+                (chunk(10), vec!["test#shard1/10"]),
+                (chunk(20), vec!["test/20", "test#shard1/20"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exclude_authors() {
+        use crate::test_utils::test_link;
+        use crate::utils::exclude_authors;
+        use hdk::prelude::*;
+
+        let alice = AgentPubKey::from_raw_36(vec![1; 36]);
+        let bob = AgentPubKey::from_raw_36(vec![2; 36]);
+        let carol = AgentPubKey::from_raw_36(vec![3; 36]);
+        let link = |i: u8| test_link(i, "test");
+        let links = vec![
+            (bob.clone(), link(4)),
+            (alice.clone(), link(1)),
+            (carol.clone(), link(3)),
+            (bob.clone(), link(2)),
+            (alice.clone(), link(5)),
+            (carol.clone(), link(6)),
+        ];
+
+        assert_eq!(
+            exclude_authors(links.clone(), &[bob.clone()], None),
+            vec![link(1), link(3), link(5), link(6)]
+        );
+        assert_eq!(
+            exclude_authors(links.clone(), &[bob.clone(), carol.clone()], None),
+            vec![link(1), link(5)]
+        );
+        //Blocked links do not count towards limit
+        assert_eq!(
+            exclude_authors(links.clone(), &[bob.clone()], Some(2)),
+            vec![link(1), link(3)]
+        );
+        assert_eq!(exclude_authors(links.clone(), &[], None).len(), 6);
+        assert!(exclude_authors(links, &[alice, bob, carol], None).is_empty());
+    }
+
+    #[test]
+    fn test_distinct_authors() {
+        use crate::test_utils::test_link;
+        use crate::utils::distinct_authors;
+        use hdk::prelude::*;
+
+        let alice = AgentPubKey::from_raw_36(vec![1; 36]);
+        let bob = AgentPubKey::from_raw_36(vec![2; 36]);
+        let carol = AgentPubKey::from_raw_36(vec![3; 36]);
+        let link = |i: u8| test_link(i, "test");
+        let links = vec![
+            (carol.clone(), link(4)),
+            (bob.clone(), link(2)),
+            (alice.clone(), link(3)),
+            (bob.clone(), link(1)),
+            (carol.clone(), link(6)),
+            (alice.clone(), link(5)),
+        ];
+
+        assert_eq!(
+            distinct_authors(links),
+            vec![bob.clone(), alice.clone(), carol.clone()]
+        );
+        assert_eq!(
+            distinct_authors(vec![(alice.clone(), link(1))]),
+            vec![alice]
+        );
+        assert!(distinct_authors(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_bucket_by_time() {
+        use crate::utils::{bucket_bounds, bucket_by_time, index_for_timestamp};
+        use chrono::{TimeZone, Utc};
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        let genesis = Duration::from_secs(1_628_776_800);
+        let at = |secs: i64| Utc.timestamp(genesis.as_secs() as i64 + secs, 0);
+        let target = |i: u8| EntryHash::from_raw_36(vec![i; 36]);
+
+        //Three 20 second buckets across 60 seconds of 15 second chunks; chunk & bucket boundaries do not line up
+        let buckets = bucket_bounds(&at(0), &at(60), 3).unwrap();
+        assert_eq!(
+            buckets,
+            vec![(at(0), at(20)), (at(20), at(40)), (at(40), at(60))]
+        );
+        let interval = Duration::from_secs(15);
+        let chunk_of = |secs: i64| {
+            index_for_timestamp(
+                genesis + Duration::from_secs(secs as u64),
+                &interval,
+                &genesis,
+            )
+            .unwrap()
+        };
+        //Entries 2 & 3 share a chunk but land in different buckets
+        assert_eq!(chunk_of(17), chunk_of(22));
+        let items = vec![
+            (at(22), target(3)),
+            (at(5), target(1)),
+            (at(59), target(5)),
+            (at(17), target(2)),
+            (at(40), target(4)),
+            //Outside of span
+            (at(60), target(6)),
+            (at(-1), target(7)),
+        ];
+        assert_eq!(
+            bucket_by_time(items, buckets),
+            vec![
+                (at(0), at(20), vec![target(1), target(2)]),
+                (at(20), at(40), vec![target(3)]),
+                (at(40), at(60), vec![target(4), target(5)]),
+            ]
+        );
+
+        //Uneven division; last bucket still ends at until & buckets do not overlap
+        let buckets = bucket_bounds(&at(0), &at(10), 3).unwrap();
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[2].1, at(10));
+        assert!(buckets.windows(2).all(|pair| pair[0].1 == pair[1].0));
+        //Empty buckets are kept
+        assert_eq!(
+            bucket_by_time(vec![], bucket_bounds(&at(0), &at(10), 2).unwrap())
+                .into_iter()
+                .map(|(_, _, targets)| targets.len())
+                .collect::<Vec<usize>>(),
+            vec![0, 0]
+        );
+
+        assert!(bucket_bounds(&at(0), &at(10), 0).is_err());
+        assert!(bucket_bounds(&at(10), &at(10), 2).is_err());
+        assert!(bucket_bounds(&at(10), &at(0), 2).is_err());
+    }
+
+    #[test]
+    fn test_at_link_limit() {
+        use crate::test_utils::test_link;
+        use crate::utils::at_link_limit;
+        use hdk::prelude::*;
+
+        let alice = AgentPubKey::from_raw_36(vec![1; 36]);
+        let bob = AgentPubKey::from_raw_36(vec![2; 36]);
+        let link = test_link::<&str>;
+        let links = vec![
+            (alice.clone(), link(1, "post")),
+            (bob.clone(), link(2, "post")),
+            (alice.clone(), link(3, "reaction")),
+            (bob.clone(), link(4, "post")),
+            (alice.clone(), link(5, "post")),
+        ];
+
+        //Alice has made 3 links on the index
+        assert!(!at_link_limit(&links, &alice, None, 4));
+        assert!(at_link_limit(&links, &alice, None, 3));
+        //Links of other agents do not count towards alice's limit
+        assert!(!at_link_limit(&links, &bob, None, 3));
+        //Counted per tag
+        assert!(at_link_limit(
+            &links,
+            &alice,
+            Some(&LinkTag::new("post")),
+            2
+        ));
+        assert!(!at_link_limit(
+            &links,
+            &alice,
+            Some(&LinkTag::new("reaction")),
+            2
+        ));
+        assert!(!at_link_limit(&[], &alice, None, 1));
+    }
+
+    #[test]
+    fn test_in_batches() {
+        use crate::utils::in_batches;
+
+        let mut batches = vec![];
+        let results = in_batches((0..10).collect::<Vec<u32>>(), 4, |batch| {
+            batches.push(batch.clone());
+            Ok(batch.into_iter().map(|item| item * 2).collect())
+        })
+        .unwrap();
+        //Three batches with results in the order of items
+        assert_eq!(
+            batches,
+            vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]
+        );
+        assert_eq!(results, (0..10).map(|item| item * 2).collect::<Vec<u32>>());
+
+        let mut calls = 0;
+        let results = in_batches(vec![1, 2, 3], 0, |batch| {
+            calls += 1;
+            Ok(batch)
+        })
+        .unwrap();
+        assert_eq!((calls, results), (3, vec![1, 2, 3]));
+
+        let mut calls = 0;
+        let results = in_batches(Vec::<u32>::new(), 4, |batch| {
+            calls += 1;
+            Ok(batch)
+        })
+        .unwrap();
+        assert_eq!((calls, results.len()), (0, 0));
+
+        //A batch returning the wrong number of results is an error rather than misaligned results
+        assert!(in_batches(vec![1, 2, 3], 2, |_batch| Ok(vec![0])).is_err());
+    }
+
+    #[test]
+    fn test_links_to_move() {
+        use crate::test_utils::test_link;
+        use crate::utils::links_to_move;
+        use hdk::prelude::*;
+
+        let entry = EntryHash::from_raw_36(vec![1; 36]);
+        let other = EntryHash::from_raw_36(vec![2; 36]);
+        let link = |i: u8, target: &EntryHash, tag: &str| Link {
+            target: target.clone(),
+            ..test_link(i, tag)
+        };
+        let links = vec![
+            link(1, &entry, "event"),
+            link(2, &other, "event"),
+            link(3, &entry, "event_reaction"),
+        ];
+
+        //Only links to entry with exactly the same tag are moved; prefix matches are not
+        let (moved, other_tags_remain) =
+            links_to_move(links.clone(), &entry, &LinkTag::new("event"));
+        assert_eq!(moved, vec![link(1, &entry, "event")]);
+        assert!(other_tags_remain);
+
+        let (moved, other_tags_remain) =
+            links_to_move(links[..2].to_vec(), &entry, &LinkTag::new("event"));
+        assert_eq!(moved.len(), 1);
+        assert!(!other_tags_remain);
+
+        let (moved, _) = links_to_move(links, &entry, &LinkTag::new("missing"));
+        assert!(moved.is_empty());
+    }
+
+    #[test]
+    fn test_build_time_path() {
+        use crate::entries::{Index, IndexType, StringIndex};
+        use crate::utils::{
+            build_time_path, duration_to_datetime, index_for_timestamp, naivedatetime_to_components,
+        };
+        use hdk::hash_path::path::Component;
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        //Path as built by pushing one component at a time
+        let reference = |from: &Duration, depth: &IndexType, chunk: Option<&Index>| {
+            let mut path = vec![Component::from(
+                StringIndex(String::from("test_index"))
+                    .get_sb()
+                    .unwrap()
+                    .bytes()
+                    .to_owned(),
+            )];
+            path.append(
+                &mut naivedatetime_to_components(&duration_to_datetime(from).naive_utc(), depth)
+                    .unwrap(),
+            );
+            if let Some(chunk) = chunk {
+                path.push(Component::from(
+                    SerializedBytes::try_from(chunk).unwrap().bytes().to_owned(),
+                ));
+            };
+            path
+        };
+
+        let interval = Duration::from_secs(10);
+        let genesis = Duration::from_secs(0);
+        for time in [0, 1_628_776_883, 1_640_995_199, 1_709_164_800] {
+            let chunk =
+                index_for_timestamp(Duration::from_secs(time), &interval, &genesis).unwrap();
+            for depth in [
+                IndexType::Day,
+                IndexType::Hour,
+                IndexType::Minute,
+                IndexType::Second,
+            ] {
+                let time_path =
+                    build_time_path(String::from("test_index"), &chunk.from, &depth, None).unwrap();
+                assert_eq!(time_path, reference(&chunk.from, &depth, None));
+                assert_eq!(time_path.capacity(), time_path.len());
+
+                let chunk_path = build_time_path(
+                    String::from("test_index"),
+                    &chunk.from,
+                    &depth,
+                    Some(&chunk),
+                )
+                .unwrap();
+                assert_eq!(chunk_path, reference(&chunk.from, &depth, Some(&chunk)));
+                assert_eq!(chunk_path.capacity(), chunk_path.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_page_by_size() {
+        use crate::entries::Index;
+        use crate::test_utils::test_link;
+        use crate::utils::page_by_size;
+        use crate::{EntryChunkIndex, PageCursor, ENTRY_CHUNK_INDEX_VERSION};
+        use chrono::{TimeZone, Utc};
+        use hdk::prelude::*;
+        use std::cell::RefCell;
+        use std::time::Duration;
+
+        let link = |i: u8| test_link(i, "test");
+        let chunk = |from: u64, links: Vec<u8>| EntryChunkIndex {
+            index: Index {
+                from: Duration::from_secs(from),
+                until: Duration::from_secs(from + 10),
+            },
+            links: links.into_iter().map(link).collect(),
+            version: ENTRY_CHUNK_INDEX_VERSION,
+        };
+        let chunks = vec![
+            chunk(1_628_776_890, vec![6, 7, 8, 9]),
+            chunk(1_628_776_880, vec![1, 2, 3, 4, 5]),
+        ]
+        .into_iter()
+        .map(|chunk| (chunk.index, chunk.links))
+        .collect::<Vec<(Index, Vec<Link>)>>();
+        let fetched = RefCell::new(vec![]);
+        let fetch = |links: Vec<Link>| {
+            fetched.borrow_mut().push(links[0].clone());
+            Ok(links)
+        };
+        let start = PageCursor {
+            from: Utc.timestamp(1_628_776_880, 0),
+            seen: vec![],
+        };
+        let size =
+            |chunk: &EntryChunkIndex| SerializedBytes::try_from(chunk).unwrap().bytes().len();
+        let header_size = size(&chunk(1_628_776_880, vec![]));
+        let link_size = size(&chunk(1_628_776_880, vec![1])) - header_size;
+
+        //Everything fits
+        let (page, cursor) = page_by_size(chunks.clone(), &start, 1024 * 1024, fetch).unwrap();
+        assert_eq!(page.len(), 2);
+        assert!(cursor.is_none());
+
+        //Window exceeding budget returns a first page & a cursor; following cursors returns every link exactly once
+        let max_bytes = header_size + link_size * 3;
+        let mut cursor = start;
+        let mut delivered = vec![];
+        let mut pages = 0;
+        loop {
+            let (page, next) = page_by_size(chunks.clone(), &cursor, max_bytes, fetch).unwrap();
+            let page_size = page
+                .iter()
+                .map(|chunk| header_size + link_size * chunk.links.len())
+                .sum::<usize>();
+            assert!(page_size <= max_bytes);
+            delivered.extend(page.into_iter().flat_map(|chunk| chunk.links));
+            pages += 1;
+            match next {
+                Some(next) => cursor = next,
+                None => break,
+            };
+        }
+        assert!(pages > 1);
+        assert_eq!(delivered, (1..10).map(link).collect::<Vec<Link>>());
+
+        //Chunks after a full page & chunks before the cursor are never fetched
+        fetched.borrow_mut().clear();
+        page_by_size(chunks.clone(), &start, max_bytes, fetch).unwrap();
+        assert_eq!(*fetched.borrow(), vec![link(1)]);
+        fetched.borrow_mut().clear();
+        let later = PageCursor {
+            from: Utc.timestamp(1_628_776_890, 0),
+            seen: vec![],
+        };
+        page_by_size(chunks.clone(), &later, max_bytes, fetch).unwrap();
+        assert_eq!(*fetched.borrow(), vec![link(6)]);
+
+        //Cursor not aligned to a chunk still returns every link of the chunk it falls in
+        let unaligned = PageCursor {
+            from: Utc.timestamp(1_628_776_885, 0),
+            seen: vec![],
+        };
+        let (page, cursor) = page_by_size(chunks.clone(), &unaligned, 1024 * 1024, fetch).unwrap();
+        assert!(cursor.is_none());
+        assert_eq!(
+            page.into_iter()
+                .flat_map(|chunk| chunk.links)
+                .collect::<Vec<Link>>(),
+            (1..10).map(link).collect::<Vec<Link>>()
+        );
+        let (page, cursor) = page_by_size(chunks.clone(), &unaligned, max_bytes, fetch).unwrap();
+        assert_eq!(page[0].links, vec![link(1), link(2), link(3)]);
+        let (page, _) = page_by_size(chunks.clone(), &cursor.unwrap(), max_bytes, fetch).unwrap();
+        assert_eq!(page[0].links[0], link(4));
+
+        //Budget too small for any link
+        assert!(page_by_size(chunks, &cursor, header_size, fetch).is_err());
+    }
+
+    #[test]
+    fn test_resolve_with_fallback() {
+        use crate::errors::IndexError;
+        use crate::utils::resolve_with_fallback;
+        use std::cell::RefCell;
+
+        let calls = RefCell::new(vec![]);
+        let source = |name: &'static str, value: Option<u32>| {
+            let calls = &calls;
+            move || {
+                calls.borrow_mut().push(name);
+                Ok(value)
+            }
+        };
+
+        //Local hit; network is never asked
+        let found = resolve_with_fallback(source("local", Some(1)), source("network", Some(2)));
+        assert_eq!(found.unwrap(), Some(1));
+        assert_eq!(*calls.borrow(), vec!["local"]);
+
+        //Local miss falls back to network
+        calls.borrow_mut().clear();
+        let found = resolve_with_fallback(source("local", None), source("network", Some(2)));
+        assert_eq!(found.unwrap(), Some(2));
+        assert_eq!(*calls.borrow(), vec!["local", "network"]);
+
+        //Missing on both
+        calls.borrow_mut().clear();
+        let found = resolve_with_fallback(source("local", None), source("network", None));
+        assert_eq!(found.unwrap(), None);
+        assert_eq!(*calls.borrow(), vec!["local", "network"]);
+
+        //Local errors are returned without asking network
+        calls.borrow_mut().clear();
+        let found = resolve_with_fallback(
+            || Err(IndexError::InternalError("local failed")),
+            source("network", Some(2)),
+        );
+        assert!(found.is_err());
+        assert!(calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_format_relative() {
+        use crate::utils::format_relative;
+        use chrono::{TimeZone, Utc};
+        use std::time::Duration;
+
+        let interval = Duration::from_secs(10);
+        let genesis = Duration::from_secs(1_628_776_800);
+
+        //Genesis instant
+        assert_eq!(
+            format_relative(Utc.timestamp(1_628_776_800, 0), &interval, &genesis).unwrap(),
+            "chunk 0, +0ms into chunk, +0ms from genesis"
+        );
+        //Mid chunk
+        assert_eq!(
+            format_relative(
+                Utc.timestamp(1_628_776_830, 250_000_000),
+                &interval,
+                &genesis
+            )
+            .unwrap(),
+            "chunk 3, +250ms into chunk, +30250ms from genesis"
+        );
+        //Chunk boundary belongs to the chunk it starts
+        assert_eq!(
+            format_relative(Utc.timestamp(1_628_776_840, 0), &interval, &genesis).unwrap(),
+            "chunk 4, +0ms into chunk, +40000ms from genesis"
+        );
+        assert!(format_relative(Utc.timestamp(1_628_776_799, 0), &interval, &genesis).is_err());
+    }
+
+    #[test]
+    fn test_timestamp_to_duration() {
+        use crate::utils::timestamp_to_duration;
+        use hdk::prelude::Timestamp;
+        use std::time::Duration;
+
+        assert_eq!(
+            timestamp_to_duration(&Timestamp::from_micros(1_628_776_880_500_000)).unwrap(),
+            Duration::from_millis(1_628_776_880_500)
+        );
+        assert_eq!(
+            timestamp_to_duration(&Timestamp::from_micros(0)).unwrap(),
+            Duration::from_secs(0)
+        );
+        assert!(timestamp_to_duration(&Timestamp::from_micros(-1)).is_err());
+    }
+
+    #[test]
+    fn test_datetime_to_duration() {
+        use crate::errors::IndexError;
+        use crate::utils::datetime_to_duration;
+        use chrono::{TimeZone, Utc};
+        use std::time::Duration;
+
+        assert_eq!(
+            datetime_to_duration(&Utc.timestamp(1_628_776_880, 500_000_000)).unwrap(),
+            Duration::from_millis(1_628_776_880_500)
+        );
+        assert_eq!(
+            datetime_to_duration(&Utc.timestamp(0, 0)).unwrap(),
+            Duration::from_secs(0)
+        );
+        //Would wrap to a time far in the future if cast to u64
+        assert!(matches!(
+            datetime_to_duration(&Utc.timestamp(-1, 0)),
+            Err(IndexError::InvalidTime(_))
+        ));
+    }
 }
@@ -1,51 +1,960 @@
-use hdk::prelude::*;
+use std::time::Duration;
 
-use crate::entries::Index;
+use hdk::{hash_path::path::Component, prelude::*};
+
+use crate::entries::{Index, IndexType, StringIndex, WrappedPath};
 use crate::errors::{IndexError, IndexResult};
-use crate::MAX_CHUNK_INTERVAL;
+use crate::utils::{
+    deepest_index_type, duration_to_datetime, naivedatetime_to_components, now_since_epoch,
+    shard_index_names, timestamp_to_duration, unsharded_index_name,
+};
+use crate::{
+    ENFORCE_SPAM_LIMIT, GENESIS, INDEX_DEPTH, INDEX_SHARDS, MAX_BACKDATE, MAX_CHUNK_INTERVAL,
+    SPAM_LIMIT_PER_TAG, TIME_PATH_LINK_TAG,
+};
+
+/// Amount of time an index is allowed to sit in the future relative to the validating agents clock.
+/// Allows for small amounts of clock drift between agents
+pub(crate) const FUTURE_TIME_TOLERANCE: Duration = Duration::from_secs(60);
+
+/// Kinds of link made by this crate
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum IndexLinkKind {
+    /// Link from an index to an indexed entry
+    Index,
+    /// Link from an indexed entry back to the index it was indexed under
+    TimePath,
+    /// Link from the deepest time path to an index hanging from it, made by Path::ensure()
+    Chunk,
+    /// Link between other time tree paths made by Path::ensure(), or a link not made by this crate
+    Other,
+}
+
+/// Determine the kind of a link from its base, target & tag
+pub(crate) fn classify_link(base: &Entry, target: &Entry, tag: &LinkTag) -> IndexLinkKind {
+    if get_index_from_entry(base).is_some() {
+        IndexLinkKind::Index
+    } else if tag.0.starts_with(TIME_PATH_LINK_TAG.as_bytes()) {
+        IndexLinkKind::TimePath
+    } else if get_path_from_entry(base).is_some() && get_index_from_entry(target).is_some() {
+        IndexLinkKind::Chunk
+    } else {
+        IndexLinkKind::Other
+    }
+}
+
+/// Validate any link made by this crate; intended to be the only call a host DNA's validate_create_link callback needs to make.
+/// Links from an index are checked by [`validate_index_link_timing()`], [`validate_index_link_age()`], [`validate_index_link_reachable()`] & [`validate_index_link_limit()`]
+/// in turn. Links back to the time tree from indexed entries must point onto an index & links onto an index are checked by
+/// [`validate_chunk_link()`]. Other links are considered valid
+pub fn validate_create_link(data: &ValidateCreateLinkData) -> ExternResult<ValidateCallbackResult> {
+    match classify_link(&data.base, &data.target, &data.link_add.tag) {
+        IndexLinkKind::Index => {
+            let checks: [fn(&ValidateCreateLinkData) -> ExternResult<ValidateCallbackResult>; 4] = [
+                validate_index_link_timing,
+                validate_index_link_age,
+                validate_index_link_reachable,
+                validate_index_link_limit,
+            ];
+            for validate in checks.iter() {
+                match validate(data)? {
+                    ValidateCallbackResult::Valid => (),
+                    invalid => return Ok(invalid),
+                };
+            }
+            Ok(ValidateCallbackResult::Valid)
+        }
+        IndexLinkKind::TimePath => Ok(validate_time_path_link(&data.target)),
+        IndexLinkKind::Chunk => validate_chunk_link(data),
+        IndexLinkKind::Other => Ok(ValidateCallbackResult::Valid),
+    }
+}
+
+/// Check that a link back to the time tree points onto an index
+pub(crate) fn validate_time_path_link(target: &Entry) -> ValidateCallbackResult {
+    match get_index_from_entry(target) {
+        Some(_) => ValidateCallbackResult::Valid,
+        None => {
+            ValidateCallbackResult::Invalid(String::from("Time path link must point to an index"))
+        }
+    }
+}
+
+/// Validate that a link onto an index is made from the time path containing the start of the index; so that the index cannot be
+/// made discoverable under the wrong time window. Links which do not point onto an index are not checked and are considered valid
+pub fn validate_chunk_link(data: &ValidateCreateLinkData) -> ExternResult<ValidateCallbackResult> {
+    let (base, target, chunk) = match (
+        get_path_from_entry(&data.base),
+        get_path_from_entry(&data.target),
+        get_index_from_entry(&data.target),
+    ) {
+        (Some(base), Some(target), Some(chunk)) => (base, target, chunk),
+        _ => return Ok(ValidateCallbackResult::Valid),
+    };
+    let depth = deepest_index_type(&INDEX_DEPTH).ok_or(WasmError::Host(String::from(
+        "Index depth must contain at least one level",
+    )))?;
+    match validate_chunk_path(&base, &target, &chunk, &depth) {
+        Ok(()) => Ok(ValidateCallbackResult::Valid),
+        Err(err) => Ok(ValidateCallbackResult::Invalid(String::from(err))),
+    }
+}
+
+/// Check that target is a direct child of base & that the time components of base are those of the start of chunk at the given depth
+pub(crate) fn validate_chunk_path(
+    base: &Path,
+    target: &Path,
+    chunk: &Index,
+    depth: &IndexType,
+) -> IndexResult<()> {
+    let base: Vec<Component> = base.clone().into();
+    let target: Vec<Component> = target.clone().into();
+    if base.is_empty() || target.len() != base.len() + 1 || target[..base.len()] != base[..] {
+        return Err(IndexError::RequestError(
+            "Time chunk must be linked from its parent path",
+        ));
+    };
+    let expected =
+        naivedatetime_to_components(&duration_to_datetime(&chunk.from).naive_utc(), depth)?;
+    if base[1..] != expected[..] {
+        return Err(IndexError::RequestError(
+            "Time chunk must be linked from the time path containing its start",
+        ));
+    };
+    Ok(())
+}
+
+/// Validate that a link made from an index in the time tree does not point onto an index which starts in the future.
+/// Time is read from the links base path so validators do not have to trust the authors client side checks. The future is judged
+/// relative to the timestamp of the link's header rather than the validator's clock, so every validator reaches the same verdict
+/// whenever it validates the link; holochain's own validation rejects headers timestamped beyond the present.
+/// Links which do not originate from an index path are not checked and are considered valid
+pub fn validate_index_link_timing(
+    data: &ValidateCreateLinkData,
+) -> ExternResult<ValidateCallbackResult> {
+    let index = match get_index_from_entry(&data.base) {
+        Some(index) => index,
+        None => return Ok(ValidateCallbackResult::Valid),
+    };
+    let made_at = match timestamp_to_duration(&data.link_add.timestamp) {
+        Ok(made_at) => made_at,
+        Err(err) => return Ok(ValidateCallbackResult::Invalid(String::from(err))),
+    };
+    match validate_index_timing(&index, &made_at, &FUTURE_TIME_TOLERANCE) {
+        Ok(()) => Ok(ValidateCallbackResult::Valid),
+        Err(err) => Ok(ValidateCallbackResult::Invalid(String::from(err))),
+    }
+}
+
+/// Validate that the index a link is made from is linked to from its parent path; so that the index can be found when traversing the time tree.
+/// An index which has not been linked into the tree would hold links which are invisible to searches.
+/// Since the parent link may not yet have reached the validating agent, a missing link is reported as an unresolved dependency on the parent path.
+/// Links which do not originate from an index path are not checked and are considered valid
+pub fn validate_index_link_reachable(
+    data: &ValidateCreateLinkData,
+) -> ExternResult<ValidateCallbackResult> {
+    let path = match get_path_from_entry(&data.base) {
+        Some(path) if Index::try_from(path.clone()).is_ok() => path,
+        _ => return Ok(ValidateCallbackResult::Valid),
+    };
+    let components: Vec<Component> = path.into();
+    let parent = Path::from(components[..components.len() - 1].to_vec()).path_entry_hash()?;
+    let parent_targets = get_links(parent.clone(), None)?
+        .into_iter()
+        .map(|link| link.target)
+        .collect::<Vec<EntryHash>>();
+    if is_linked_from_parent(&parent_targets, &data.link_add.base_address) {
+        Ok(ValidateCallbackResult::Valid)
+    } else {
+        Ok(ValidateCallbackResult::UnresolvedDependencies(vec![
+            parent.into()
+        ]))
+    }
+}
+
+/// Check that a path is the target of one of its parents links
+pub(crate) fn is_linked_from_parent(parent_targets: &[EntryHash], path: &EntryHash) -> bool {
+    parent_targets.iter().any(|target| target == path)
+}
+
+/// Try to read a Path from an entry. Returns None if entry is not a path
+pub(crate) fn get_path_from_entry(entry: &Entry) -> Option<Path> {
+    match entry {
+        Entry::App(bytes) => Path::try_from(bytes.clone().into_sb()).ok(),
+        _ => None,
+    }
+}
+
+/// Try to read an Index from an entry which is expected to be an index path. Returns None if entry is not an index path
+pub(crate) fn get_index_from_entry(entry: &Entry) -> Option<Index> {
+    Index::try_from(get_path_from_entry(entry)?).ok()
+}
+
+/// Validate that a link made from an index in the time tree is not made on an index which ended more than MAX_BACKDATE before the link
+/// was made; so that DHT's can refuse to have ancient data backfilled into the time tree. Time is read from the links base path and
+/// compared against the timestamp of the link's header rather than the validator's clock, so a link stays valid however long after
+/// it was made it is validated. Links which do not originate from an index path, or DHT's without a max_backdate configured, are not
+/// checked and are considered valid
+pub fn validate_index_link_age(
+    data: &ValidateCreateLinkData,
+) -> ExternResult<ValidateCallbackResult> {
+    let index = match get_index_from_entry(&data.base) {
+        Some(index) => index,
+        None => return Ok(ValidateCallbackResult::Valid),
+    };
+    let made_at = match timestamp_to_duration(&data.link_add.timestamp) {
+        Ok(made_at) => made_at,
+        Err(err) => return Ok(ValidateCallbackResult::Invalid(String::from(err))),
+    };
+    match validate_index_age(&index, &made_at, &MAX_BACKDATE) {
+        Ok(()) => Ok(ValidateCallbackResult::Valid),
+        Err(err) => Ok(ValidateCallbackResult::Invalid(String::from(err))),
+    }
+}
+
+/// Check that an index does not end more than max_backdate before made_at. Unbounded if max_backdate is None
+pub(crate) fn validate_index_age(
+    index: &Index,
+    made_at: &Duration,
+    max_backdate: &Option<Duration>,
+) -> IndexResult<()> {
+    if let Some(max_backdate) = max_backdate {
+        if made_at.saturating_sub(*max_backdate) > index.until {
+            return Err(IndexError::RequestError(
+                "Index link cannot be made on a time index older than max backdate",
+            ));
+        };
+    };
+    Ok(())
+}
+
+/// Check that an index does not start further after made_at than tolerance allows
+pub(crate) fn validate_index_timing(
+    index: &Index,
+    made_at: &Duration,
+    tolerance: &Duration,
+) -> IndexResult<()> {
+    if index.from > *made_at + *tolerance {
+        return Err(IndexError::RequestError(
+            "Index link cannot be made on a time index in the future",
+        ));
+    };
+    Ok(())
+}
+
+/// Check that the deepest level of the index depth can uniquely place chunks of the given interval.
+/// Every chunk must fit inside a single path node at the deepest level, and path node boundaries must fall on chunk boundaries
+pub(crate) fn validate_index_depth(depth: &[IndexType], interval: &Duration) -> IndexResult<()> {
+    if interval.as_nanos() == 0 {
+        return Err(IndexError::DepthIntervalMismatch(
+            "Chunk interval must be greater than zero",
+        ));
+    };
+    let deepest = depth
+        .iter()
+        .map(|index_type| index_type.granularity())
+        .min()
+        .ok_or(IndexError::DepthIntervalMismatch(
+            "Index depth must contain at least one level",
+        ))?;
+    if *interval > deepest {
+        return Err(IndexError::DepthIntervalMismatch(
+            "Chunk interval is larger than the deepest level of the index depth",
+        ));
+    };
+    if deepest.as_nanos() % interval.as_nanos() != 0 {
+        return Err(IndexError::DepthIntervalMismatch(
+            "Chunk interval does not evenly divide the deepest level of the index depth",
+        ));
+    };
+    Ok(())
+}
+
+/// Check that genesis falls on an interval boundary counted from UNIX epoch. Time path nodes are aligned to the calendar (and so to UNIX epoch);
+/// a genesis which is not would produce chunks which straddle two path nodes and could not be found from the path of their later half
+pub(crate) fn validate_genesis_alignment(
+    genesis: &Duration,
+    interval: &Duration,
+) -> IndexResult<()> {
+    if interval.as_nanos() == 0 || genesis.as_nanos() % interval.as_nanos() != 0 {
+        return Err(IndexError::InvalidConfiguration(
+            "genesis must be a multiple of max_chunk_interval since UNIX epoch",
+        ));
+    };
+    Ok(())
+}
+
+/// Check that an index spans exactly one interval and is aligned to interval boundaries counted from genesis
+pub(crate) fn validate_index_alignment(
+    index: &Index,
+    interval: &Duration,
+    genesis: &Duration,
+) -> IndexResult<()> {
+    if index.from < *genesis {
+        return Err(IndexError::RequestError(
+            "Time chunk cannot start before genesis",
+        ));
+    };
+    if index.until < index.from || index.until - index.from != *interval {
+        return Err(IndexError::RequestError(
+            "Time chunk should use period equal to max interval set by DNA",
+        ));
+    };
+    //Compared at nanosecond precision; a chunk with any stray offset would hash to a different path than the chunk other clients create
+    if (index.from - *genesis).as_nanos() % interval.as_nanos() != 0 {
+        return Err(IndexError::RequestError(
+            "Time chunk does not follow chunk interval ordering",
+        ));
+    };
+    Ok(())
+}
+
+/// Validate the libs configured INDEX_DEPTH against the configured MAX_CHUNK_INTERVAL
+pub(crate) fn validate_configured_index_depth() -> IndexResult<()> {
+    validate_index_depth(&INDEX_DEPTH, &MAX_CHUNK_INTERVAL)
+}
 
 impl Index {
     pub fn validate_chunk(&self) -> IndexResult<()> {
-        //TODO: incorrect error type being used here
-        let now_since_epoch = sys_time()?
-            .checked_difference_signed(&Timestamp::from_micros(0))
-            .ok_or(IndexError::InternalError("Should not overflow"))?
-            .to_std()
-            .map_err(|_err| IndexError::InternalError("Should not overflow"))?;
+        let now_since_epoch = now_since_epoch()?;
         if self.from > now_since_epoch {
             return Err(IndexError::RequestError(
                 "Time chunk cannot start in the future",
             ));
         };
-        if self.until - self.from != *MAX_CHUNK_INTERVAL {
-            return Err(IndexError::RequestError(
-                "Time chunk should use period equal to max interval set by DNA",
-            ));
+        validate_index_alignment(self, &MAX_CHUNK_INTERVAL, &GENESIS)
+    }
+}
+
+/// Validate that the author of a link made from an index has not made more than ENFORCE_SPAM_LIMIT links on that index.
+/// Links made on the index in any shard count towards the limit; since authors choose the entries they index, and with them the shard,
+/// a limit per shard would allow INDEX_SHARDS times as many links. If SPAM_LIMIT_PER_TAG is set only links with the same tag as the
+/// link being validated are counted.
+/// Links which do not originate from an index path are not checked and are considered valid
+pub fn validate_index_link_limit(
+    data: &ValidateCreateLinkData,
+) -> ExternResult<ValidateCallbackResult> {
+    //Interesting interplay developing here
+    //The complexity to make one link increases with number of links on that chunk
+    //Thus you could say its worth making chunks as small as possible
+    //But then you may get added retrieval complexity for a given timeperiod
+    //I.e having to ask for links on 100 individual second chunks vs two 50 second chunks
+    //You could probably algorithmically deduce the ideal value for retrival vs commit intensity
+    //To avoid rescanning all links on a chunk for every new link the count is read from the authors own chain instead
+    let (path, index) = match get_path_from_entry(&data.base) {
+        Some(path) => match Index::try_from(path.clone()) {
+            Ok(index) => (path, index),
+            Err(_err) => return Ok(ValidateCallbackResult::Valid),
+        },
+        None => return Ok(ValidateCallbackResult::Valid),
+    };
+    let bases =
+        shard_chunk_hashes(&path, &index).map_err(|err| WasmError::Host(String::from(err)))?;
+    let link = &data.link_add;
+    let tag = if *SPAM_LIMIT_PER_TAG {
+        Some(&link.tag)
+    } else {
+        None
+    };
+    let prior = count_prior_links(
+        link.prev_header.to_owned(),
+        &link_horizon(&index, &FUTURE_TIME_TOLERANCE),
+        *ENFORCE_SPAM_LIMIT,
+        |base, link_tag| bases.contains(base) && tag.map(|tag| tag == link_tag).unwrap_or(true),
+        |hash| {
+            Ok(ChainHeader::from(
+                must_get_header(hash.to_owned())?.header(),
+            ))
+        },
+    )
+    .map_err(|err| WasmError::Host(String::from(err)))?;
+    if prior >= *ENFORCE_SPAM_LIMIT {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Agent has reached the link limit for this index",
+        )));
+    };
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Hashes of the path of index in every shard of the index that path belongs to
+pub(crate) fn shard_chunk_hashes(path: &Path, index: &Index) -> IndexResult<Vec<EntryHash>> {
+    let shard_index = StringIndex::try_from(&WrappedPath(path.to_owned()))?.0;
+    shard_index_names(unsharded_index_name(&shard_index), *INDEX_SHARDS)
+        .into_iter()
+        .map(|shard| Ok(index.path(shard)?.path_entry_hash()?))
+        .collect()
+}
+
+/// Earliest header timestamp a link onto index can carry and still pass [`validate_index_link_timing()`]
+pub(crate) fn link_horizon(index: &Index, tolerance: &Duration) -> Timestamp {
+    Timestamp::from_micros(index.from.saturating_sub(*tolerance).as_micros() as i64)
+}
+
+/// The parts of a header on an authors chain needed to count the links they have made
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ChainHeader {
+    pub timestamp: Timestamp,
+    pub prev_header: Option<HeaderHash>,
+    /// Base & tag if the header created a link
+    pub link: Option<(EntryHash, LinkTag)>,
+}
+
+impl From<&Header> for ChainHeader {
+    fn from(header: &Header) -> Self {
+        ChainHeader {
+            timestamp: header.timestamp(),
+            prev_header: header.prev_header().cloned(),
+            link: match header {
+                Header::CreateLink(create_link) => Some((
+                    create_link.base_address.to_owned(),
+                    create_link.tag.to_owned(),
+                )),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Count the links accepted by counts which were made on an authors chain before (and including) the header at prev_header. Since chain timestamps never
+/// decrease the walk stops at the first header made before horizon, or once limit links have been counted; so the cost of a count
+/// depends on the authors own activity since horizon and never on the number of links other agents have made on the same base.
+/// Headers are immutable, so every validator counts the same links whatever its view of the DHT
+pub(crate) fn count_prior_links<
+    M: Fn(&EntryHash, &LinkTag) -> bool,
+    F: FnMut(&HeaderHash) -> IndexResult<ChainHeader>,
+>(
+    prev_header: HeaderHash,
+    horizon: &Timestamp,
+    limit: usize,
+    counts: M,
+    mut get_header: F,
+) -> IndexResult<usize> {
+    let mut count = 0;
+    let mut next = Some(prev_header);
+    while let Some(hash) = next {
+        if count >= limit {
+            break;
         };
-        if self.until - self.from != *MAX_CHUNK_INTERVAL {
-            return Err(IndexError::RequestError(
-                "Time chunk should use period equal to max interval set by DNA",
-            ));
+        let header = get_header(&hash)?;
+        if header.timestamp < *horizon {
+            break;
         };
-        if self.from.as_millis() % MAX_CHUNK_INTERVAL.as_millis() != 0 {
-            return Err(IndexError::RequestError(
-                "Time chunk does not follow chunk interval ordering",
+        if let Some((base, tag)) = &header.link {
+            if counts(base, tag) {
+                count += 1;
+            };
+        };
+        next = header.prev_header;
+    }
+    Ok(count)
+}
+
+mod validation_tests {
+    #[test]
+    fn test_count_prior_links() {
+        use crate::validation::{count_prior_links, ChainHeader};
+        use hdk::prelude::*;
+        use std::cell::Cell;
+        use std::collections::HashMap;
+
+        let hash = |seq: u32| {
+            let mut raw = seq.to_le_bytes().to_vec();
+            raw.resize(36, 0);
+            HeaderHash::from_raw_36(raw)
+        };
+        let index_base = EntryHash::from_raw_36(vec![1; 36]);
+        let other_base = EntryHash::from_raw_36(vec![2; 36]);
+        let post = LinkTag::new("post");
+        let reaction = LinkTag::new("reaction");
+        //Alice's chain: 100 headers before the chunk started, then links on the chunk interleaved with links elsewhere
+        let chain = |links: u32| {
+            (0..100 + links * 2)
+                .map(|seq| {
+                    let link = match seq {
+                        seq if seq < 100 => Some((index_base.clone(), post.clone())),
+                        seq if seq % 2 == 0 => Some((index_base.clone(), post.clone())),
+                        seq if seq % 4 == 1 => Some((other_base.clone(), post.clone())),
+                        _ => Some((index_base.clone(), reaction.clone())),
+                    };
+                    let header = ChainHeader {
+                        timestamp: Timestamp::from_micros(seq as i64),
+                        prev_header: if seq == 0 { None } else { Some(hash(seq - 1)) },
+                        link,
+                    };
+                    (hash(seq), header)
+                })
+                .collect::<HashMap<HeaderHash, ChainHeader>>()
+        };
+        let horizon = Timestamp::from_micros(100);
+        let on_index = |base: &EntryHash, _tag: &LinkTag| *base == index_base;
+        let posts_on_index = |base: &EntryHash, tag: &LinkTag| *base == index_base && *tag == post;
+
+        let chain_of_10 = chain(10);
+        let get = |hash: &HeaderHash| Ok(chain_of_10[hash].clone());
+        //Headers before the chunk started are never counted
+        assert_eq!(
+            count_prior_links(hash(119), &horizon, 50, on_index, get).unwrap(),
+            15
+        );
+        assert_eq!(
+            count_prior_links(hash(119), &horizon, 50, posts_on_index, get).unwrap(),
+            10
+        );
+        assert_eq!(
+            count_prior_links(hash(99), &horizon, 50, on_index, get).unwrap(),
+            0
+        );
+        //Counting stops at the limit
+        assert_eq!(
+            count_prior_links(hash(119), &horizon, 4, on_index, get).unwrap(),
+            4
+        );
+
+        //Headers fetched to reach the limit stays constant however many links have already been made on the chunk
+        for links in [10, 100, 1000].iter() {
+            let chain = chain(*links);
+            let fetched = Cell::new(0);
+            let get = |hash: &HeaderHash| {
+                fetched.set(fetched.get() + 1);
+                Ok(chain[hash].clone())
+            };
+            let count =
+                count_prior_links(hash(99 + links * 2), &horizon, 6, on_index, get).unwrap();
+            assert_eq!(count, 6);
+            assert_eq!(fetched.get(), 8);
+        }
+    }
+
+    #[test]
+    fn test_link_horizon() {
+        use crate::entries::Index;
+        use crate::validation::link_horizon;
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        let index = Index {
+            from: Duration::from_secs(1_628_776_880),
+            until: Duration::from_secs(1_628_776_890),
+        };
+        assert_eq!(
+            link_horizon(&index, &Duration::from_secs(60)),
+            Timestamp::from_micros(1_628_776_820_000_000)
+        );
+        let genesis_index = Index {
+            from: Duration::from_secs(10),
+            until: Duration::from_secs(20),
+        };
+        assert_eq!(
+            link_horizon(&genesis_index, &Duration::from_secs(60)),
+            Timestamp::from_micros(0)
+        );
+    }
+
+    #[test]
+    fn test_validate_index_alignment() {
+        use crate::entries::Index;
+        use crate::validation::validate_index_alignment;
+        use std::time::Duration;
+
+        let interval = Duration::from_secs(10);
+        let index_at = |from: u64| Index {
+            from: Duration::from_millis(from),
+            until: Duration::from_millis(from) + interval,
+        };
+        //Aligned to UNIX epoch
+        let genesis = Duration::from_secs(0);
+        assert!(
+            validate_index_alignment(&index_at(1_628_776_830_000), &interval, &genesis).is_ok()
+        );
+        assert!(
+            validate_index_alignment(&index_at(1_628_776_835_000), &interval, &genesis).is_err()
+        );
+
+        //Aligned to declared genesis
+        let genesis = Duration::from_millis(1_628_776_805_000);
+        assert!(
+            validate_index_alignment(&index_at(1_628_776_835_000), &interval, &genesis).is_ok()
+        );
+        assert!(
+            validate_index_alignment(&index_at(1_628_776_830_000), &interval, &genesis).is_err()
+        );
+        //Before genesis
+        assert!(
+            validate_index_alignment(&index_at(1_628_776_795_000), &interval, &genesis).is_err()
+        );
+
+        //Offset from an interval boundary by a few milliseconds or less
+        let genesis = Duration::from_secs(0);
+        let offset_index = |offset: Duration| Index {
+            from: Duration::from_millis(1_628_776_830_000) + offset,
+            until: Duration::from_millis(1_628_776_830_000) + offset + interval,
+        };
+        assert!(validate_index_alignment(
+            &offset_index(Duration::from_secs(0)),
+            &interval,
+            &genesis
+        )
+        .is_ok());
+        assert!(validate_index_alignment(
+            &offset_index(Duration::from_millis(3)),
+            &interval,
+            &genesis
+        )
+        .is_err());
+        assert!(validate_index_alignment(
+            &offset_index(Duration::from_micros(250)),
+            &interval,
+            &genesis
+        )
+        .is_err());
+        assert!(validate_index_alignment(
+            &offset_index(Duration::from_nanos(1)),
+            &interval,
+            &genesis
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate_index_timing() {
+        use crate::entries::Index;
+        use crate::validation::validate_index_timing;
+        use std::time::Duration;
+
+        let now = Duration::from_secs(1_628_776_890);
+        let tolerance = Duration::from_secs(60);
+        let index_at = |from: Duration| Index {
+            from: from,
+            until: from + Duration::from_secs(1),
+        };
+        //Past
+        assert!(validate_index_timing(
+            &index_at(now - Duration::from_secs(3600)),
+            &now,
+            &tolerance
+        )
+        .is_ok());
+        //Present
+        assert!(validate_index_timing(&index_at(now), &now, &tolerance).is_ok());
+        //Future but within tolerance
+        assert!(
+            validate_index_timing(&index_at(now + Duration::from_secs(30)), &now, &tolerance)
+                .is_ok()
+        );
+        //Future
+        assert!(validate_index_timing(
+            &index_at(now + Duration::from_secs(3600)),
+            &now,
+            &tolerance
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate_index_age() {
+        use crate::entries::Index;
+        use crate::validation::validate_index_age;
+        use std::time::Duration;
+
+        let made_at = Duration::from_secs(1_628_776_890);
+        let day = Duration::from_secs(86400);
+        let index_at = |from: Duration| Index {
+            from: from,
+            until: from + Duration::from_secs(10),
+        };
+        //Within horizon
+        assert!(validate_index_age(&index_at(made_at - day / 2), &made_at, &Some(day)).is_ok());
+        //Index ending exactly at the horizon
+        assert!(validate_index_age(
+            &index_at(made_at - day - Duration::from_secs(10)),
+            &made_at,
+            &Some(day)
+        )
+        .is_ok());
+        //Beyond horizon
+        assert!(validate_index_age(&index_at(made_at - day * 2), &made_at, &Some(day)).is_err());
+        //Unbounded by default
+        assert!(validate_index_age(&index_at(Duration::from_secs(0)), &made_at, &None).is_ok());
+        //Horizon before UNIX epoch
+        assert!(validate_index_age(
+            &index_at(Duration::from_secs(0)),
+            &made_at,
+            &Some(made_at * 2)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_validate_index_depth() {
+        use crate::entries::IndexType;
+        use crate::errors::IndexError;
+        use crate::validation::validate_index_depth;
+        use std::time::Duration;
+
+        let depth = vec![IndexType::Minute, IndexType::Hour, IndexType::Day];
+        assert!(validate_index_depth(&depth, &Duration::from_secs(10)).is_ok());
+        assert!(validate_index_depth(&depth, &Duration::from_secs(60)).is_ok());
+
+        let depth = vec![IndexType::Hour, IndexType::Day];
+        assert!(matches!(
+            validate_index_depth(&depth, &Duration::from_secs(7 * 3600)),
+            Err(IndexError::DepthIntervalMismatch(_))
+        ));
+        assert!(matches!(
+            validate_index_depth(&depth, &Duration::from_secs(90 * 60)),
+            Err(IndexError::DepthIntervalMismatch(_))
+        ));
+        assert!(matches!(
+            validate_index_depth(&depth, &Duration::from_secs(0)),
+            Err(IndexError::DepthIntervalMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_linked_from_parent() {
+        use crate::validation::is_linked_from_parent;
+        use hdk::prelude::*;
+
+        let index = EntryHash::from_raw_36(vec![1; 36]);
+        let siblings = vec![
+            EntryHash::from_raw_36(vec![2; 36]),
+            EntryHash::from_raw_36(vec![3; 36]),
+        ];
+        //Orphaned index
+        assert!(!is_linked_from_parent(&siblings, &index));
+        assert!(!is_linked_from_parent(&[], &index));
+
+        //Linked index
+        let mut linked = siblings.clone();
+        linked.push(index.clone());
+        assert!(is_linked_from_parent(&linked, &index));
+    }
+
+    #[test]
+    fn test_genesis_off_calendar_boundary() {
+        use crate::entries::IndexType;
+        use crate::utils::{
+            duration_to_datetime, index_depth_for_interval, index_for_timestamp,
+            naivedatetime_to_components,
+        };
+        use crate::validation::validate_genesis_alignment;
+        use std::time::Duration;
+
+        //Genesis at 00:00:37.500 is not on a second boundary
+        let genesis = Duration::new(1_628_726_437, 500_000_000);
+        assert!(validate_genesis_alignment(&genesis, &Duration::from_secs(10)).is_err());
+        assert!(validate_genesis_alignment(&genesis, &Duration::from_secs(1)).is_err());
+
+        //But is on a 500ms boundary; every chunk should sit inside a single path node so entries resolve from the path of their own time
+        let interval = Duration::from_millis(500);
+        assert!(validate_genesis_alignment(&genesis, &interval).is_ok());
+        let depth = index_depth_for_interval(&interval);
+        assert_eq!(depth[0], IndexType::Second);
+        let components = |time: Duration| {
+            naivedatetime_to_components(&duration_to_datetime(&time).naive_utc(), &depth[0])
+                .unwrap()
+        };
+        for offset in 0..200u64 {
+            let time = genesis + Duration::from_millis(offset * 130);
+            let index = index_for_timestamp(time, &interval, &genesis).unwrap();
+            assert!(index.from <= time && time < index.until);
+            assert_eq!(components(time), components(index.from));
+            assert_eq!(
+                components(index.from),
+                components(index.until - Duration::from_nanos(1))
+            );
+        }
+    }
+
+    #[test]
+    fn test_seven_hour_interval() {
+        use crate::entries::IndexType;
+        use crate::errors::IndexError;
+        use crate::utils::{
+            duration_to_datetime, index_depth_for_interval, index_for_timestamp,
+            naivedatetime_to_components,
+        };
+        use crate::validation::validate_index_depth;
+        use crate::IndexConfiguration;
+        use std::time::Duration;
+
+        let day = |time: Duration| {
+            naivedatetime_to_components(&duration_to_datetime(&time).naive_utc(), &IndexType::Day)
+                .unwrap()
+        };
+        let hours = |hours: u64| Duration::from_secs(hours * 3600);
+
+        //7 hour chunks drift relative to days; 01:00 on the second day falls in the chunk starting 21:00 the day before
+        let interval = hours(7);
+        let index = index_for_timestamp(hours(25), &interval, &Duration::from_secs(0)).unwrap();
+        assert_eq!(index.from, hours(21));
+        assert_ne!(day(index.from), day(index.until - Duration::from_nanos(1)));
+
+        //Such intervals are rejected rather than risk chunks being missed by day level traversal
+        assert!(matches!(
+            validate_index_depth(&index_depth_for_interval(&interval), &interval),
+            Err(IndexError::DepthIntervalMismatch(_))
+        ));
+        let config = IndexConfiguration {
+            enforce_spam_limit: 20,
+            max_chunk_interval: interval.as_millis() as usize,
+            genesis: None,
+            get_retry_attempts: None,
+            spam_limit_per_tag: false,
+            index_shards: None,
+            get_links_concurrency: None,
+            max_backdate: None,
+        };
+        assert!(config.validate().is_err());
+
+        //Accepted hour intervals never produce chunks crossing midnight
+        for interval in vec![1, 2, 3, 4, 6, 8, 12, 24].into_iter().map(hours) {
+            assert!(validate_index_depth(&index_depth_for_interval(&interval), &interval).is_ok());
+            for hour in 0..72 {
+                let index =
+                    index_for_timestamp(hours(hour), &interval, &Duration::from_secs(0)).unwrap();
+                assert_eq!(day(index.from), day(index.until - Duration::from_nanos(1)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_classify_and_validate_links() {
+        use crate::entries::Index;
+        use crate::utils::time_path_link_tag;
+        use crate::validation::{classify_link, validate_time_path_link, IndexLinkKind};
+        use hdk::hash_path::path::{Component, Path};
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        let entry = |path: Path| {
+            Entry::App(AppEntryBytes::try_from(SerializedBytes::try_from(path).unwrap()).unwrap())
+        };
+        let index = Index {
+            from: Duration::from_secs(1_628_776_880),
+            until: Duration::from_secs(1_628_776_890),
+        };
+        let index_path = entry(Path::from(vec![
+            Component::from("test_index"),
+            Component::from(SerializedBytes::try_from(index).unwrap().bytes().to_owned()),
+        ]));
+        let time_path = entry(Path::from(vec![
+            Component::from("test_index"),
+            Component::from("2021"),
+        ]));
+        let app_entry = Entry::App(
+            AppEntryBytes::try_from(SerializedBytes::from(UnsafeBytes::from(vec![0xc0]))).unwrap(),
+        );
+
+        //Links from an index to indexed entries
+        assert_eq!(
+            classify_link(&index_path, &app_entry, &LinkTag::new("test")),
+            IndexLinkKind::Index
+        );
+
+        //Links back to the time tree must point onto an index
+        let tag = time_path_link_tag("test_index");
+        assert_eq!(
+            classify_link(&app_entry, &index_path, &tag),
+            IndexLinkKind::TimePath
+        );
+        assert_eq!(
+            validate_time_path_link(&index_path),
+            ValidateCallbackResult::Valid
+        );
+        assert!(matches!(
+            validate_time_path_link(&time_path),
+            ValidateCallbackResult::Invalid(_)
+        ));
+        assert!(matches!(
+            validate_time_path_link(&app_entry),
+            ValidateCallbackResult::Invalid(_)
+        ));
+
+        //Path links & links not made by this crate
+        assert_eq!(
+            classify_link(&time_path, &time_path, &LinkTag::new("2021")),
+            IndexLinkKind::Other
+        );
+        assert_eq!(
+            classify_link(&app_entry, &app_entry, &LinkTag::new("test")),
+            IndexLinkKind::Other
+        );
+
+        //Links from a time path onto an index
+        assert_eq!(
+            classify_link(&time_path, &index_path, &LinkTag::new("chunk")),
+            IndexLinkKind::Chunk
+        );
+    }
+
+    #[test]
+    fn test_validate_chunk_path() {
+        use crate::entries::{Index, IndexType, StringIndex};
+        use crate::utils::{duration_to_datetime, naivedatetime_to_components};
+        use crate::validation::validate_chunk_path;
+        use hdk::hash_path::path::{Component, Path};
+        use hdk::prelude::*;
+        use std::time::Duration;
+
+        //Chunk starting 2021-08-12 14:01:20 hung from day paths
+        let chunk = Index {
+            from: Duration::from_secs(1_628_776_880),
+            until: Duration::from_secs(1_628_776_890),
+        };
+        let day_path = |from: &Duration| {
+            let mut components = vec![Component::from(
+                StringIndex(String::from("test_index"))
+                    .get_sb()
+                    .unwrap()
+                    .bytes()
+                    .to_owned(),
+            )];
+            components.append(
+                &mut naivedatetime_to_components(
+                    &duration_to_datetime(from).naive_utc(),
+                    &IndexType::Day,
+                )
+                .unwrap(),
+            );
+            components
+        };
+        let chunk_path = |mut day: Vec<Component>| {
+            day.push(Component::from(
+                SerializedBytes::try_from(chunk.clone())
+                    .unwrap()
+                    .bytes()
+                    .to_owned(),
             ));
+            Path::from(day)
         };
-        Ok(())
-    }
-
-    // pub fn validate_chunk_link(&self, link: LinkData) -> ExternResult<()> {
-    //     //Interesting interplay developing here
-    //     //The complexity to make one link increases with number of links on that chunk
-    //     //Thus you could say its worth making chunks as small as possible
-    //     //But then you may get added retrieval complexity for a given timeperiod
-    //     //I.e having to ask for links on 100 individual second chunks vs two 50 second chunks
-    //     //You could probably algorithmically deduce the ideal value for retrival vs commit intensity
-    //     if get_links(self.hash(), None).filter(|commited_link| commited_link.author == link.author).count() -1 > DIRECT_CHUNK_LIMIT {
-    //         return Err(())
-    //     } else {
-    //         return Ok(())
-    //     }
-    // }
+
+        let correct_day = day_path(&chunk.from);
+        assert!(validate_chunk_path(
+            &Path::from(correct_day.clone()),
+            &chunk_path(correct_day.clone()),
+            &chunk,
+            &IndexType::Day
+        )
+        .is_ok());
+
+        //Chunk linked under the previous day
+        let wrong_day = day_path(&(chunk.from - Duration::from_secs(86400)));
+        assert!(validate_chunk_path(
+            &Path::from(wrong_day.clone()),
+            &chunk_path(wrong_day),
+            &chunk,
+            &IndexType::Day
+        )
+        .is_err());
+
+        //Chunk path does not hang from the base path
+        assert!(validate_chunk_path(
+            &Path::from(correct_day[..2].to_vec()),
+            &chunk_path(correct_day),
+            &chunk,
+            &IndexType::Day
+        )
+        .is_err());
+    }
 }
@@ -10,6 +10,9 @@ use hc_time_index::*;
 pub struct TestEntry {
     pub title: String,
     pub created: DateTime<Utc>,
+    //If set entry is also indexed at the time it was updated
+    #[serde(default)]
+    pub updated: Option<DateTime<Utc>>,
 }
 
 impl IndexableEntry for TestEntry {
@@ -17,6 +20,12 @@ impl IndexableEntry for TestEntry {
         self.created
     }
 
+    fn index_times(&self) -> Vec<DateTime<Utc>> {
+        let mut times = vec![self.created];
+        times.extend(self.updated);
+        times
+    }
+
     fn hash(&self) -> ExternResult<EntryHash> {
         hash_entry(self)
     }
@@ -26,17 +35,106 @@ entry_defs![Path::entry_def(), TestEntry::entry_def()];
 
 #[hdk_extern]
 pub fn init(_: ()) -> ExternResult<InitCallbackResult> {
-    Ok(InitCallbackResult::Pass)
+    match hc_time_index::validate_configuration() {
+        Ok(()) => Ok(InitCallbackResult::Pass),
+        Err(err) => Ok(InitCallbackResult::Fail(String::from(err))),
+    }
+}
+
+#[hdk_extern]
+pub fn validate_create_link(data: ValidateCreateLinkData) -> ExternResult<ValidateCallbackResult> {
+    hc_time_index::validate_create_link(&data)
+}
+
+#[hdk_extern]
+pub fn index_entry(entry: TestEntry) -> ExternResult<Indexed> {
+    create_entry(&entry)?;
+    Ok(hc_time_index::index_entry(
+        String::from("test_index"),
+        entry,
+        LinkTag::new("test"),
+    )?)
+}
+
+#[hdk_extern]
+pub fn index_hash(entry: TestEntry) -> ExternResult<()> {
+    create_entry(&entry)?;
+    //Index using only the hash of the created entry
+    let hash = hash_entry(&entry)?;
+    hc_time_index::index_hash(
+        String::from("test_index"),
+        hash,
+        entry.created,
+        LinkTag::new("test"),
+        false,
+    )?;
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Debug)]
+pub struct IndexUncommittedInput {
+    pub entry: TestEntry,
+    pub allow_dangling: bool,
+}
+
+#[hdk_extern]
+pub fn index_uncommitted_hash(input: IndexUncommittedInput) -> ExternResult<()> {
+    //Entry is not committed so its hash does not resolve
+    let hash = hash_entry(&input.entry)?;
+    hc_time_index::index_hash(
+        String::from("test_index"),
+        hash,
+        input.entry.created,
+        LinkTag::new("test"),
+        input.allow_dangling,
+    )?;
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Debug)]
+pub struct IndexEntryWithInput {
+    pub title: String,
+    //Time entry should be indexed at as milliseconds since UNIX epoch
+    pub created_millis: i64,
 }
 
 #[hdk_extern]
-pub fn index_entry(entry: TestEntry) -> ExternResult<()> {
+pub fn index_entry_with(input: IndexEntryWithInput) -> ExternResult<()> {
+    let created = hc_time_index::from_unix_millis(input.created_millis)?;
+    let entry = TestEntry {
+        title: input.title,
+        created: created,
+        updated: None,
+    };
     create_entry(&entry)?;
-    hc_time_index::index_entry(String::from("test_index"), entry, LinkTag::new("test"))
-        .map_err(|err| WasmError::Host(String::from(err)))?;
+    //Time is resolved by closure rather than by IndexableEntry::entry_time()
+    hc_time_index::index_entry_with(
+        String::from("test_index"),
+        hash_entry(&entry)?,
+        || created,
+        LinkTag::new("test"),
+    )?;
     Ok(())
 }
 
+#[derive(Serialize, Deserialize, SerializedBytes, Debug)]
+pub struct ReindexInput {
+    pub target: EntryHash,
+    pub old_time: DateTime<Utc>,
+    pub new_time: DateTime<Utc>,
+}
+
+#[hdk_extern]
+pub fn reindex(input: ReindexInput) -> ExternResult<Indexed> {
+    Ok(hc_time_index::reindex(
+        String::from("test_index"),
+        input.target,
+        input.old_time,
+        input.new_time,
+        LinkTag::new("test"),
+    )?)
+}
+
 #[derive(Serialize, Deserialize, SerializedBytes, Debug)]
 pub struct GetAddressesSinceInput {
     pub index: String,
@@ -44,6 +142,8 @@ pub struct GetAddressesSinceInput {
     pub until: DateTime<Utc>,
     pub limit: Option<usize>,
     pub link_tag: Option<LinkTag>,
+    #[serde(default)]
+    pub include_deleted: bool,
 }
 
 #[hdk_extern]
@@ -55,8 +155,28 @@ pub fn get_indexes_for_time_span(
         input.from,
         input.until,
         input.link_tag,
-    )
-    .map_err(|err| WasmError::Host(String::from(err)))?)
+        input.include_deleted,
+    )?)
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Debug)]
+pub struct PrefetchSpanInput {
+    pub index: String,
+    pub from: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub link_tag: Option<LinkTag>,
+    pub include_targets: bool,
+}
+
+#[hdk_extern]
+pub fn prefetch_span(input: PrefetchSpanInput) -> ExternResult<usize> {
+    Ok(hc_time_index::prefetch_span(
+        input.index,
+        input.from,
+        input.until,
+        input.link_tag,
+        input.include_targets,
+    )?)
 }
 
 // #[hdk_extern]
@@ -81,8 +201,7 @@ pub fn get_links_and_load_for_time_span(input: GetAddressesSinceInput) -> Extern
         input.link_tag,
         hc_time_index::SearchStrategy::Dfs,
         Some(10),
-    )
-    .map_err(|err| WasmError::Host(String::from(err)))?)
+    )?)
 }
 
 #[derive(Serialize, Deserialize, SerializedBytes, Debug)]
@@ -90,29 +209,72 @@ pub struct GetCurrentAddressesInput {
     pub index: String,
     pub limit: Option<usize>,
     pub link_tag: Option<LinkTag>,
+    pub not_older_than: Option<DateTime<Utc>>,
 }
 
 #[hdk_extern]
 pub fn get_current_addresses(
     input: GetCurrentAddressesInput,
 ) -> ExternResult<Option<EntryChunkIndex>> {
-    Ok(
-        hc_time_index::get_current_index(input.index, input.link_tag)
-            .map_err(|err| WasmError::Host(String::from(err)))?,
-    )
+    Ok(hc_time_index::get_current_index(
+        input.index,
+        input.link_tag,
+    )?)
+}
+
+#[hdk_extern]
+pub fn get_current_or_recent_index(
+    input: GetCurrentAddressesInput,
+) -> ExternResult<Option<EntryChunkIndex>> {
+    Ok(hc_time_index::get_current_or_recent_index(
+        input.index,
+        input.link_tag,
+    )?)
 }
 
 #[hdk_extern]
 pub fn get_most_recent_indexes(
     input: GetCurrentAddressesInput,
 ) -> ExternResult<Option<EntryChunkIndex>> {
-    Ok(
-        hc_time_index::get_most_recent_indexes(input.index, input.link_tag)
-            .map_err(|err| WasmError::Host(String::from(err)))?,
-    )
+    Ok(hc_time_index::get_most_recent_indexes(
+        input.index,
+        input.link_tag,
+        input.not_older_than,
+    )?)
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Debug)]
+pub struct GetRecentIndexesInput {
+    pub index: String,
+    pub chunk_count: usize,
+    pub link_tag: Option<LinkTag>,
+}
+
+#[hdk_extern]
+pub fn get_recent_indexes(input: GetRecentIndexesInput) -> ExternResult<Vec<EntryChunkIndex>> {
+    Ok(hc_time_index::get_recent_indexes(
+        input.index,
+        input.chunk_count,
+        input.link_tag,
+    )?)
+}
+
+#[hdk_extern]
+pub fn ensure_current_chunk(index: String) -> ExternResult<hc_time_index::entries::Index> {
+    Ok(hc_time_index::ensure_current_chunk(index)?)
+}
+
+#[hdk_extern]
+pub fn when_indexed(address: EntryHash) -> ExternResult<Vec<DateTime<Utc>>> {
+    Ok(hc_time_index::when_indexed(address)?)
+}
+
+#[hdk_extern]
+pub fn index_time_bounds(index: String) -> ExternResult<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+    Ok(hc_time_index::index_time_bounds(index)?)
 }
 
 #[hdk_extern]
 pub fn remove_index(address: EntryHash) -> ExternResult<()> {
-    Ok(hc_time_index::remove_index(address).map_err(|err| WasmError::Host(String::from(err)))?)
+    Ok(hc_time_index::remove_index(address)?)
 }
@@ -140,6 +140,383 @@ orchestrator.registerScenario("test get links and load dfs", async (s, t) => {
   t.equal(asc_results[4].title, "A test index")
 })
 
+orchestrator.registerScenario("test most recent index skips emptied index", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (24*60*60*1000); //1 day ago
+  var yesterday = new Date(now.getTime() - dateOffset);
+
+  //Index entry
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index", created: now.toISOString()})
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index2", created: yesterday.toISOString()})
+
+  let newest = await alice_happ.cells[0].call("testing_zome", "get_most_recent_indexes", {index: "test_index"})
+  console.log("Got newest index", newest);
+  t.equal(newest.links.length, 1)
+
+  //Remove the newest entry leaving the newest day path without any links
+  await alice_happ.cells[0].call("testing_zome", "remove_index", newest.links[0].target)
+
+  let fallback = await alice_happ.cells[0].call("testing_zome", "get_most_recent_indexes", {index: "test_index"})
+  console.log("Got fallback index", fallback);
+  t.equal(fallback.links.length, 1)
+  t.ok(fallback.index.from.secs < newest.index.from.secs)
+})
+
+orchestrator.registerScenario("test current or recent index", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (2*60*1000); //2 minutes ago
+  var twoMinutesAgo = new Date(new Date().getTime() - dateOffset);
+
+  //Index entry
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index", created: twoMinutesAgo.toISOString()})
+
+  let current = await alice_happ.cells[0].call("testing_zome", "get_current_addresses", {index: "test_index"})
+  console.log("Got current index", current);
+  t.equal(current, null)
+
+  let recent = await alice_happ.cells[0].call("testing_zome", "get_current_or_recent_index", {index: "test_index"})
+  console.log("Got current or recent index", recent);
+  t.equal(recent.links.length, 1)
+})
+
+orchestrator.registerScenario("test most recent index not older than", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (24*60*60*1000); //1 day ago
+  var yesterday = new Date(now.getTime() - dateOffset);
+  var dateOffset = (60*60*1000); //1 hour ago
+  var hourAgo = new Date(now.getTime() - dateOffset);
+  var dateOffset = (24*60*60*1000) * 2; //2 days ago
+  var twoDaysAgo = new Date(now.getTime() - dateOffset);
+
+  //Index entry
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index", created: yesterday.toISOString()})
+
+  let within = await alice_happ.cells[0].call("testing_zome", "get_most_recent_indexes", {index: "test_index", not_older_than: twoDaysAgo.toISOString()})
+  console.log("Got index within bound", within);
+  t.equal(within.links.length, 1)
+
+  let outside = await alice_happ.cells[0].call("testing_zome", "get_most_recent_indexes", {index: "test_index", not_older_than: hourAgo.toISOString()})
+  console.log("Got index outside bound", outside);
+  t.equal(outside, null)
+})
+
+orchestrator.registerScenario("test ensure current chunk", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  let empty = await alice_happ.cells[0].call("testing_zome", "get_current_addresses", {index: "test_index"})
+  t.equal(empty, null)
+
+  let chunk = await alice_happ.cells[0].call("testing_zome", "ensure_current_chunk", "test_index")
+  console.log("Ensured current chunk", chunk);
+
+  let current = await alice_happ.cells[0].call("testing_zome", "get_current_addresses", {index: "test_index"})
+  console.log("Got current index", current);
+  t.deepEqual(current.index, chunk)
+  t.equal(current.links.length, 0)
+
+  //Second call should be a no-op and return the same chunk
+  let again = await alice_happ.cells[0].call("testing_zome", "ensure_current_chunk", "test_index")
+  t.deepEqual(again, chunk)
+})
+
+orchestrator.registerScenario("test when indexed", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  //Index entry
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index", created: now.toISOString()})
+
+  let recent = await alice_happ.cells[0].call("testing_zome", "get_most_recent_indexes", {index: "test_index"})
+  t.equal(recent.links.length, 1)
+
+  let times = await alice_happ.cells[0].call("testing_zome", "when_indexed", recent.links[0].target)
+  console.log("Got index times", times);
+  t.equal(times.length, 1)
+  t.equal(new Date(times[0]).getTime() / 1000, recent.index.from.secs)
+})
+
+orchestrator.registerScenario("test index hash", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (24*60*60*1000); //1 day ago
+  var yesterday = new Date(now.getTime() - dateOffset);
+
+  //Index entry by hash
+  await alice_happ.cells[0].call("testing_zome", "index_hash", {title: "A hash index", created: now.toISOString()})
+
+  let results_between = await alice_happ.cells[0].call("testing_zome", "get_links_and_load_for_time_span", {index: "test_index", from: yesterday.toISOString(), until: new Date().toISOString(), limit: 10})
+  console.log("Got results", results_between);
+  t.equal(results_between.length, 1)
+  t.equal(results_between[0].title, "A hash index")
+})
+
+orchestrator.registerScenario("test index entry with", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (24*60*60*1000); //1 day ago
+  var yesterday = new Date(now.getTime() - dateOffset);
+
+  //Index entry using time resolved by closure from unix millis
+  await alice_happ.cells[0].call("testing_zome", "index_entry_with", {title: "A closure index", created_millis: now.getTime()})
+
+  let results_between = await alice_happ.cells[0].call("testing_zome", "get_links_and_load_for_time_span", {index: "test_index", from: yesterday.toISOString(), until: new Date().toISOString(), limit: 10})
+  console.log("Got results", results_between);
+  t.equal(results_between.length, 1)
+  t.equal(results_between[0].title, "A closure index")
+  t.equal(new Date(results_between[0].created).getTime(), now.getTime())
+})
+
+orchestrator.registerScenario("test index hash target not found", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  //Indexing a hash which does not resolve is rejected
+  try {
+    await alice_happ.cells[0].call("testing_zome", "index_uncommitted_hash", {entry: {title: "A missing entry", created: now.toISOString()}, allow_dangling: false})
+    t.fail("Expected indexing of missing entry to fail")
+  } catch (err) {
+    console.log("Got error", err);
+    t.ok(JSON.stringify(err).includes("Entry to be indexed could not be found"))
+  }
+
+  //Committed entry is indexed as normal
+  await alice_happ.cells[0].call("testing_zome", "index_hash", {title: "A hash index", created: now.toISOString()})
+
+  //Override allows the missing entry to be indexed
+  await alice_happ.cells[0].call("testing_zome", "index_uncommitted_hash", {entry: {title: "A missing entry", created: now.toISOString()}, allow_dangling: true})
+})
+
+orchestrator.registerScenario("test prefetch span", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (24*60*60*1000); //1 day ago
+  var yesterday = new Date(now.getTime() - dateOffset);
+
+  //Two entries in one index & one in another
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index", created: now.toISOString()})
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index2", created: now.toISOString()})
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index3", created: yesterday.toISOString()})
+
+  let chunks = await alice_happ.cells[0].call("testing_zome", "prefetch_span", {index: "test_index", from: yesterday.toISOString(), until: new Date().toISOString(), include_targets: false})
+  t.equal(chunks, 2)
+
+  //Two indexes & three link targets
+  let all = await alice_happ.cells[0].call("testing_zome", "prefetch_span", {index: "test_index", from: yesterday.toISOString(), until: new Date().toISOString(), include_targets: true})
+  t.equal(all, 5)
+})
+
+orchestrator.registerScenario("test index entry at many times", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (24*60*60*1000); //1 day ago
+  var yesterday = new Date(now.getTime() - dateOffset);
+
+  //Entry created yesterday & updated today is indexed under both times
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "An updated index", created: yesterday.toISOString(), updated: now.toISOString()})
+  let indexes = await alice_happ.cells[0].call("testing_zome", "get_indexes_for_time_span", {index: "test_index", from: yesterday.toISOString(), until: new Date().toISOString()})
+  console.log("Got indexes", indexes);
+  t.equal(indexes.length, 2)
+  t.equal(indexes[0].links.length, 1)
+  t.equal(indexes[1].links.length, 1)
+  t.deepEqual(indexes[0].links[0].target, indexes[1].links[0].target)
+})
+
+orchestrator.registerScenario("test index entry retry", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (24*60*60*1000); //1 day ago
+  var yesterday = new Date(now.getTime() - dateOffset);
+
+  //Retrying with identical arguments does not make a second link
+  let first = await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A retried index", created: now.toISOString()})
+  t.equal(first, "Created")
+  let second = await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A retried index", created: now.toISOString()})
+  t.equal(second, "AlreadyExisted")
+
+  let indexes = await alice_happ.cells[0].call("testing_zome", "get_indexes_for_time_span", {index: "test_index", from: yesterday.toISOString(), until: new Date().toISOString(), link_tag: Buffer.from("test")})
+  console.log("Got indexes", indexes);
+  t.equal(indexes.length, 1)
+  t.equal(indexes[0].links.length, 1)
+})
+
+orchestrator.registerScenario("test reindex", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (24*60*60*1000); //1 day ago
+  var yesterday = new Date(now.getTime() - dateOffset);
+  var twoDaysAgo = new Date(now.getTime() - (2 * dateOffset));
+
+  //Entry indexed yesterday & moved to two days ago
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A moved index", created: yesterday.toISOString()})
+  let indexes = await alice_happ.cells[0].call("testing_zome", "get_indexes_for_time_span", {index: "test_index", from: twoDaysAgo.toISOString(), until: now.toISOString(), link_tag: Buffer.from("test")})
+  t.equal(indexes.length, 1)
+  let target = indexes[0].links[0].target
+  let moved = await alice_happ.cells[0].call("testing_zome", "reindex", {target: target, old_time: yesterday.toISOString(), new_time: twoDaysAgo.toISOString()})
+  t.equal(moved, "Created")
+
+  let live = await alice_happ.cells[0].call("testing_zome", "get_indexes_for_time_span", {index: "test_index", from: new Date(twoDaysAgo.getTime() - 60000).toISOString(), until: now.toISOString(), link_tag: Buffer.from("test")})
+  console.log("Got indexes after reindex", live);
+  let populated = live.filter((index) => index.links.length > 0)
+  t.equal(populated.length, 1)
+  t.equal(populated[0].links.length, 1)
+  t.deepEqual(populated[0].links[0].target, target)
+  t.deepEqual(populated[0].links[0].tag, indexes[0].links[0].tag)
+  t.ok(new Date(populated[0].index.from.secs * 1000) <= twoDaysAgo)
+
+  let when = await alice_happ.cells[0].call("testing_zome", "when_indexed", target)
+  t.equal(when.length, 1)
+})
+
+orchestrator.registerScenario("test spam limit", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  //Fill one index up to the spam limit (20) of the test DNA
+  for (let i = 0; i < 20; i++) {
+    await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index" + i, created: now.toISOString()})
+  }
+  try {
+    await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index over limit", created: now.toISOString()})
+    t.fail("Indexing beyond the spam limit should fail")
+  } catch (err) {
+    console.log("Got expected error", err);
+    t.ok(JSON.stringify(err).includes("link limit"))
+  }
+
+  //Other indexes are unaffected
+  var secondLater = new Date(now.getTime() + 1000);
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index over limit", created: secondLater.toISOString()})
+  let indexes = await alice_happ.cells[0].call("testing_zome", "get_indexes_for_time_span", {index: "test_index", from: now.toISOString(), until: new Date(now.getTime() + 2000).toISOString()})
+  t.equal(indexes.map((index) => index.links.length).reduce((a, b) => a + b, 0), 21)
+})
+
+orchestrator.registerScenario("test include deleted", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (24*60*60*1000); //1 day ago
+  var yesterday = new Date(now.getTime() - dateOffset);
+
+  //Index entries & remove one of them
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index", created: now.toISOString()})
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index2", created: yesterday.toISOString()})
+  let indexes = await alice_happ.cells[0].call("testing_zome", "get_indexes_for_time_span", {index: "test_index", from: yesterday.toISOString(), until: new Date().toISOString()})
+  t.equal(indexes.length, 2)
+  await alice_happ.cells[0].call("testing_zome", "remove_index", indexes[0].links[0].target)
+
+  //Deleted links are excluded by default
+  let live = await alice_happ.cells[0].call("testing_zome", "get_indexes_for_time_span", {index: "test_index", from: yesterday.toISOString(), until: new Date().toISOString()})
+  console.log("Got live indexes", live);
+  t.equal(live.map((index) => index.links.length).reduce((a, b) => a + b, 0), 1)
+
+  let all = await alice_happ.cells[0].call("testing_zome", "get_indexes_for_time_span", {index: "test_index", from: yesterday.toISOString(), until: new Date().toISOString(), include_deleted: true})
+  console.log("Got indexes including deleted", all);
+  t.equal(all.map((index) => index.links.length).reduce((a, b) => a + b, 0), 2)
+})
+
+orchestrator.registerScenario("test index time bounds", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  let empty = await alice_happ.cells[0].call("testing_zome", "index_time_bounds", "test_index")
+  t.equal(empty, null)
+
+  var dateOffset = (24*60*60*1000) * 400; //400 days ago
+  var lastYear = new Date(now.getTime() - dateOffset);
+
+  //Index entries spanning two years
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index", created: now.toISOString()})
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index2", created: lastYear.toISOString()})
+
+  let bounds = await alice_happ.cells[0].call("testing_zome", "index_time_bounds", "test_index")
+  console.log("Got bounds", bounds);
+  t.ok(new Date(bounds[0]).getTime() <= lastYear.getTime())
+  t.ok(lastYear.getTime() - new Date(bounds[0]).getTime() < 60*60*1000)
+  t.ok(new Date(bounds[1]).getTime() > now.getTime())
+  t.ok(new Date(bounds[1]).getTime() - now.getTime() < 60*60*1000)
+})
+
+orchestrator.registerScenario("test get recent indexes", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  //Index entries in three separate days
+  var day = 24*60*60*1000;
+  var yesterday = new Date(now.getTime() - day);
+  var twoDaysAgo = new Date(now.getTime() - (day * 2));
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index", created: twoDaysAgo.toISOString()})
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index2", created: now.toISOString()})
+  await alice_happ.cells[0].call("testing_zome", "index_entry", {title: "A test index3", created: yesterday.toISOString()})
+
+  let recent = await alice_happ.cells[0].call("testing_zome", "get_recent_indexes", {index: "test_index", chunk_count: 2})
+  console.log("Got recent indexes", recent);
+  t.equal(recent.length, 2)
+  t.ok(recent[0].index.from.secs > recent[1].index.from.secs)
+  t.ok(recent[0].index.from.secs * 1000 <= now.getTime())
+  t.ok(recent[1].index.from.secs * 1000 <= yesterday.getTime())
+
+  //Asking for more chunks than exist returns all populated chunks
+  let all = await alice_happ.cells[0].call("testing_zome", "get_recent_indexes", {index: "test_index", chunk_count: 10})
+  t.equal(all.length, 3)
+  t.ok(all[1].index.from.secs > all[2].index.from.secs)
+})
+
+orchestrator.registerScenario("test empty index", async (s, t) => {
+  const [alice] = await s.players([conductorConfig])
+  console.log("Init alice happ");
+  const [[alice_happ]] = await alice.installAgentsHapps(installation)
+
+  var dateOffset = (24*60*60*1000); //1 day ago
+  var yesterday = new Date(now.getTime() - dateOffset);
+
+  //Nothing has been indexed; every query should return an empty result rather than an error
+  let most_recent = await alice_happ.cells[0].call("testing_zome", "get_most_recent_indexes", {index: "test_index"})
+  t.equal(most_recent, null)
+  let recent = await alice_happ.cells[0].call("testing_zome", "get_recent_indexes", {index: "test_index", chunk_count: 3})
+  t.deepEqual(recent, [])
+  let current = await alice_happ.cells[0].call("testing_zome", "get_current_addresses", {index: "test_index"})
+  t.equal(current, null)
+  let current_or_recent = await alice_happ.cells[0].call("testing_zome", "get_current_or_recent_index", {index: "test_index"})
+  t.equal(current_or_recent, null)
+  let bounds = await alice_happ.cells[0].call("testing_zome", "index_time_bounds", "test_index")
+  t.equal(bounds, null)
+  let indexes = await alice_happ.cells[0].call("testing_zome", "get_indexes_for_time_span", {index: "test_index", from: yesterday.toISOString(), until: now.toISOString(), limit: 10})
+  t.deepEqual(indexes, [])
+  let loaded = await alice_happ.cells[0].call("testing_zome", "get_links_and_load_for_time_span", {index: "test_index", from: yesterday.toISOString(), until: now.toISOString(), limit: 10})
+  t.deepEqual(loaded, [])
+  let loaded_desc = await alice_happ.cells[0].call("testing_zome", "get_links_and_load_for_time_span", {index: "test_index", from: now.toISOString(), until: yesterday.toISOString(), limit: 10})
+  t.deepEqual(loaded_desc, [])
+})
+
 // orchestrator.registerScenario("test simple index", async (s, t) => {
 //   const [alice] = await s.players([conductorConfig])
 //   console.log("Init alice happ");